@@ -13,6 +13,7 @@ import {
 } from 'lucide-react';
 
 import { useBackend } from '@/app/context';
+import { formatStrikeError } from '@/backend';
 import {
   Card,
   CardContent,
@@ -108,14 +109,17 @@ export default function AdminPage() {
     setInputError('');
 
     try {
-      await actor.add_admin(principalToAdd);
+      const result = await actor.add_admin(principalToAdd);
+      if ('Err' in result) {
+        throw new Error(formatStrikeError(result.Err));
+      }
       await fetchAdmins();
       setAddDialogOpen(false);
       setNewAdminInput('');
       toast.success('Administrator added successfully');
     } catch (error) {
       console.error('Error adding admin:', error);
-      toast.success('Failed to add administrator');
+      toast.error(`Failed to add administrator: ${(error as Error).message}`);
     } finally {
       setIsLoading(false);
     }
@@ -126,7 +130,10 @@ export default function AdminPage() {
 
     setIsLoading(true);
     try {
-      await actor.remove_admin(selectedAdmin);
+      const result = await actor.remove_admin(selectedAdmin);
+      if ('Err' in result) {
+        throw new Error(formatStrikeError(result.Err));
+      }
       await fetchAdmins();
       setRemoveDialogOpen(false);
       setSelectedAdmin(null);
@@ -134,7 +141,9 @@ export default function AdminPage() {
       toast.success('Administrator removed successfully');
     } catch (error) {
       console.error('Error removing admin:', error);
-      toast.success('Failed to remove administrator');
+      toast.error(
+        `Failed to remove administrator: ${(error as Error).message}`,
+      );
     } finally {
       setIsLoading(false);
     }
@@ -1,6 +1,6 @@
 'use client';
 
-import React, { useState, useEffect, useCallback } from 'react';
+import { useState, useEffect, useCallback } from 'react';
 import { useBackend } from '@/app/context';
 import { Registry } from '@/backend/types';
 
@@ -48,20 +48,27 @@ export default function ManageRegistryPage() {
   const { actor } = useBackend();
 
   const [total, setTotal] = useState(0);
-  const [page, setPage] = useState(1);
+  // The cursor of each page up to the current one, the first page has none
+  const [cursors, setCursors] = useState<(string | undefined)[]>([undefined]);
+  const [nextCursor, setNextCursor] = useState<string | null>(null);
   const [pageSize] = useState(10);
+  const page = cursors.length;
 
-  const fetchRegistriesByStatus = async (status: StatusType) => {
+  const fetchRegistriesPage = async (
+    status: StatusType,
+    pageCursors: (string | undefined)[],
+  ) => {
     setIsFetchingRegistries(true);
     try {
       const data = await actor.get_registries({
         status,
-        pagination: { page: 1, pageSize },
+        cursor: pageCursors[pageCursors.length - 1],
+        limit: pageSize,
       });
-      if (data) {
-        setRegistries(data.items);
-        setTotal(data.total);
-      } else setRegistries([]);
+      setRegistries(data.items);
+      setTotal(data.total);
+      setNextCursor(data.nextCursor);
+      setCursors(pageCursors);
     } catch (error) {
       console.error('Error fetching registries:', error);
       setRegistries([]);
@@ -70,6 +77,9 @@ export default function ManageRegistryPage() {
     }
   };
 
+  const fetchRegistriesByStatus = (status: StatusType) =>
+    fetchRegistriesPage(status, [undefined]);
+
   useEffect(() => {
     fetchRegistriesByStatus(selectedStatus);
   }, [selectedStatus]);
@@ -168,25 +178,6 @@ export default function ManageRegistryPage() {
     }
   };
 
-  const fetchRegistriesPage = async (status: StatusType, page: number) => {
-    setIsFetchingRegistries(true);
-    try {
-      const data = await actor.get_registries({
-        status,
-        pagination: { page, pageSize },
-      });
-      if (data) {
-        setRegistries(data.items);
-        setTotal(data.total);
-      } else setRegistries([]);
-    } catch (error) {
-      console.error('Error fetching registries:', error);
-      setRegistries([]);
-    } finally {
-      setIsFetchingRegistries(false);
-    }
-  };
-
   return (
     <div className="container mx-auto px-4 py-8 max-w-7xl min-h-[calc(100vh-200px)]">
       <h1 className="text-3xl font-bold mb-6">Manage Registries</h1>
@@ -277,64 +268,28 @@ export default function ManageRegistryPage() {
                   <Button
                     variant="outline"
                     size="sm"
-                    onClick={() => {
-                      if (page > 1) {
-                        setPage(page - 1);
-                        fetchRegistriesPage(selectedStatus, page - 1);
-                      }
-                    }}
+                    onClick={() =>
+                      fetchRegistriesPage(selectedStatus, cursors.slice(0, -1))
+                    }
                     disabled={page === 1 || isFetchingRegistries}
                   >
                     Previous
                   </Button>
-                  <div className="flex items-center">
-                    {Array.from(
-                      { length: Math.ceil(total / pageSize) },
-                      (_, i) => i + 1,
-                    )
-                      .filter((p) => {
-                        return (
-                          p === 1 ||
-                          p === Math.ceil(total / pageSize) ||
-                          (p >= page - 1 && p <= page + 1)
-                        );
-                      })
-                      .map((p, i, arr) => (
-                        <React.Fragment key={p}>
-                          {i > 0 && arr[i - 1] !== p - 1 && (
-                            <span className="px-2 text-muted-foreground">
-                              ...
-                            </span>
-                          )}
-                          <Button
-                            variant={p === page ? 'default' : 'outline'}
-                            size="sm"
-                            className="w-9 h-9"
-                            style={{ color: p === page ? 'white' : 'black' }}
-                            onClick={() => {
-                              setPage(p);
-                              fetchRegistriesPage(selectedStatus, p);
-                            }}
-                            disabled={isFetchingRegistries}
-                          >
-                            {p}
-                          </Button>
-                        </React.Fragment>
-                      ))}
-                  </div>
+                  <span className="px-2 text-sm text-muted-foreground">
+                    Page {page} of {Math.ceil(total / pageSize)}
+                  </span>
                   <Button
                     variant="outline"
                     size="sm"
                     onClick={() => {
-                      if (page < Math.ceil(total / pageSize)) {
-                        setPage(page + 1);
-                        fetchRegistriesPage(selectedStatus, page + 1);
+                      if (nextCursor) {
+                        fetchRegistriesPage(selectedStatus, [
+                          ...cursors,
+                          nextCursor,
+                        ]);
                       }
                     }}
-                    disabled={
-                      page >= Math.ceil(total / pageSize) ||
-                      isFetchingRegistries
-                    }
+                    disabled={!nextCursor || isFetchingRegistries}
                   >
                     Next
                   </Button>
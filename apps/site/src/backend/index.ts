@@ -11,7 +11,8 @@ import {
 import {
   _SERVICE,
   idlFactory,
-  Pagination,
+  SortOrder,
+  StrikeError,
   StrikeStatus,
 } from './strike_backend.did.js';
 import { Principal } from '@dfinity/principal';
@@ -40,6 +41,23 @@ export declare interface CreateActorOptions {
   actorOptions?: ActorConfig;
 }
 
+/** The message of an error of the backend, the same as its `Display` in the canister */
+export const formatStrikeError = (err: StrikeError): string => {
+  if ('NotFound' in err) return 'Not found';
+  if ('Unauthorized' in err) return 'Unauthorized';
+  if ('InvalidField' in err)
+    return `${err.InvalidField.field} ${err.InvalidField.reason}`;
+  if ('AlreadyFinalized' in err) return 'Canister already trusted or blocked';
+  if ('AlreadyExists' in err) return 'Already exists';
+  if ('InvalidStatus' in err)
+    return `Not allowed while the canister is ${Object.keys(err.InvalidStatus.status)[0]}`;
+  if ('RateLimited' in err)
+    return `Rate limited, retry after ${err.RateLimited.retry_after} seconds`;
+  if ('CallFailed' in err)
+    return `Call to ${err.CallFailed.canister_id.toText()} failed: ${err.CallFailed.reason}`;
+  return err.Unavailable.reason;
+};
+
 export const createRawActor = (
   canisterId: string | Principal,
   options: CreateActorOptions = {},
@@ -91,11 +109,12 @@ export const createActor = (canisterId, options = {}) => {
         project_name: args.projectName,
         description: args.description,
         website_url: [args.strikeCardLink],
+        controller: [],
       });
       if ('Ok' in result) {
         return { success: true };
       } else {
-        return { success: false };
+        return { success: false, error: formatStrikeError(result.Err) };
       }
     },
     get_strike_by_canister_id: async (args: {
@@ -117,7 +136,10 @@ export const createActor = (canisterId, options = {}) => {
     },
     get_registries: async (args: {
       status: 'Submitted' | 'Trusted' | 'Blocked' | 'All';
-      pagination: Pagination;
+      // The `nextCursor` of the previous page, none for the first page
+      cursor?: string;
+      limit: number;
+      order?: SortOrder;
     }) => {
       const statusObj: [] | [StrikeStatus] =
         args.status === 'All'
@@ -130,16 +152,25 @@ export const createActor = (canisterId, options = {}) => {
 
       const result = await actor.get_registries({
         status: statusObj,
-        pagination: args.pagination,
+        order: args.order ? [args.order] : [],
+        pagination: {
+          start_after: args.cursor ? [args.cursor] : [],
+          limit: args.limit,
+        },
       });
+      if ('Err' in result) {
+        throw new Error(formatStrikeError(result.Err));
+      }
       return {
-        total: result.total,
-        items: result.items.map((item) => asRegistry(item)),
+        total: result.Ok.total,
+        items: result.Ok.items.map((item) => asRegistry(item)),
+        nextCursor: result.Ok.next_cursor[0] ?? null,
       };
     },
     update_registry_status: async (args: {
       canisterId: Principal | string;
       status: 'Submitted' | 'Trusted' | 'Blocked';
+      reason?: string;
     }) => {
       const result = await actor.update_registry_status({
         canister_id:
@@ -152,12 +183,13 @@ export const createActor = (canisterId, options = {}) => {
             : args.status === 'Submitted'
               ? { Submitted: null }
               : { Trusted: null },
+        reason: args.reason ? [args.reason] : [],
       });
 
       if ('Ok' in result) {
         return { success: true };
       } else {
-        return { success: false, error: result.Err };
+        return { success: false, error: formatStrikeError(result.Err) };
       }
     },
   };
@@ -3,6 +3,7 @@ import type { ActorMethod } from '@dfinity/agent';
 import type { IDL } from '@dfinity/candid';
 
 export interface AddRegistryParams {
+  'controller' : [] | [Principal],
   'website_url' : [] | [string],
   'twitter' : [] | [string],
   'name' : string,
@@ -12,27 +13,326 @@ export interface AddRegistryParams {
   'project_name' : string,
   'telegram' : [] | [string],
 }
+export interface Appeal {
+  'id' : bigint,
+  'status' : AppealStatus,
+  'statement' : string,
+  'canister_id' : Principal,
+  'created_at' : bigint,
+  'resolution' : [] | [AppealResolution],
+  'appellant' : Principal,
+}
+export interface AppealResolution {
+  'resolved_at' : bigint,
+  'resolved_by' : Principal,
+  'reason' : [] | [string],
+}
+export type AppealStatus = { 'Open' : null } |
+  { 'Closed' : null } |
+  { 'Rejected' : null } |
+  { 'Accepted' : null };
+export type AuditAction = { 'ImportSnapshot' : null } |
+  { 'UpdateRegistryDetails' : null } |
+  { 'CloseAppeal' : null } |
+  { 'ApproveRevision' : null } |
+  { 'PinModuleHash' : null } |
+  { 'RevokeRole' : null } |
+  { 'ReviewVote' : null } |
+  { 'RejectAppeal' : null } |
+  { 'PurgeRegistry' : null } |
+  { 'UpdateRegistryStatus' : null } |
+  { 'ApproveSubmitter' : null } |
+  { 'RejectRevision' : null } |
+  { 'SetFeatureToggles' : null } |
+  { 'ForfeitDeposit' : null } |
+  { 'SetMaxBatchLookup' : null } |
+  { 'SetSubmissionLimits' : null } |
+  { 'RefundDeposit' : null } |
+  { 'SetReviewQuorum' : null } |
+  { 'GrantRole' : null } |
+  { 'AcceptAppeal' : null } |
+  { 'SetDepositConfig' : null } |
+  { 'AddRegistry' : null } |
+  { 'AppealBlock' : null } |
+  { 'WithdrawRegistry' : null };
+export interface AuditEntry {
+  'id' : bigint,
+  'old_value' : [] | [AuditValue],
+  'principal' : [] | [Principal],
+  'action' : AuditAction,
+  'canister_id' : [] | [Principal],
+  'timestamp' : bigint,
+  'new_value' : [] | [AuditValue],
+  'caller' : Principal,
+  'reason' : [] | [string],
+}
+export type AuditValue = { 'FeatureToggles' : FeatureToggles } |
+  { 'Quorum' : number } |
+  { 'MaxBatchLookup' : number } |
+  { 'Role' : Role } |
+  { 'Status' : StrikeStatus } |
+  { 'DepositConfig' : DepositConfig } |
+  { 'Tombstone' : Tombstone } |
+  { 'SubmissionLimits' : SubmissionLimits };
+export interface CertifiedStrike {
+  'certificate' : Uint8Array | number[],
+  'witness' : Uint8Array | number[],
+  'registry' : [] | [StrikeRegistry],
+}
+export interface CursorPagination {
+  'start_after' : [] | [string],
+  'limit' : number,
+}
+export interface DailyStats {
+  'day_start' : bigint,
+  'submissions' : bigint,
+  'blocks' : bigint,
+  'approvals' : bigint,
+}
+export interface Deposit {
+  'status' : DepositStatus,
+  'depositor' : Principal,
+  'block_index' : bigint,
+  'canister_id' : Principal,
+  'ledger' : Principal,
+  'refund_attempt' : [] | [TransferAttempt],
+  'amount' : bigint,
+  'collected_at' : bigint,
+}
+export interface DepositConfig {
+  'ledger' : Principal,
+  'refund_policy' : RefundPolicy,
+  'amount' : bigint,
+}
+export type DepositStatus = { 'Refunding' : null } |
+  { 'Collecting' : null } |
+  { 'Refunded' : { 'block_index' : bigint } } |
+  { 'Held' : null } |
+  { 'Forfeited' : null } |
+  { 'Pending' : null };
+export interface FeatureToggles {
+  'appeals' : boolean,
+  'module_hash_monitor' : boolean,
+  'submissions' : boolean,
+  'reports' : boolean,
+}
+export interface GetAuditLogParams {
+  'actor' : [] | [Principal],
+  'pagination' : CursorPagination,
+  'canister_id' : [] | [Principal],
+}
 export interface GetRegistriesParams {
   'status' : [] | [StrikeStatus],
-  'pagination' : Pagination,
+  'order' : [] | [SortOrder],
+  'pagination' : CursorPagination,
+}
+export interface HttpRequest {
+  'url' : string,
+  'method' : string,
+  'body' : Uint8Array | number[],
+  'headers' : Array<[string, string]>,
+  'certificate_version' : [] | [number],
+}
+export interface HttpResponse {
+  'body' : Uint8Array | number[],
+  'headers' : Array<[string, string]>,
+  'upgrade' : [] | [boolean],
+  'status_code' : number,
+}
+export interface ImportProgress {
+  'total_chunks' : number,
+  'committed_chunks' : number,
+  'manifest_hash' : string,
+}
+export interface MonitorRuns {
+  'last_started_at' : [] | [bigint],
+  'last_completed_at' : [] | [bigint],
+  'demoted' : bigint,
+}
+export interface MyRegistration {
+  'pending_revision' : [] | [Revision],
+  'review_notes' : Array<ReviewNote>,
+  'registry' : StrikeRegistry,
 }
 export interface PaginatedResponse {
   'total' : number,
+  'next_cursor' : [] | [string],
+  'items' : Array<AuditEntry>,
+}
+export interface PaginatedResponse_1 {
+  'total' : number,
+  'next_cursor' : [] | [string],
+  'items' : Array<MyRegistration>,
+}
+export interface PaginatedResponse_2 {
+  'total' : number,
+  'next_cursor' : [] | [string],
+  'items' : Array<Appeal>,
+}
+export interface PaginatedResponse_3 {
+  'total' : number,
+  'next_cursor' : [] | [string],
+  'items' : Array<Review>,
+}
+export interface PaginatedResponse_4 {
+  'total' : number,
+  'next_cursor' : [] | [string],
+  'items' : Array<Revision>,
+}
+export interface PaginatedResponse_5 {
+  'total' : number,
+  'next_cursor' : [] | [string],
   'items' : Array<StrikeRegistry>,
 }
-export interface Pagination { 'page' : number, 'pageSize' : number }
+export interface PaginatedResponse_6 {
+  'total' : number,
+  'next_cursor' : [] | [string],
+  'items' : Array<ReportQueueEntry>,
+}
+export type RefundPolicy = { 'Never' : null } |
+  { 'OnTrustedOrWithdrawn' : null } |
+  { 'OnTrusted' : null };
+export interface RegistryDetails {
+  'website_url' : [] | [string],
+  'twitter' : [] | [string],
+  'name' : [] | [string],
+  'description' : [] | [string],
+  'email' : [] | [string],
+  'project_name' : [] | [string],
+  'telegram' : [] | [string],
+}
+export interface RegistryStats {
+  'status_counts' : Array<StatusCount>,
+  'median_review_time_secs' : [] | [bigint],
+  'reviewed' : bigint,
+  'daily' : Array<DailyStats>,
+}
+export interface Report {
+  'evidence_url' : [] | [string],
+  'note' : [] | [string],
+  'canister_id' : Principal,
+  'created_at' : bigint,
+  'category' : ReportCategory,
+  'reporter' : Principal,
+}
+export type ReportCategory = { 'Scam' : null } |
+  { 'Phishing' : null } |
+  { 'Drainer' : null } |
+  { 'Other' : null } |
+  { 'Impersonation' : null };
+export interface ReportQueueEntry {
+  'status' : [] | [StrikeStatus],
+  'report_count' : number,
+  'canister_id' : Principal,
+}
 export type Result = { 'Ok' : null } |
-  { 'Err' : string };
+  { 'Err' : StrikeError };
+export type Result_1 = { 'Ok' : ImportProgress } |
+  { 'Err' : StrikeError };
+export type Result_10 = { 'Ok' : PaginatedResponse_6 } |
+  { 'Err' : StrikeError };
+export type Result_11 = { 'Ok' : Array<StrikeSummary> } |
+  { 'Err' : StrikeError };
+export type Result_12 = { 'Ok' : bigint } |
+  { 'Err' : StrikeError };
+export type Result_2 = { 'Ok' : Array<Appeal> } |
+  { 'Err' : StrikeError };
+export type Result_3 = { 'Ok' : PaginatedResponse } |
+  { 'Err' : StrikeError };
+export type Result_4 = { 'Ok' : CertifiedStrike } |
+  { 'Err' : StrikeError };
+export type Result_5 = { 'Ok' : PaginatedResponse_1 } |
+  { 'Err' : StrikeError };
+export type Result_6 = { 'Ok' : PaginatedResponse_2 } |
+  { 'Err' : StrikeError };
+export type Result_7 = { 'Ok' : PaginatedResponse_3 } |
+  { 'Err' : StrikeError };
+export type Result_8 = { 'Ok' : PaginatedResponse_4 } |
+  { 'Err' : StrikeError };
+export type Result_9 = { 'Ok' : PaginatedResponse_5 } |
+  { 'Err' : StrikeError };
+export interface Review {
+  'escalated' : boolean,
+  'votes' : Array<ReviewVote>,
+  'canister_id' : Principal,
+}
+export interface ReviewNote {
+  'status' : StrikeStatus,
+  'timestamp' : bigint,
+  'reason' : string,
+}
+export interface ReviewVote {
+  'status' : StrikeStatus,
+  'timestamp' : bigint,
+  'reviewer' : Principal,
+  'reason' : [] | [string],
+}
+export interface Revision {
+  'canister_id' : Principal,
+  'details' : RegistryDetails,
+  'submitted_at' : bigint,
+  'submitted_by' : Principal,
+}
+export type Role = { 'Support' : null } |
+  { 'Reviewer' : null } |
+  { 'Admin' : null } |
+  { 'Owner' : null };
+export interface RoleAssignment { 'principal' : Principal, 'role' : Role }
+export interface SearchFilters { 'status' : [] | [StrikeStatus] }
+export interface SnapshotChunk {
+  'data' : Uint8Array | number[],
+  'checksum' : string,
+  'index' : number,
+}
+export interface SnapshotCursor {
+  'start_after' : [] | [Principal],
+  'section' : SnapshotSection,
+  'created_at' : bigint,
+  'index' : number,
+  'chunk_checksums' : Array<string>,
+  'registry_count' : bigint,
+  'role_count' : bigint,
+}
+export interface SnapshotExport {
+  'chunk' : SnapshotChunk,
+  'next' : [] | [SnapshotCursor],
+  'manifest_hash' : [] | [string],
+  'manifest' : [] | [SnapshotManifest],
+}
+export interface SnapshotManifest {
+  'created_at' : bigint,
+  'chunk_checksums' : Array<string>,
+  'registry_count' : bigint,
+  'role_count' : bigint,
+  'format' : number,
+}
+export type SnapshotSection = { 'Roles' : null } |
+  { 'Registry' : null };
+export type SortOrder = { 'CreatedAtAsc' : null } |
+  { 'CreatedAtDesc' : null } |
+  { 'ProjectNameAsc' : null };
+export interface StatusCount { 'status' : StrikeStatus, 'count' : bigint }
+export type StrikeError = { 'AlreadyFinalized' : null } |
+  { 'CallFailed' : { 'canister_id' : Principal, 'reason' : string } } |
+  { 'InvalidStatus' : { 'status' : StrikeStatus } } |
+  { 'InvalidField' : { 'field' : string, 'reason' : string } } |
+  { 'NotFound' : null } |
+  { 'Unauthorized' : null } |
+  { 'AlreadyExists' : null } |
+  { 'RateLimited' : { 'retry_after' : bigint } } |
+  { 'Unavailable' : { 'reason' : string } };
 export interface StrikeRegistry {
   'status' : StrikeStatus,
   'website_url' : [] | [string],
   'twitter' : [] | [string],
   'name' : string,
+  'report_count' : [] | [number],
   'canister_id' : Principal,
   'added_by' : Principal,
   'description' : string,
   'created_at' : bigint,
   'email' : string,
+  'status_reason' : [] | [string],
   'module_hash' : [] | [string],
   'project_name' : string,
   'telegram' : [] | [string],
@@ -40,19 +340,111 @@ export interface StrikeRegistry {
 export type StrikeStatus = { 'Blocked' : null } |
   { 'Submitted' : null } |
   { 'Trusted' : null };
+export interface StrikeSummary {
+  'status' : [] | [StrikeStatus],
+  'canister_id' : Principal,
+  'module_hash' : [] | [string],
+}
+export interface SubmissionLimits {
+  'per_principal_window_secs' : bigint,
+  'per_principal' : number,
+  'global' : number,
+  'global_window_secs' : bigint,
+}
+export interface SubmitterApproval {
+  'controller' : Principal,
+  'submitter' : Principal,
+  'approved_at' : bigint,
+}
+export interface Tombstone {
+  'status' : StrikeStatus,
+  'website_url' : [] | [string],
+  'added_by' : Principal,
+  'created_at' : bigint,
+  'module_hash' : [] | [string],
+  'project_name' : string,
+}
+export interface TransferAttempt { 'fee' : bigint, 'created_at_time' : bigint }
+export interface UpdateRegistryDetailsParams {
+  'canister_id' : Principal,
+  'details' : RegistryDetails,
+}
 export interface UpdateRegistryStatusParams {
   'status' : StrikeStatus,
   'canister_id' : Principal,
+  'reason' : [] | [string],
 }
 export interface _SERVICE {
+  'accept_appeal' : ActorMethod<
+    [Principal, StrikeStatus, [] | [string]],
+    Result
+  >,
   'add_admin' : ActorMethod<[Principal], Result>,
   'add_registry' : ActorMethod<[AddRegistryParams], Result>,
+  'appeal_block' : ActorMethod<[Principal, string], Result>,
+  'approve_revision' : ActorMethod<[Principal], Result>,
+  'approve_submitter' : ActorMethod<[Principal, Principal], Result>,
+  'cancel_import' : ActorMethod<[], Result>,
+  'export_snapshot' : ActorMethod<[[] | [SnapshotCursor]], SnapshotExport>,
+  'finalize_import' : ActorMethod<[SnapshotManifest, string], Result_1>,
   'get_admins' : ActorMethod<[], Array<Principal>>,
-  'get_registries' : ActorMethod<[GetRegistriesParams], PaginatedResponse>,
+  'get_appeals' : ActorMethod<[Principal], Result_2>,
+  'get_audit_log' : ActorMethod<[GetAuditLogParams], Result_3>,
+  'get_certified_strike_by_canister_id' : ActorMethod<[Principal], Result_4>,
+  'get_deposit' : ActorMethod<[Principal], [] | [Deposit]>,
+  'get_deposit_config' : ActorMethod<[], [] | [DepositConfig]>,
+  'get_feature_toggles' : ActorMethod<[], FeatureToggles>,
+  'get_max_batch_lookup' : ActorMethod<[], number>,
+  'get_module_hash_monitor_runs' : ActorMethod<[], MonitorRuns>,
+  'get_my_registrations' : ActorMethod<[CursorPagination], Result_5>,
+  'get_open_appeals' : ActorMethod<[CursorPagination], Result_6>,
+  'get_pending_reviews' : ActorMethod<[CursorPagination], Result_7>,
+  'get_pending_revision' : ActorMethod<[Principal], [] | [Revision]>,
+  'get_pending_revisions' : ActorMethod<[CursorPagination], Result_8>,
+  'get_registries' : ActorMethod<[GetRegistriesParams], Result_9>,
+  'get_report_queue' : ActorMethod<[CursorPagination], Result_10>,
+  'get_reports' : ActorMethod<[Principal], Array<Report>>,
+  'get_review_quorum' : ActorMethod<[], number>,
+  'get_stats' : ActorMethod<[], RegistryStats>,
   'get_strike_by_canister_id' : ActorMethod<[Principal], [] | [StrikeRegistry]>,
+  'get_strikes_by_canister_ids' : ActorMethod<[Array<Principal>], Result_11>,
+  'get_strikes_by_canister_ids_composite' : ActorMethod<
+    [Array<Principal>],
+    Result_11
+  >,
+  'get_submission_limits' : ActorMethod<[], SubmissionLimits>,
+  'get_submitter_approval' : ActorMethod<[Principal], [] | [SubmitterApproval]>,
+  'grant_role' : ActorMethod<[Principal, Role], Result>,
+  'http_request' : ActorMethod<[HttpRequest], HttpResponse>,
+  'http_request_update' : ActorMethod<[HttpRequest], HttpResponse>,
+  'import_snapshot_chunk' : ActorMethod<[SnapshotChunk], Result>,
   'is_admin' : ActorMethod<[Principal], boolean>,
+  'list_roles' : ActorMethod<[], Array<RoleAssignment>>,
+  'purge_registry' : ActorMethod<[Principal, string], Result>,
+  'refund_deposit' : ActorMethod<[Principal], Result_12>,
+  'reject_appeal' : ActorMethod<[Principal, string], Result>,
+  'reject_revision' : ActorMethod<[Principal, [] | [string]], Result>,
   'remove_admin' : ActorMethod<[Principal], Result>,
+  'report_canister' : ActorMethod<
+    [Principal, ReportCategory, [] | [string], [] | [string]],
+    Result
+  >,
+  'revoke_role' : ActorMethod<[Principal], Result>,
+  'search_registries' : ActorMethod<
+    [string, SearchFilters, CursorPagination],
+    Result_9
+  >,
+  'set_deposit_config' : ActorMethod<[[] | [DepositConfig]], Result>,
+  'set_feature_toggles' : ActorMethod<[FeatureToggles], Result>,
+  'set_max_batch_lookup' : ActorMethod<[number], Result>,
+  'set_review_quorum' : ActorMethod<[number], Result>,
+  'set_submission_limits' : ActorMethod<[SubmissionLimits], Result>,
+  'update_registry_details' : ActorMethod<
+    [UpdateRegistryDetailsParams],
+    Result
+  >,
   'update_registry_status' : ActorMethod<[UpdateRegistryStatusParams], Result>,
+  'withdraw_registry' : ActorMethod<[Principal, [] | [string]], Result>,
 }
 export declare const idlFactory: IDL.InterfaceFactory;
 export declare const init: (args: { IDL: typeof IDL }) => IDL.Type[];
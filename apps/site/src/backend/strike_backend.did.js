@@ -1,6 +1,26 @@
 export const idlFactory = ({ IDL }) => {
-  const Result = IDL.Variant({ 'Ok' : IDL.Null, 'Err' : IDL.Text });
+  const StrikeStatus = IDL.Variant({
+    'Blocked' : IDL.Null,
+    'Submitted' : IDL.Null,
+    'Trusted' : IDL.Null,
+  });
+  const StrikeError = IDL.Variant({
+    'AlreadyFinalized' : IDL.Null,
+    'CallFailed' : IDL.Record({
+      'canister_id' : IDL.Principal,
+      'reason' : IDL.Text,
+    }),
+    'InvalidStatus' : IDL.Record({ 'status' : StrikeStatus }),
+    'InvalidField' : IDL.Record({ 'field' : IDL.Text, 'reason' : IDL.Text }),
+    'NotFound' : IDL.Null,
+    'Unauthorized' : IDL.Null,
+    'AlreadyExists' : IDL.Null,
+    'RateLimited' : IDL.Record({ 'retry_after' : IDL.Nat64 }),
+    'Unavailable' : IDL.Record({ 'reason' : IDL.Text }),
+  });
+  const Result = IDL.Variant({ 'Ok' : IDL.Null, 'Err' : StrikeError });
   const AddRegistryParams = IDL.Record({
+    'controller' : IDL.Opt(IDL.Principal),
     'website_url' : IDL.Opt(IDL.Text),
     'twitter' : IDL.Opt(IDL.Text),
     'name' : IDL.Text,
@@ -10,59 +30,516 @@ export const idlFactory = ({ IDL }) => {
     'project_name' : IDL.Text,
     'telegram' : IDL.Opt(IDL.Text),
   });
-  const StrikeStatus = IDL.Variant({
-    'Blocked' : IDL.Null,
-    'Submitted' : IDL.Null,
-    'Trusted' : IDL.Null,
+  const SnapshotSection = IDL.Variant({
+    'Roles' : IDL.Null,
+    'Registry' : IDL.Null,
   });
-  const Pagination = IDL.Record({ 'page' : IDL.Nat32, 'pageSize' : IDL.Nat32 });
-  const GetRegistriesParams = IDL.Record({
-    'status' : IDL.Opt(StrikeStatus),
-    'pagination' : Pagination,
+  const SnapshotCursor = IDL.Record({
+    'start_after' : IDL.Opt(IDL.Principal),
+    'section' : SnapshotSection,
+    'created_at' : IDL.Nat64,
+    'index' : IDL.Nat32,
+    'chunk_checksums' : IDL.Vec(IDL.Text),
+    'registry_count' : IDL.Nat64,
+    'role_count' : IDL.Nat64,
+  });
+  const SnapshotChunk = IDL.Record({
+    'data' : IDL.Vec(IDL.Nat8),
+    'checksum' : IDL.Text,
+    'index' : IDL.Nat32,
+  });
+  const SnapshotManifest = IDL.Record({
+    'created_at' : IDL.Nat64,
+    'chunk_checksums' : IDL.Vec(IDL.Text),
+    'registry_count' : IDL.Nat64,
+    'role_count' : IDL.Nat64,
+    'format' : IDL.Nat32,
+  });
+  const SnapshotExport = IDL.Record({
+    'chunk' : SnapshotChunk,
+    'next' : IDL.Opt(SnapshotCursor),
+    'manifest_hash' : IDL.Opt(IDL.Text),
+    'manifest' : IDL.Opt(SnapshotManifest),
+  });
+  const ImportProgress = IDL.Record({
+    'total_chunks' : IDL.Nat32,
+    'committed_chunks' : IDL.Nat32,
+    'manifest_hash' : IDL.Text,
+  });
+  const Result_1 = IDL.Variant({ 'Ok' : ImportProgress, 'Err' : StrikeError });
+  const AppealStatus = IDL.Variant({
+    'Open' : IDL.Null,
+    'Closed' : IDL.Null,
+    'Rejected' : IDL.Null,
+    'Accepted' : IDL.Null,
+  });
+  const AppealResolution = IDL.Record({
+    'resolved_at' : IDL.Nat64,
+    'resolved_by' : IDL.Principal,
+    'reason' : IDL.Opt(IDL.Text),
+  });
+  const Appeal = IDL.Record({
+    'id' : IDL.Nat64,
+    'status' : AppealStatus,
+    'statement' : IDL.Text,
+    'canister_id' : IDL.Principal,
+    'created_at' : IDL.Nat64,
+    'resolution' : IDL.Opt(AppealResolution),
+    'appellant' : IDL.Principal,
+  });
+  const Result_2 = IDL.Variant({ 'Ok' : IDL.Vec(Appeal), 'Err' : StrikeError });
+  const CursorPagination = IDL.Record({
+    'start_after' : IDL.Opt(IDL.Text),
+    'limit' : IDL.Nat32,
+  });
+  const GetAuditLogParams = IDL.Record({
+    'actor' : IDL.Opt(IDL.Principal),
+    'pagination' : CursorPagination,
+    'canister_id' : IDL.Opt(IDL.Principal),
+  });
+  const FeatureToggles = IDL.Record({
+    'appeals' : IDL.Bool,
+    'module_hash_monitor' : IDL.Bool,
+    'submissions' : IDL.Bool,
+    'reports' : IDL.Bool,
+  });
+  const Role = IDL.Variant({
+    'Support' : IDL.Null,
+    'Reviewer' : IDL.Null,
+    'Admin' : IDL.Null,
+    'Owner' : IDL.Null,
+  });
+  const RefundPolicy = IDL.Variant({
+    'Never' : IDL.Null,
+    'OnTrustedOrWithdrawn' : IDL.Null,
+    'OnTrusted' : IDL.Null,
+  });
+  const DepositConfig = IDL.Record({
+    'ledger' : IDL.Principal,
+    'refund_policy' : RefundPolicy,
+    'amount' : IDL.Nat,
+  });
+  const Tombstone = IDL.Record({
+    'status' : StrikeStatus,
+    'website_url' : IDL.Opt(IDL.Text),
+    'added_by' : IDL.Principal,
+    'created_at' : IDL.Nat64,
+    'module_hash' : IDL.Opt(IDL.Text),
+    'project_name' : IDL.Text,
+  });
+  const SubmissionLimits = IDL.Record({
+    'per_principal_window_secs' : IDL.Nat64,
+    'per_principal' : IDL.Nat32,
+    'global' : IDL.Nat32,
+    'global_window_secs' : IDL.Nat64,
+  });
+  const AuditValue = IDL.Variant({
+    'FeatureToggles' : FeatureToggles,
+    'Quorum' : IDL.Nat32,
+    'MaxBatchLookup' : IDL.Nat32,
+    'Role' : Role,
+    'Status' : StrikeStatus,
+    'DepositConfig' : DepositConfig,
+    'Tombstone' : Tombstone,
+    'SubmissionLimits' : SubmissionLimits,
+  });
+  const AuditAction = IDL.Variant({
+    'ImportSnapshot' : IDL.Null,
+    'UpdateRegistryDetails' : IDL.Null,
+    'CloseAppeal' : IDL.Null,
+    'ApproveRevision' : IDL.Null,
+    'PinModuleHash' : IDL.Null,
+    'RevokeRole' : IDL.Null,
+    'ReviewVote' : IDL.Null,
+    'RejectAppeal' : IDL.Null,
+    'PurgeRegistry' : IDL.Null,
+    'UpdateRegistryStatus' : IDL.Null,
+    'ApproveSubmitter' : IDL.Null,
+    'RejectRevision' : IDL.Null,
+    'SetFeatureToggles' : IDL.Null,
+    'ForfeitDeposit' : IDL.Null,
+    'SetMaxBatchLookup' : IDL.Null,
+    'SetSubmissionLimits' : IDL.Null,
+    'RefundDeposit' : IDL.Null,
+    'SetReviewQuorum' : IDL.Null,
+    'GrantRole' : IDL.Null,
+    'AcceptAppeal' : IDL.Null,
+    'SetDepositConfig' : IDL.Null,
+    'AddRegistry' : IDL.Null,
+    'AppealBlock' : IDL.Null,
+    'WithdrawRegistry' : IDL.Null,
+  });
+  const AuditEntry = IDL.Record({
+    'id' : IDL.Nat64,
+    'old_value' : IDL.Opt(AuditValue),
+    'principal' : IDL.Opt(IDL.Principal),
+    'action' : AuditAction,
+    'canister_id' : IDL.Opt(IDL.Principal),
+    'timestamp' : IDL.Nat64,
+    'new_value' : IDL.Opt(AuditValue),
+    'caller' : IDL.Principal,
+    'reason' : IDL.Opt(IDL.Text),
+  });
+  const PaginatedResponse = IDL.Record({
+    'total' : IDL.Nat32,
+    'next_cursor' : IDL.Opt(IDL.Text),
+    'items' : IDL.Vec(AuditEntry),
+  });
+  const Result_3 = IDL.Variant({
+    'Ok' : PaginatedResponse,
+    'Err' : StrikeError,
   });
   const StrikeRegistry = IDL.Record({
     'status' : StrikeStatus,
     'website_url' : IDL.Opt(IDL.Text),
     'twitter' : IDL.Opt(IDL.Text),
     'name' : IDL.Text,
+    'report_count' : IDL.Opt(IDL.Nat32),
     'canister_id' : IDL.Principal,
     'added_by' : IDL.Principal,
     'description' : IDL.Text,
     'created_at' : IDL.Nat64,
     'email' : IDL.Text,
+    'status_reason' : IDL.Opt(IDL.Text),
     'module_hash' : IDL.Opt(IDL.Text),
     'project_name' : IDL.Text,
     'telegram' : IDL.Opt(IDL.Text),
   });
-  const PaginatedResponse = IDL.Record({
+  const CertifiedStrike = IDL.Record({
+    'certificate' : IDL.Vec(IDL.Nat8),
+    'witness' : IDL.Vec(IDL.Nat8),
+    'registry' : IDL.Opt(StrikeRegistry),
+  });
+  const Result_4 = IDL.Variant({ 'Ok' : CertifiedStrike, 'Err' : StrikeError });
+  const DepositStatus = IDL.Variant({
+    'Refunding' : IDL.Null,
+    'Collecting' : IDL.Null,
+    'Refunded' : IDL.Record({ 'block_index' : IDL.Nat }),
+    'Held' : IDL.Null,
+    'Forfeited' : IDL.Null,
+    'Pending' : IDL.Null,
+  });
+  const TransferAttempt = IDL.Record({
+    'fee' : IDL.Nat,
+    'created_at_time' : IDL.Nat64,
+  });
+  const Deposit = IDL.Record({
+    'status' : DepositStatus,
+    'depositor' : IDL.Principal,
+    'block_index' : IDL.Nat,
+    'canister_id' : IDL.Principal,
+    'ledger' : IDL.Principal,
+    'refund_attempt' : IDL.Opt(TransferAttempt),
+    'amount' : IDL.Nat,
+    'collected_at' : IDL.Nat64,
+  });
+  const MonitorRuns = IDL.Record({
+    'last_started_at' : IDL.Opt(IDL.Nat64),
+    'last_completed_at' : IDL.Opt(IDL.Nat64),
+    'demoted' : IDL.Nat64,
+  });
+  const RegistryDetails = IDL.Record({
+    'website_url' : IDL.Opt(IDL.Text),
+    'twitter' : IDL.Opt(IDL.Text),
+    'name' : IDL.Opt(IDL.Text),
+    'description' : IDL.Opt(IDL.Text),
+    'email' : IDL.Opt(IDL.Text),
+    'project_name' : IDL.Opt(IDL.Text),
+    'telegram' : IDL.Opt(IDL.Text),
+  });
+  const Revision = IDL.Record({
+    'canister_id' : IDL.Principal,
+    'details' : RegistryDetails,
+    'submitted_at' : IDL.Nat64,
+    'submitted_by' : IDL.Principal,
+  });
+  const ReviewNote = IDL.Record({
+    'status' : StrikeStatus,
+    'timestamp' : IDL.Nat64,
+    'reason' : IDL.Text,
+  });
+  const MyRegistration = IDL.Record({
+    'pending_revision' : IDL.Opt(Revision),
+    'review_notes' : IDL.Vec(ReviewNote),
+    'registry' : StrikeRegistry,
+  });
+  const PaginatedResponse_1 = IDL.Record({
+    'total' : IDL.Nat32,
+    'next_cursor' : IDL.Opt(IDL.Text),
+    'items' : IDL.Vec(MyRegistration),
+  });
+  const Result_5 = IDL.Variant({
+    'Ok' : PaginatedResponse_1,
+    'Err' : StrikeError,
+  });
+  const PaginatedResponse_2 = IDL.Record({
+    'total' : IDL.Nat32,
+    'next_cursor' : IDL.Opt(IDL.Text),
+    'items' : IDL.Vec(Appeal),
+  });
+  const Result_6 = IDL.Variant({
+    'Ok' : PaginatedResponse_2,
+    'Err' : StrikeError,
+  });
+  const ReviewVote = IDL.Record({
+    'status' : StrikeStatus,
+    'timestamp' : IDL.Nat64,
+    'reviewer' : IDL.Principal,
+    'reason' : IDL.Opt(IDL.Text),
+  });
+  const Review = IDL.Record({
+    'escalated' : IDL.Bool,
+    'votes' : IDL.Vec(ReviewVote),
+    'canister_id' : IDL.Principal,
+  });
+  const PaginatedResponse_3 = IDL.Record({
+    'total' : IDL.Nat32,
+    'next_cursor' : IDL.Opt(IDL.Text),
+    'items' : IDL.Vec(Review),
+  });
+  const Result_7 = IDL.Variant({
+    'Ok' : PaginatedResponse_3,
+    'Err' : StrikeError,
+  });
+  const PaginatedResponse_4 = IDL.Record({
+    'total' : IDL.Nat32,
+    'next_cursor' : IDL.Opt(IDL.Text),
+    'items' : IDL.Vec(Revision),
+  });
+  const Result_8 = IDL.Variant({
+    'Ok' : PaginatedResponse_4,
+    'Err' : StrikeError,
+  });
+  const SortOrder = IDL.Variant({
+    'CreatedAtAsc' : IDL.Null,
+    'CreatedAtDesc' : IDL.Null,
+    'ProjectNameAsc' : IDL.Null,
+  });
+  const GetRegistriesParams = IDL.Record({
+    'status' : IDL.Opt(StrikeStatus),
+    'order' : IDL.Opt(SortOrder),
+    'pagination' : CursorPagination,
+  });
+  const PaginatedResponse_5 = IDL.Record({
     'total' : IDL.Nat32,
+    'next_cursor' : IDL.Opt(IDL.Text),
     'items' : IDL.Vec(StrikeRegistry),
   });
+  const Result_9 = IDL.Variant({
+    'Ok' : PaginatedResponse_5,
+    'Err' : StrikeError,
+  });
+  const ReportQueueEntry = IDL.Record({
+    'status' : IDL.Opt(StrikeStatus),
+    'report_count' : IDL.Nat32,
+    'canister_id' : IDL.Principal,
+  });
+  const PaginatedResponse_6 = IDL.Record({
+    'total' : IDL.Nat32,
+    'next_cursor' : IDL.Opt(IDL.Text),
+    'items' : IDL.Vec(ReportQueueEntry),
+  });
+  const Result_10 = IDL.Variant({
+    'Ok' : PaginatedResponse_6,
+    'Err' : StrikeError,
+  });
+  const ReportCategory = IDL.Variant({
+    'Scam' : IDL.Null,
+    'Phishing' : IDL.Null,
+    'Drainer' : IDL.Null,
+    'Other' : IDL.Null,
+    'Impersonation' : IDL.Null,
+  });
+  const Report = IDL.Record({
+    'evidence_url' : IDL.Opt(IDL.Text),
+    'note' : IDL.Opt(IDL.Text),
+    'canister_id' : IDL.Principal,
+    'created_at' : IDL.Nat64,
+    'category' : ReportCategory,
+    'reporter' : IDL.Principal,
+  });
+  const StatusCount = IDL.Record({
+    'status' : StrikeStatus,
+    'count' : IDL.Nat64,
+  });
+  const DailyStats = IDL.Record({
+    'day_start' : IDL.Nat64,
+    'submissions' : IDL.Nat64,
+    'blocks' : IDL.Nat64,
+    'approvals' : IDL.Nat64,
+  });
+  const RegistryStats = IDL.Record({
+    'status_counts' : IDL.Vec(StatusCount),
+    'median_review_time_secs' : IDL.Opt(IDL.Nat64),
+    'reviewed' : IDL.Nat64,
+    'daily' : IDL.Vec(DailyStats),
+  });
+  const StrikeSummary = IDL.Record({
+    'status' : IDL.Opt(StrikeStatus),
+    'canister_id' : IDL.Principal,
+    'module_hash' : IDL.Opt(IDL.Text),
+  });
+  const Result_11 = IDL.Variant({
+    'Ok' : IDL.Vec(StrikeSummary),
+    'Err' : StrikeError,
+  });
+  const SubmitterApproval = IDL.Record({
+    'controller' : IDL.Principal,
+    'submitter' : IDL.Principal,
+    'approved_at' : IDL.Nat64,
+  });
+  const HttpRequest = IDL.Record({
+    'url' : IDL.Text,
+    'method' : IDL.Text,
+    'body' : IDL.Vec(IDL.Nat8),
+    'headers' : IDL.Vec(IDL.Tuple(IDL.Text, IDL.Text)),
+    'certificate_version' : IDL.Opt(IDL.Nat16),
+  });
+  const HttpResponse = IDL.Record({
+    'body' : IDL.Vec(IDL.Nat8),
+    'headers' : IDL.Vec(IDL.Tuple(IDL.Text, IDL.Text)),
+    'upgrade' : IDL.Opt(IDL.Bool),
+    'status_code' : IDL.Nat16,
+  });
+  const RoleAssignment = IDL.Record({
+    'principal' : IDL.Principal,
+    'role' : Role,
+  });
+  const Result_12 = IDL.Variant({ 'Ok' : IDL.Nat, 'Err' : StrikeError });
+  const SearchFilters = IDL.Record({ 'status' : IDL.Opt(StrikeStatus) });
+  const UpdateRegistryDetailsParams = IDL.Record({
+    'canister_id' : IDL.Principal,
+    'details' : RegistryDetails,
+  });
   const UpdateRegistryStatusParams = IDL.Record({
     'status' : StrikeStatus,
     'canister_id' : IDL.Principal,
+    'reason' : IDL.Opt(IDL.Text),
   });
   return IDL.Service({
+    'accept_appeal' : IDL.Func(
+        [IDL.Principal, StrikeStatus, IDL.Opt(IDL.Text)],
+        [Result],
+        [],
+      ),
     'add_admin' : IDL.Func([IDL.Principal], [Result], []),
     'add_registry' : IDL.Func([AddRegistryParams], [Result], []),
+    'appeal_block' : IDL.Func([IDL.Principal, IDL.Text], [Result], []),
+    'approve_revision' : IDL.Func([IDL.Principal], [Result], []),
+    'approve_submitter' : IDL.Func(
+        [IDL.Principal, IDL.Principal],
+        [Result],
+        [],
+      ),
+    'cancel_import' : IDL.Func([], [Result], []),
+    'export_snapshot' : IDL.Func(
+        [IDL.Opt(SnapshotCursor)],
+        [SnapshotExport],
+        ['query'],
+      ),
+    'finalize_import' : IDL.Func([SnapshotManifest, IDL.Text], [Result_1], []),
     'get_admins' : IDL.Func([], [IDL.Vec(IDL.Principal)], []),
-    'get_registries' : IDL.Func(
-        [GetRegistriesParams],
-        [PaginatedResponse],
+    'get_appeals' : IDL.Func([IDL.Principal], [Result_2], ['query']),
+    'get_audit_log' : IDL.Func([GetAuditLogParams], [Result_3], ['query']),
+    'get_certified_strike_by_canister_id' : IDL.Func(
+        [IDL.Principal],
+        [Result_4],
+        ['query'],
+      ),
+    'get_deposit' : IDL.Func([IDL.Principal], [IDL.Opt(Deposit)], ['query']),
+    'get_deposit_config' : IDL.Func([], [IDL.Opt(DepositConfig)], ['query']),
+    'get_feature_toggles' : IDL.Func([], [FeatureToggles], ['query']),
+    'get_max_batch_lookup' : IDL.Func([], [IDL.Nat32], ['query']),
+    'get_module_hash_monitor_runs' : IDL.Func([], [MonitorRuns], ['query']),
+    'get_my_registrations' : IDL.Func(
+        [CursorPagination],
+        [Result_5],
         ['query'],
       ),
+    'get_open_appeals' : IDL.Func([CursorPagination], [Result_6], ['query']),
+    'get_pending_reviews' : IDL.Func([CursorPagination], [Result_7], ['query']),
+    'get_pending_revision' : IDL.Func(
+        [IDL.Principal],
+        [IDL.Opt(Revision)],
+        ['query'],
+      ),
+    'get_pending_revisions' : IDL.Func(
+        [CursorPagination],
+        [Result_8],
+        ['query'],
+      ),
+    'get_registries' : IDL.Func([GetRegistriesParams], [Result_9], ['query']),
+    'get_report_queue' : IDL.Func([CursorPagination], [Result_10], ['query']),
+    'get_reports' : IDL.Func([IDL.Principal], [IDL.Vec(Report)], ['query']),
+    'get_review_quorum' : IDL.Func([], [IDL.Nat32], ['query']),
+    'get_stats' : IDL.Func([], [RegistryStats], ['query']),
     'get_strike_by_canister_id' : IDL.Func(
         [IDL.Principal],
         [IDL.Opt(StrikeRegistry)],
         ['query'],
       ),
+    'get_strikes_by_canister_ids' : IDL.Func(
+        [IDL.Vec(IDL.Principal)],
+        [Result_11],
+        ['query'],
+      ),
+    'get_strikes_by_canister_ids_composite' : IDL.Func(
+        [IDL.Vec(IDL.Principal)],
+        [Result_11],
+        ['composite_query'],
+      ),
+    'get_submission_limits' : IDL.Func([], [SubmissionLimits], ['query']),
+    'get_submitter_approval' : IDL.Func(
+        [IDL.Principal],
+        [IDL.Opt(SubmitterApproval)],
+        ['query'],
+      ),
+    'grant_role' : IDL.Func([IDL.Principal, Role], [Result], []),
+    'http_request' : IDL.Func([HttpRequest], [HttpResponse], ['query']),
+    'http_request_update' : IDL.Func([HttpRequest], [HttpResponse], []),
+    'import_snapshot_chunk' : IDL.Func([SnapshotChunk], [Result], []),
     'is_admin' : IDL.Func([IDL.Principal], [IDL.Bool], ['query']),
+    'list_roles' : IDL.Func([], [IDL.Vec(RoleAssignment)], ['query']),
+    'purge_registry' : IDL.Func([IDL.Principal, IDL.Text], [Result], []),
+    'refund_deposit' : IDL.Func([IDL.Principal], [Result_12], []),
+    'reject_appeal' : IDL.Func([IDL.Principal, IDL.Text], [Result], []),
+    'reject_revision' : IDL.Func(
+        [IDL.Principal, IDL.Opt(IDL.Text)],
+        [Result],
+        [],
+      ),
     'remove_admin' : IDL.Func([IDL.Principal], [Result], []),
+    'report_canister' : IDL.Func(
+        [IDL.Principal, ReportCategory, IDL.Opt(IDL.Text), IDL.Opt(IDL.Text)],
+        [Result],
+        [],
+      ),
+    'revoke_role' : IDL.Func([IDL.Principal], [Result], []),
+    'search_registries' : IDL.Func(
+        [IDL.Text, SearchFilters, CursorPagination],
+        [Result_9],
+        ['query'],
+      ),
+    'set_deposit_config' : IDL.Func([IDL.Opt(DepositConfig)], [Result], []),
+    'set_feature_toggles' : IDL.Func([FeatureToggles], [Result], []),
+    'set_max_batch_lookup' : IDL.Func([IDL.Nat32], [Result], []),
+    'set_review_quorum' : IDL.Func([IDL.Nat32], [Result], []),
+    'set_submission_limits' : IDL.Func([SubmissionLimits], [Result], []),
+    'update_registry_details' : IDL.Func(
+        [UpdateRegistryDetailsParams],
+        [Result],
+        [],
+      ),
     'update_registry_status' : IDL.Func(
         [UpdateRegistryStatusParams],
         [Result],
         [],
       ),
+    'withdraw_registry' : IDL.Func(
+        [IDL.Principal, IDL.Opt(IDL.Text)],
+        [Result],
+        [],
+      ),
   });
 };
 export const init = ({ IDL }) => { return []; };
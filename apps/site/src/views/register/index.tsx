@@ -29,7 +29,7 @@ export function CanisterRegistry() {
       );
     } else {
       toast.error(
-        'Oops, something went wrong. Please try again in a bit or reach out to support.',
+        `Oops, something went wrong: ${res.error}. Please try again in a bit or reach out to support.`,
       );
     }
   };
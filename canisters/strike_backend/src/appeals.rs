@@ -0,0 +1,399 @@
+use candid::{CandidType, Principal};
+use ic_cdk::caller;
+use ic_stable_structures::{storable::Bound, Storable};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use tracing::info;
+
+use crate::audit::{self, AuditAction, AuditRecord};
+use crate::encoding::{self, Record};
+use crate::error::StrikeError;
+use crate::features::{self, Feature};
+use crate::index::{self, read_principal, write_principal, IndexKey, MIN_PRINCIPAL, PRINCIPAL_SIZE};
+use crate::limits::{self, MAX_REASON_LEN, MAX_STATEMENT_LEN};
+use crate::management;
+use crate::memory::{APPEALS, OPEN_APPEALS};
+use crate::roles;
+use crate::strike::{self, StrikeStatus};
+use crate::types::{CursorPagination, PaginatedResponse};
+
+#[derive(Serialize, Deserialize, CandidType, Debug, PartialEq, Clone, Copy)]
+pub enum AppealStatus {
+    Open,
+    Accepted,
+    Rejected,
+    /// Left without a decision because the block was lifted some other way, e.g. by review
+    Closed,
+}
+
+#[derive(Serialize, Deserialize, CandidType, Debug, Clone)]
+pub struct AppealResolution {
+    pub resolved_by: Principal,
+    pub resolved_at: u64,
+    pub reason: Option<String>,
+}
+
+/// An appeal against the block of a canister. The appeals of a canister form its thread, in the
+/// order they were filed.
+#[derive(Serialize, Deserialize, CandidType, Debug, Clone)]
+pub struct Appeal {
+    pub id: u64,
+    pub canister_id: Principal,
+    pub appellant: Principal,
+    pub statement: String,
+    pub created_at: u64,
+    pub status: AppealStatus,
+    pub resolution: Option<AppealResolution>,
+}
+
+impl Record for Appeal {
+    const VERSION: u8 = 1;
+}
+
+impl Storable for Appeal {
+    fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
+        encoding::record_to_bytes(self)
+    }
+
+    fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
+        encoding::record_from_bytes(&bytes)
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Orders the appeals of a canister by their sequence number
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct AppealKey {
+    pub canister_id: Principal,
+    pub id: u64,
+}
+
+impl Storable for AppealKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = Vec::with_capacity(PRINCIPAL_SIZE + 8);
+        write_principal(&mut bytes, &self.canister_id);
+        bytes.extend_from_slice(&self.id.to_be_bytes());
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Self {
+            canister_id: read_principal(&bytes),
+            id: u64::from_be_bytes(bytes[PRINCIPAL_SIZE..PRINCIPAL_SIZE + 8].try_into().unwrap()),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: (PRINCIPAL_SIZE + 8) as u32,
+        is_fixed_size: true,
+    };
+}
+
+/// Orders the open appeals by filing time. A canister has at most one open appeal, which leaves the
+/// index once it's resolved.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct OpenAppealKey {
+    pub created_at: u64,
+    pub canister_id: Principal,
+}
+
+impl OpenAppealKey {
+    fn first() -> Self {
+        Self {
+            created_at: 0,
+            canister_id: MIN_PRINCIPAL,
+        }
+    }
+}
+
+impl From<&Appeal> for OpenAppealKey {
+    fn from(appeal: &Appeal) -> Self {
+        Self {
+            created_at: appeal.created_at,
+            canister_id: appeal.canister_id,
+        }
+    }
+}
+
+impl IndexKey for OpenAppealKey {
+    fn canister_id(&self) -> Principal {
+        self.canister_id
+    }
+}
+
+impl Storable for OpenAppealKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = Vec::with_capacity(8 + PRINCIPAL_SIZE);
+        bytes.extend_from_slice(&self.created_at.to_be_bytes());
+        write_principal(&mut bytes, &self.canister_id);
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Self {
+            created_at: u64::from_be_bytes(bytes[..8].try_into().unwrap()),
+            canister_id: read_principal(&bytes[8..]),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: (8 + PRINCIPAL_SIZE) as u32,
+        is_fixed_size: true,
+    };
+}
+
+/// The appeals filed against the block of `canister_id`, oldest first
+pub fn get_appeals(canister_id: Principal) -> Vec<Appeal> {
+    APPEALS.with(|s| {
+        s.borrow()
+            .range(AppealKey { canister_id, id: 0 }..)
+            .take_while(|(key, _)| key.canister_id == canister_id)
+            .map(|(_, appeal)| appeal)
+            .collect()
+    })
+}
+
+fn open_appeal(canister_id: Principal) -> Option<Appeal> {
+    get_appeals(canister_id)
+        .into_iter()
+        .find(|appeal| appeal.status == AppealStatus::Open)
+}
+
+/// Lists the appeals awaiting a decision across all canisters, the longest waiting first
+pub fn get_open_appeals(pagination: CursorPagination) -> Result<PaginatedResponse<Appeal>, StrikeError> {
+    let page = OPEN_APPEALS.with(|s| {
+        index::scan(
+            &s.borrow(),
+            OpenAppealKey::first(),
+            pagination.start_after.as_deref(),
+            pagination.limit(),
+            false,
+            |_| true,
+        )
+    })?;
+
+    Ok(PaginatedResponse {
+        items: page.canister_ids.into_iter().filter_map(open_appeal).collect(),
+        total: OPEN_APPEALS.with(|s| s.borrow().len()) as u32,
+        next_cursor: page.next_cursor,
+    })
+}
+
+/// Builds the index of open appeals from scratch, for appeals filed before it existed
+pub fn reindex_open() {
+    OPEN_APPEALS.with(|s| s.borrow_mut().clear_new());
+
+    let open = APPEALS.with(|s| {
+        let mut open = 0;
+        for (_, appeal) in s.borrow().iter().filter(|(_, appeal)| appeal.status == AppealStatus::Open) {
+            OPEN_APPEALS.with(|s| s.borrow_mut().insert(OpenAppealKey::from(&appeal), ()));
+            open += 1;
+        }
+        open
+    });
+
+    info!("Indexed {} open appeals", open);
+}
+
+/// The thread of a canister is visible to the staff and to whoever submitted the canister or appealed its block
+pub fn get_appeal_thread(canister_id: Principal) -> Result<Vec<Appeal>, StrikeError> {
+    let caller = caller();
+    let appeals = get_appeals(canister_id);
+
+    let allowed = roles::effective_role(caller).is_some()
+        || strike::get_strike_by_canister_id(canister_id).map_or(false, |registry| registry.added_by == caller)
+        || appeals.iter().any(|appeal| appeal.appellant == caller);
+    if !allowed {
+        return Err(StrikeError::Unauthorized);
+    }
+
+    Ok(appeals)
+}
+
+pub fn clear_appeals(canister_id: Principal) {
+    for appeal in get_appeals(canister_id) {
+        OPEN_APPEALS.with(|s| s.borrow_mut().remove(&OpenAppealKey::from(&appeal)));
+        APPEALS.with(|s| {
+            s.borrow_mut().remove(&AppealKey {
+                canister_id,
+                id: appeal.id,
+            })
+        });
+    }
+}
+
+pub async fn appeal_block(canister_id: Principal, statement: String) -> Result<(), StrikeError> {
+    features::ensure_enabled(Feature::Appeals)?;
+
+    let caller = caller();
+
+    if statement.trim().is_empty() {
+        return Err(StrikeError::empty("statement"));
+    }
+
+    limits::check_lengths(&[("statement", Some(&statement), MAX_STATEMENT_LEN)])?;
+
+    let registry = strike::get_strike_by_canister_id(canister_id).ok_or(StrikeError::NotFound)?;
+    if registry.status != StrikeStatus::Blocked {
+        return Err(StrikeError::InvalidStatus { status: registry.status });
+    }
+
+    // Besides the original submitter, only the current controllers speak for the canister
+    if registry.added_by != caller {
+        management::ensure_controller(canister_id, caller).await?;
+    }
+
+    // The canister may have been unblocked or appealed while we were waiting for the management canister
+    let registry = strike::get_strike_by_canister_id(canister_id).ok_or(StrikeError::NotFound)?;
+    if registry.status != StrikeStatus::Blocked {
+        return Err(StrikeError::InvalidStatus { status: registry.status });
+    }
+
+    if open_appeal(canister_id).is_some() {
+        return Err(StrikeError::AlreadyExists);
+    }
+
+    let appeal = Appeal {
+        id: get_appeals(canister_id).len() as u64,
+        canister_id,
+        appellant: caller,
+        statement,
+        created_at: ic_cdk::api::time(),
+        status: AppealStatus::Open,
+        resolution: None,
+    };
+    OPEN_APPEALS.with(|s| s.borrow_mut().insert(OpenAppealKey::from(&appeal), ()));
+    APPEALS.with(|s| {
+        s.borrow_mut().insert(
+            AppealKey {
+                canister_id,
+                id: appeal.id,
+            },
+            appeal,
+        )
+    });
+
+    audit::record(caller, AuditRecord::registry(canister_id, AuditAction::AppealBlock));
+
+    Ok(())
+}
+
+fn resolve(
+    actor: Principal,
+    canister_id: Principal,
+    status: AppealStatus,
+    reason: Option<String>,
+) -> Result<Appeal, StrikeError> {
+    let mut appeal = open_appeal(canister_id).ok_or(StrikeError::NotFound)?;
+    OPEN_APPEALS.with(|s| s.borrow_mut().remove(&OpenAppealKey::from(&appeal)));
+
+    appeal.status = status;
+    appeal.resolution = Some(AppealResolution {
+        resolved_by: actor,
+        resolved_at: ic_cdk::api::time(),
+        reason,
+    });
+    APPEALS.with(|s| {
+        s.borrow_mut().insert(
+            AppealKey {
+                canister_id,
+                id: appeal.id,
+            },
+            appeal.clone(),
+        )
+    });
+
+    Ok(appeal)
+}
+
+/// Closes the open appeal of `canister_id`, if any, once its block was lifted on behalf of `actor`
+pub fn close_open_appeal(actor: Principal, canister_id: Principal) {
+    if resolve(actor, canister_id, AppealStatus::Closed, None).is_ok() {
+        audit::record(actor, AuditRecord::registry(canister_id, AuditAction::CloseAppeal));
+    }
+}
+
+/// Accepting an appeal is an admin decision that doesn't wait for the review quorum, the audit log
+/// says so next to the admin's reason
+fn quorum_bypass_reason(reason: Option<&str>) -> String {
+    match reason {
+        Some(reason) => format!("Review quorum bypassed to accept the appeal: {}", reason),
+        None => "Review quorum bypassed to accept the appeal".to_string(),
+    }
+}
+
+/// Lifts the block of the canister, moving it back to review or straight to trusted
+pub async fn accept_appeal(canister_id: Principal, status: StrikeStatus, reason: Option<String>) -> Result<(), StrikeError> {
+    if status == StrikeStatus::Blocked {
+        return Err(StrikeError::invalid_field("status", "must be Submitted or Trusted"));
+    }
+
+    limits::check_lengths(&[("reason", reason.as_deref(), MAX_REASON_LEN)])?;
+
+    if open_appeal(canister_id).is_none() {
+        return Err(StrikeError::NotFound);
+    }
+
+    let module_hash = strike::module_hash_to_pin(canister_id, status).await?;
+
+    // Another admin may have resolved the appeal, or reviewers lifted the block, while the module
+    // hash was fetched
+    if open_appeal(canister_id).is_none() {
+        return Err(StrikeError::NotFound);
+    }
+    let registry = strike::get_strike_by_canister_id(canister_id).ok_or(StrikeError::NotFound)?;
+    if registry.status != StrikeStatus::Blocked {
+        return Err(StrikeError::InvalidStatus { status: registry.status });
+    }
+
+    let caller = caller();
+    // Resolved first, so that lifting the block doesn't close it
+    resolve(caller, canister_id, AppealStatus::Accepted, reason.clone())?;
+
+    strike::set_registry_status(canister_id, status, reason.clone(), module_hash)?;
+
+    audit::record(
+        caller,
+        AuditRecord::registry(canister_id, AuditAction::AcceptAppeal).reason(Some(quorum_bypass_reason(reason.as_deref()))),
+    );
+
+    Ok(())
+}
+
+pub fn reject_appeal(canister_id: Principal, reason: String) -> Result<(), StrikeError> {
+    if reason.trim().is_empty() {
+        return Err(StrikeError::empty("reason"));
+    }
+
+    limits::check_lengths(&[("reason", Some(&reason), MAX_REASON_LEN)])?;
+
+    resolve(caller(), canister_id, AppealStatus::Rejected, Some(reason.clone()))?;
+
+    audit::record(
+        caller(),
+        AuditRecord::registry(canister_id, AuditAction::RejectAppeal).reason(Some(reason)),
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_appeal_key_roundtrip() {
+        let key = AppealKey {
+            canister_id: Principal::from_slice(&[1; 10]),
+            id: 3,
+        };
+        assert_eq!(AppealKey::from_bytes(key.to_bytes()), key);
+
+        let key = OpenAppealKey {
+            created_at: 7,
+            canister_id: Principal::from_slice(&[1; 10]),
+        };
+        assert_eq!(OpenAppealKey::from_bytes(key.to_bytes()), key);
+    }
+}
@@ -0,0 +1,118 @@
+use candid::{CandidType, Principal};
+use ic_cdk::caller;
+use ic_stable_structures::{storable::Bound, Storable};
+use serde::{Deserialize, Serialize};
+
+use crate::audit::{self, AuditAction, AuditRecord};
+use crate::encoding::{self, Record};
+use crate::error::StrikeError;
+use crate::management;
+use crate::memory::SUBMITTER_APPROVALS;
+
+/// Approvals are only good for a week, a submission after that needs a fresh one
+const APPROVAL_TTL_NANOS: u64 = 7 * 24 * 60 * 60 * 1_000_000_000;
+
+/// A controller's consent for another principal to submit its canister, e.g. a developer
+/// submitting a canister that is controlled by a DAO
+#[derive(Serialize, Deserialize, CandidType, Debug, Clone)]
+pub struct SubmitterApproval {
+    pub controller: Principal,
+    pub submitter: Principal,
+    pub approved_at: u64,
+}
+
+impl Record for SubmitterApproval {
+    const VERSION: u8 = 1;
+}
+
+impl Storable for SubmitterApproval {
+    fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
+        encoding::record_to_bytes(self)
+    }
+
+    fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
+        encoding::record_from_bytes(&bytes)
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Lets `submitter` register `canister_id` on behalf of the caller, who has to control it. Replaces
+/// any earlier approval of the canister.
+pub async fn approve_submitter(canister_id: Principal, submitter: Principal) -> Result<(), StrikeError> {
+    let caller = caller();
+    if submitter == Principal::anonymous() {
+        return Err(StrikeError::invalid_field("submitter", "cannot be anonymous"));
+    }
+
+    management::ensure_controller(canister_id, caller).await?;
+
+    let approval = SubmitterApproval {
+        controller: caller,
+        submitter,
+        approved_at: ic_cdk::api::time(),
+    };
+    SUBMITTER_APPROVALS.with(|s| s.borrow_mut().insert(canister_id, approval));
+
+    audit::record(
+        caller,
+        AuditRecord::registry(canister_id, AuditAction::ApproveSubmitter).principal(submitter),
+    );
+    Ok(())
+}
+
+pub fn get_submitter_approval(canister_id: Principal) -> Option<SubmitterApproval> {
+    SUBMITTER_APPROVALS.with(|s| s.borrow().get(&canister_id))
+}
+
+/// Checks that `controller` approved `submitter` for the canister and that the approval hasn't expired
+pub fn ensure_approved(
+    canister_id: Principal,
+    controller: Principal,
+    submitter: Principal,
+    now: u64,
+) -> Result<(), StrikeError> {
+    match get_submitter_approval(canister_id) {
+        Some(approval)
+            if approval.controller == controller
+                && approval.submitter == submitter
+                && now.saturating_sub(approval.approved_at) <= APPROVAL_TTL_NANOS =>
+        {
+            Ok(())
+        }
+        _ => Err(StrikeError::Unauthorized),
+    }
+}
+
+/// Drops the approval of a canister once it was used
+pub fn consume(canister_id: Principal) {
+    SUBMITTER_APPROVALS.with(|s| s.borrow_mut().remove(&canister_id));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CANISTER: Principal = Principal::from_slice(&[0; 10]);
+    const ALI: Principal = Principal::from_slice(&[1; 29]);
+    const BOB: Principal = Principal::from_slice(&[2; 29]);
+
+    #[test]
+    fn test_approval_is_scoped_and_expires() {
+        let approval = SubmitterApproval {
+            controller: ALI,
+            submitter: BOB,
+            approved_at: 1_000,
+        };
+        SUBMITTER_APPROVALS.with(|s| s.borrow_mut().insert(CANISTER, approval));
+
+        assert!(ensure_approved(CANISTER, ALI, BOB, 1_000).is_ok());
+        assert!(ensure_approved(CANISTER, BOB, BOB, 1_000).is_err());
+        assert!(ensure_approved(CANISTER, ALI, ALI, 1_000).is_err());
+        assert!(ensure_approved(CANISTER, ALI, BOB, 1_000 + APPROVAL_TTL_NANOS).is_ok());
+        assert!(ensure_approved(CANISTER, ALI, BOB, 1_001 + APPROVAL_TTL_NANOS).is_err());
+
+        consume(CANISTER);
+        assert!(ensure_approved(CANISTER, ALI, BOB, 1_000).is_err());
+    }
+}
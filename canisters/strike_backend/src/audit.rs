@@ -0,0 +1,428 @@
+use candid::{CandidType, Principal};
+use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::thread::LocalKey;
+use tracing::info;
+
+use crate::deposits::DepositConfig;
+use crate::encoding::{self, Record};
+use crate::error::StrikeError;
+use crate::features::FeatureToggles;
+use crate::index::{read_principal, write_principal, PRINCIPAL_SIZE};
+use crate::limits::SubmissionLimits;
+use crate::memory::{Memory, AUDIT_BY_ACTOR, AUDIT_BY_CANISTER, AUDIT_COUNT_BY_ACTOR, AUDIT_COUNT_BY_CANISTER, AUDIT_LOG};
+use crate::roles::Role;
+use crate::strike::{StrikeRegistry, StrikeStatus};
+use crate::types::{CursorPagination, PaginatedResponse};
+
+#[derive(Serialize, Deserialize, CandidType, Debug, PartialEq, Clone, Copy)]
+pub enum AuditAction {
+    AddRegistry,
+    UpdateRegistryStatus,
+    GrantRole,
+    RevokeRole,
+    ReviewVote,
+    SetReviewQuorum,
+    AppealBlock,
+    AcceptAppeal,
+    RejectAppeal,
+    CloseAppeal,
+    UpdateRegistryDetails,
+    ApproveRevision,
+    RejectRevision,
+    WithdrawRegistry,
+    PurgeRegistry,
+    SetSubmissionLimits,
+    SetDepositConfig,
+    RefundDeposit,
+    /// The deposit of a purged entry was kept
+    ForfeitDeposit,
+    SetFeatureToggles,
+    /// The reason is the hash of the manifest
+    ImportSnapshot,
+    SetMaxBatchLookup,
+    /// A controller let another principal submit its canister, the principal is the submitter
+    ApproveSubmitter,
+    /// The monitor pinned the module hash of an entry trusted before pinning, the reason is the hash
+    PinModuleHash,
+}
+
+#[derive(Serialize, Deserialize, CandidType, Debug, PartialEq, Clone)]
+pub enum AuditValue {
+    Status(StrikeStatus),
+    Role(Role),
+    Quorum(u32),
+    Tombstone(Tombstone),
+    SubmissionLimits(SubmissionLimits),
+    DepositConfig(DepositConfig),
+    FeatureToggles(FeatureToggles),
+    MaxBatchLookup(u32),
+}
+
+/// What a removed entry looked like, without the contact details of the submitter
+#[derive(Serialize, Deserialize, CandidType, Debug, PartialEq, Clone)]
+pub struct Tombstone {
+    pub status: StrikeStatus,
+    pub module_hash: Option<String>,
+    pub project_name: String,
+    pub website_url: Option<String>,
+    pub added_by: Principal,
+    pub created_at: u64,
+}
+
+impl From<&StrikeRegistry> for Tombstone {
+    fn from(registry: &StrikeRegistry) -> Self {
+        Self {
+            status: registry.status,
+            module_hash: registry.module_hash.clone(),
+            project_name: registry.project_name.clone(),
+            website_url: registry.website_url.clone(),
+            added_by: registry.added_by,
+            created_at: registry.created_at,
+        }
+    }
+}
+
+/// A single mutation of the registry or the admins, as recorded in the append-only audit log
+#[derive(Serialize, Deserialize, CandidType, Debug, Clone)]
+pub struct AuditEntry {
+    pub id: u64,
+    pub timestamp: u64,
+    pub caller: Principal,
+    pub action: AuditAction,
+    pub canister_id: Option<Principal>,
+    pub principal: Option<Principal>,
+    pub old_value: Option<AuditValue>,
+    pub new_value: Option<AuditValue>,
+    pub reason: Option<String>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetAuditLogParams {
+    pub canister_id: Option<Principal>,
+    pub actor: Option<Principal>,
+    /// Pages go from the newest entry to the oldest
+    pub pagination: CursorPagination,
+}
+
+/// Orders the audit entries of a principal by id, the principal is either the canister or the
+/// caller depending on the index
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct AuditIndexKey {
+    pub principal: Principal,
+    pub id: u64,
+}
+
+impl Storable for AuditIndexKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = Vec::with_capacity(PRINCIPAL_SIZE + 8);
+        write_principal(&mut bytes, &self.principal);
+        bytes.extend_from_slice(&self.id.to_be_bytes());
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Self {
+            principal: read_principal(&bytes),
+            id: u64::from_be_bytes(bytes[PRINCIPAL_SIZE..PRINCIPAL_SIZE + 8].try_into().unwrap()),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: (PRINCIPAL_SIZE + 8) as u32,
+        is_fixed_size: true,
+    };
+}
+
+impl Record for AuditEntry {
+    const VERSION: u8 = 1;
+}
+
+impl Storable for AuditEntry {
+    fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
+        encoding::record_to_bytes(self)
+    }
+
+    fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
+        encoding::record_from_bytes(&bytes)
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Describes what changed, the caller and timestamp are filled in by [`record`]
+pub struct AuditRecord {
+    pub action: AuditAction,
+    pub canister_id: Option<Principal>,
+    pub principal: Option<Principal>,
+    pub old_value: Option<AuditValue>,
+    pub new_value: Option<AuditValue>,
+    pub reason: Option<String>,
+}
+
+impl AuditRecord {
+    pub fn registry(canister_id: Principal, action: AuditAction) -> Self {
+        Self {
+            action,
+            canister_id: Some(canister_id),
+            principal: None,
+            old_value: None,
+            new_value: None,
+            reason: None,
+        }
+    }
+
+    pub fn config(action: AuditAction) -> Self {
+        Self {
+            action,
+            canister_id: None,
+            principal: None,
+            old_value: None,
+            new_value: None,
+            reason: None,
+        }
+    }
+
+    pub fn role(principal: Principal, action: AuditAction) -> Self {
+        Self {
+            action,
+            canister_id: None,
+            principal: Some(principal),
+            old_value: None,
+            new_value: None,
+            reason: None,
+        }
+    }
+
+    pub fn principal(mut self, principal: Principal) -> Self {
+        self.principal = Some(principal);
+        self
+    }
+
+    pub fn values(mut self, old_value: Option<AuditValue>, new_value: Option<AuditValue>) -> Self {
+        self.old_value = old_value;
+        self.new_value = new_value;
+        self
+    }
+
+    pub fn reason(mut self, reason: Option<String>) -> Self {
+        self.reason = reason;
+        self
+    }
+}
+
+pub fn record(caller: Principal, record: AuditRecord) {
+    AUDIT_LOG.with(|log| {
+        let log = log.borrow();
+        let entry = AuditEntry {
+            id: log.len(),
+            timestamp: ic_cdk::api::time(),
+            caller,
+            action: record.action,
+            canister_id: record.canister_id,
+            principal: record.principal,
+            old_value: record.old_value,
+            new_value: record.new_value,
+            reason: record.reason,
+        };
+
+        log.append(&entry).expect("failed to append to the audit log");
+        index_entry(&entry);
+    });
+}
+
+fn index_entry(entry: &AuditEntry) {
+    if let Some(canister_id) = entry.canister_id {
+        let key = AuditIndexKey {
+            principal: canister_id,
+            id: entry.id,
+        };
+        AUDIT_BY_CANISTER.with(|s| s.borrow_mut().insert(key, ()));
+        increment(&AUDIT_COUNT_BY_CANISTER, canister_id);
+    }
+
+    let key = AuditIndexKey {
+        principal: entry.caller,
+        id: entry.id,
+    };
+    AUDIT_BY_ACTOR.with(|s| s.borrow_mut().insert(key, ()));
+    increment(&AUDIT_COUNT_BY_ACTOR, entry.caller);
+}
+
+fn increment(counts: &'static LocalKey<RefCell<StableBTreeMap<Principal, u64, Memory>>>, principal: Principal) {
+    counts.with(|s| {
+        let mut counts = s.borrow_mut();
+        let count = counts.get(&principal).unwrap_or(0);
+        counts.insert(principal, count + 1);
+    });
+}
+
+fn count_of(counts: &'static LocalKey<RefCell<StableBTreeMap<Principal, u64, Memory>>>, principal: Principal) -> u64 {
+    counts.with(|s| s.borrow().get(&principal).unwrap_or(0))
+}
+
+/// Builds the indexes of the audit log from scratch, for logs written before they existed
+pub fn reindex_log() {
+    AUDIT_BY_CANISTER.with(|s| s.borrow_mut().clear_new());
+    AUDIT_BY_ACTOR.with(|s| s.borrow_mut().clear_new());
+    AUDIT_COUNT_BY_CANISTER.with(|s| s.borrow_mut().clear_new());
+    AUDIT_COUNT_BY_ACTOR.with(|s| s.borrow_mut().clear_new());
+
+    let entries = AUDIT_LOG.with(|log| {
+        let log = log.borrow();
+        for entry in log.iter() {
+            index_entry(&entry);
+        }
+        log.len()
+    });
+
+    info!("Indexed {} audit log entries", entries);
+}
+
+fn encode_cursor(id: u64) -> String {
+    hex::encode(id.to_be_bytes())
+}
+
+fn decode_cursor(cursor: &str) -> Result<u64, StrikeError> {
+    let bytes = hex::decode(cursor).map_err(|_| StrikeError::invalid_cursor())?;
+    let bytes: [u8; 8] = bytes.try_into().map_err(|_| StrikeError::invalid_cursor())?;
+    Ok(u64::from_be_bytes(bytes))
+}
+
+/// Walks the ids of `principal` in an index down from `before`, keeping up to `limit` that satisfy `keep`
+fn walk_down(
+    index: &StableBTreeMap<AuditIndexKey, (), Memory>,
+    principal: Principal,
+    before: u64,
+    limit: usize,
+    keep: impl Fn(u64) -> bool,
+) -> Vec<u64> {
+    let mut ids = Vec::with_capacity(limit);
+    let mut bound = AuditIndexKey { principal, id: before };
+    while ids.len() < limit {
+        match index.iter_upper_bound(&bound).next() {
+            Some((key, _)) if key.principal == principal => {
+                if keep(key.id) {
+                    ids.push(key.id);
+                }
+                bound = key;
+            }
+            _ => break,
+        }
+    }
+    ids
+}
+
+/// Returns the matching entries, newest first. Filters are resolved through the indexes, only the
+/// entries of the page are read from the log.
+pub fn get_audit_log(params: GetAuditLogParams) -> Result<PaginatedResponse<AuditEntry>, StrikeError> {
+    let before = params.pagination.start_after.as_deref().map(decode_cursor).transpose()?;
+    let before = before.unwrap_or(u64::MAX);
+    // One more entry than requested tells whether there is a next page
+    let limit = params.pagination.limit() + 1;
+
+    let is_by_actor = |id: u64| {
+        params.actor.map_or(true, |actor| {
+            AUDIT_BY_ACTOR.with(|s| s.borrow().contains_key(&AuditIndexKey { principal: actor, id }))
+        })
+    };
+
+    let (mut ids, total) = match (params.canister_id, params.actor) {
+        // Entries of a canister are few, so the caller is checked entry by entry
+        (Some(canister_id), Some(_)) => AUDIT_BY_CANISTER.with(|s| {
+            let index = s.borrow();
+            let first = AuditIndexKey {
+                principal: canister_id,
+                id: 0,
+            };
+            let last = AuditIndexKey {
+                principal: canister_id,
+                id: u64::MAX,
+            };
+            (
+                walk_down(&index, canister_id, before, limit, is_by_actor),
+                index.range(first..=last).filter(|(key, _)| is_by_actor(key.id)).count() as u64,
+            )
+        }),
+        (Some(canister_id), None) => (
+            AUDIT_BY_CANISTER.with(|s| walk_down(&s.borrow(), canister_id, before, limit, |_| true)),
+            count_of(&AUDIT_COUNT_BY_CANISTER, canister_id),
+        ),
+        (None, Some(actor)) => (
+            AUDIT_BY_ACTOR.with(|s| walk_down(&s.borrow(), actor, before, limit, |_| true)),
+            count_of(&AUDIT_COUNT_BY_ACTOR, actor),
+        ),
+        (None, None) => {
+            let len = AUDIT_LOG.with(|log| log.borrow().len());
+            ((0..before.min(len)).rev().take(limit).collect(), len)
+        }
+    };
+
+    let next_cursor = if ids.len() == limit {
+        ids.truncate(limit - 1);
+        ids.last().copied().map(encode_cursor)
+    } else {
+        None
+    };
+
+    let items = AUDIT_LOG.with(|log| {
+        let log = log.borrow();
+        ids.into_iter().filter_map(|id| log.get(id)).collect()
+    });
+
+    Ok(PaginatedResponse {
+        total: total as u32,
+        items,
+        next_cursor,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::get_memory;
+    use ic_stable_structures::memory_manager::MemoryId;
+
+    const ALI: Principal = Principal::from_slice(&[1; 29]);
+    const BOB: Principal = Principal::from_slice(&[2; 10]);
+
+    #[test]
+    fn test_walks_down_the_entries_of_a_principal() {
+        let mut index = StableBTreeMap::init(get_memory(MemoryId::new(200)));
+        for (principal, id) in [(ALI, 1), (BOB, 2), (ALI, 3), (ALI, 5), (BOB, 6)] {
+            index.insert(AuditIndexKey { principal, id }, ());
+        }
+
+        assert_eq!(walk_down(&index, ALI, u64::MAX, 10, |_| true), vec![5, 3, 1]);
+        assert_eq!(walk_down(&index, ALI, u64::MAX, 2, |_| true), vec![5, 3]);
+        assert_eq!(walk_down(&index, ALI, 3, 10, |_| true), vec![1]);
+        assert_eq!(walk_down(&index, BOB, u64::MAX, 10, |id| id != 6), vec![2]);
+
+        assert_eq!(decode_cursor(&encode_cursor(42)), Ok(42));
+        assert!(decode_cursor("00").is_err());
+    }
+
+    #[test]
+    fn test_counts_the_entries_of_each_principal() {
+        for (id, (caller, canister_id)) in [(ALI, Some(BOB)), (ALI, None), (BOB, Some(BOB))].into_iter().enumerate() {
+            index_entry(&AuditEntry {
+                id: id as u64,
+                timestamp: 0,
+                caller,
+                action: AuditAction::AddRegistry,
+                canister_id,
+                principal: None,
+                old_value: None,
+                new_value: None,
+                reason: None,
+            });
+        }
+
+        assert_eq!(count_of(&AUDIT_COUNT_BY_ACTOR, ALI), 2);
+        assert_eq!(count_of(&AUDIT_COUNT_BY_ACTOR, BOB), 1);
+        assert_eq!(count_of(&AUDIT_COUNT_BY_CANISTER, BOB), 2);
+        assert_eq!(count_of(&AUDIT_COUNT_BY_CANISTER, ALI), 0);
+    }
+}
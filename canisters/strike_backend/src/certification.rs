@@ -0,0 +1,288 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use candid::{CandidType, Principal};
+use ic_certified_map::{fork, fork_hash, labeled, labeled_hash, AsHashTree, Hash, HashTree, RbTree};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::cell::RefCell;
+use tracing::info;
+
+use crate::error::StrikeError;
+use crate::http;
+use crate::memory::REGISTRY;
+use crate::strike::{StrikeRegistry, StrikeStatus};
+
+const HTTP_ASSETS_LABEL: &[u8] = b"http_assets";
+const STATUS_LABEL: &[u8] = b"status";
+// The HTTP gateway checks responses to uncertified paths against this one, so it certifies the 404s
+const NOT_FOUND_PATH: &str = "/index.html";
+
+// CBOR self-describe tag, expected at the start of encoded trees and certificates
+const CBOR_SELF_DESCRIBE_TAG: [u8; 3] = [0xd9, 0xd9, 0xf7];
+
+/// The certified data tree, with the layout
+///
+/// ```text
+/// ├── http_assets
+/// │   ├── /index.html → sha256 of the 404 body
+/// │   └── /v1/canisters/<canister ID> → sha256 of the response body
+/// └── status
+///     └── <canister ID bytes> → "Submitted" | "Trusted" | "Blocked"
+/// ```
+struct CertifiedTree {
+    http_assets: RbTree<Vec<u8>, Hash>,
+    statuses: RbTree<Vec<u8>, Vec<u8>>,
+}
+
+impl Default for CertifiedTree {
+    fn default() -> Self {
+        let mut http_assets = RbTree::new();
+        http_assets.insert(
+            NOT_FOUND_PATH.as_bytes().to_vec(),
+            Sha256::digest(http::not_found_body()).into(),
+        );
+        Self {
+            http_assets,
+            statuses: RbTree::new(),
+        }
+    }
+}
+
+impl CertifiedTree {
+    fn root_hash(&self) -> Hash {
+        fork_hash(
+            &labeled_hash(HTTP_ASSETS_LABEL, &self.http_assets.root_hash()),
+            &labeled_hash(STATUS_LABEL, &self.statuses.root_hash()),
+        )
+    }
+
+    /// Replaces the certified values of `old` with the ones of `new`
+    fn update(&mut self, old: Option<&StrikeRegistry>, new: Option<&StrikeRegistry>) {
+        if let Some(old) = old {
+            self.http_assets.delete(http::canister_path(old.canister_id).as_bytes());
+            self.statuses.delete(old.canister_id.as_slice());
+        }
+
+        if let Some(new) = new {
+            let body_hash = Sha256::digest(http::canister_body(new)).into();
+            self.http_assets
+                .insert(http::canister_path(new.canister_id).into_bytes(), body_hash);
+            self.statuses
+                .insert(new.canister_id.as_slice().to_vec(), status_leaf(new.status));
+        }
+    }
+
+    fn status_witness(&self, canister_id: Principal) -> HashTree {
+        fork(
+            HashTree::Pruned(labeled_hash(HTTP_ASSETS_LABEL, &self.http_assets.root_hash())),
+            labeled(STATUS_LABEL, self.statuses.witness(canister_id.as_slice())),
+        )
+    }
+
+    /// Proves the response at `path`, or its absence along with the 404 at `NOT_FOUND_PATH`
+    fn http_witness(&self, path: &str) -> HashTree {
+        let mut witness = self.http_assets.witness(path.as_bytes());
+        if self.http_assets.get(path.as_bytes()).is_none() {
+            witness = merge_witnesses(witness, self.http_assets.witness(NOT_FOUND_PATH.as_bytes()));
+        }
+
+        fork(
+            labeled(HTTP_ASSETS_LABEL, witness),
+            HashTree::Pruned(labeled_hash(STATUS_LABEL, &self.statuses.root_hash())),
+        )
+    }
+}
+
+/// Combines two witnesses of the same tree into one that reveals what either of them does
+fn merge_witnesses<'a>(left: HashTree<'a>, right: HashTree<'a>) -> HashTree<'a> {
+    match (left, right) {
+        (HashTree::Pruned(_), tree) | (tree, HashTree::Pruned(_)) => tree,
+        (HashTree::Fork(left), HashTree::Fork(right)) => {
+            let (left_l, left_r) = *left;
+            let (right_l, right_r) = *right;
+            fork(merge_witnesses(left_l, right_l), merge_witnesses(left_r, right_r))
+        }
+        (HashTree::Labeled(label, left), HashTree::Labeled(_, right)) => labeled(label, merge_witnesses(*left, *right)),
+        (tree, _) => tree,
+    }
+}
+
+thread_local! {
+    // Derived from `REGISTRY` and rebuilt on init and after upgrades, so it's not persisted
+    static TREE: RefCell<CertifiedTree> = RefCell::new(CertifiedTree::default());
+}
+
+/// A registry lookup that clients can verify against the subnet's public key, by checking that
+/// the witness reconstructs to the certified data of the certificate and that it contains the
+/// status of the entry under `status/<canister ID>`, or proves its absence
+#[derive(CandidType, Deserialize)]
+pub struct CertifiedStrike {
+    pub registry: Option<StrikeRegistry>,
+    #[serde(with = "serde_bytes")]
+    pub certificate: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    pub witness: Vec<u8>,
+}
+
+fn status_leaf(status: StrikeStatus) -> Vec<u8> {
+    let status = match status {
+        StrikeStatus::Submitted => "Submitted",
+        StrikeStatus::Trusted => "Trusted",
+        StrikeStatus::Blocked => "Blocked",
+    };
+    status.as_bytes().to_vec()
+}
+
+fn encode_tree(tree: &HashTree) -> Vec<u8> {
+    let mut bytes = CBOR_SELF_DESCRIBE_TAG.to_vec();
+    ciborium::into_writer(tree, &mut bytes).expect("failed to encode the witness");
+    bytes
+}
+
+/// Keeps the certified data consistent with a write to `REGISTRY`, replacing `old` with `new`
+pub fn recertify(old: Option<&StrikeRegistry>, new: Option<&StrikeRegistry>) {
+    TREE.with(|t| {
+        let mut tree = t.borrow_mut();
+        tree.update(old, new);
+        ic_cdk::api::set_certified_data(&tree.root_hash());
+    });
+}
+
+/// Builds the certified data tree from `REGISTRY`, the tree lives on the heap so this has to be
+/// called from both `init` and `post_upgrade`
+pub fn rebuild() {
+    info!("Rebuilding certified data");
+
+    TREE.with(|t| {
+        let mut tree = CertifiedTree::default();
+        REGISTRY.with(|s| {
+            for (_, registry) in s.borrow().iter() {
+                tree.update(None, Some(&registry));
+            }
+        });
+
+        ic_cdk::api::set_certified_data(&tree.root_hash());
+        *t.borrow_mut() = tree;
+    });
+}
+
+pub fn get_certified_strike(canister_id: Principal) -> Result<CertifiedStrike, StrikeError> {
+    let certificate = ic_cdk::api::data_certificate().ok_or_else(|| StrikeError::Unavailable {
+        reason: "Certificates are only available in query calls".to_string(),
+    })?;
+
+    Ok(CertifiedStrike {
+        registry: REGISTRY.with(|s| s.borrow().get(&canister_id)),
+        certificate,
+        witness: TREE.with(|t| encode_tree(&t.borrow().status_witness(canister_id))),
+    })
+}
+
+/// The `IC-Certificate` header for a response served at `path`, for the HTTP gateway to verify
+/// the response body against. None in update calls, where responses go through consensus anyway.
+pub fn http_certificate_header(path: &str) -> Option<(String, String)> {
+    let certificate = ic_cdk::api::data_certificate()?;
+    let witness = TREE.with(|t| encode_tree(&t.borrow().http_witness(path)));
+
+    Some((
+        "IC-Certificate".to_string(),
+        format!(
+            "certificate=:{}:, tree=:{}:",
+            BASE64.encode(certificate),
+            BASE64.encode(witness)
+        ),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry(canister_id: Principal, status: StrikeStatus) -> StrikeRegistry {
+        StrikeRegistry {
+            canister_id,
+            module_hash: None,
+            name: "Ali".to_string(),
+            email: "ali@example.com".to_string(),
+            telegram: None,
+            twitter: None,
+            project_name: "Project".to_string(),
+            description: "A project".to_string(),
+            website_url: None,
+            created_at: 0,
+            added_by: Principal::anonymous(),
+            status,
+            status_reason: None,
+            report_count: None,
+        }
+    }
+
+    fn lookup<'a>(tree: &'a HashTree<'a>, path: &[&[u8]]) -> Option<&'a [u8]> {
+        match (tree, path.split_first()) {
+            (HashTree::Leaf(value), None) => Some(value),
+            (HashTree::Fork(forks), Some(_)) => lookup(&forks.0, path).or_else(|| lookup(&forks.1, path)),
+            (HashTree::Labeled(label, subtree), Some((first, rest))) if label == first => lookup(subtree, rest),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_status_witness_reconstructs_root_hash() {
+        let trusted = Principal::from_slice(&[0x01]);
+        let blocked = Principal::from_slice(&[0x02]);
+        let unknown = Principal::from_slice(&[0x03]);
+
+        let mut tree = CertifiedTree::default();
+        tree.update(None, Some(&registry(trusted, StrikeStatus::Submitted)));
+        tree.update(
+            Some(&registry(trusted, StrikeStatus::Submitted)),
+            Some(&registry(trusted, StrikeStatus::Trusted)),
+        );
+        tree.update(None, Some(&registry(blocked, StrikeStatus::Blocked)));
+
+        let witness = tree.status_witness(trusted);
+        assert_eq!(witness.reconstruct(), tree.root_hash());
+        assert_eq!(lookup(&witness, &[STATUS_LABEL, trusted.as_slice()]), Some(&b"Trusted"[..]));
+
+        let witness = tree.status_witness(unknown);
+        assert_eq!(witness.reconstruct(), tree.root_hash());
+        assert_eq!(lookup(&witness, &[STATUS_LABEL, unknown.as_slice()]), None);
+    }
+
+    #[test]
+    fn test_http_witness_certifies_response_body() {
+        let canister_id = Principal::from_slice(&[0x01]);
+        let registry = registry(canister_id, StrikeStatus::Trusted);
+
+        let mut tree = CertifiedTree::default();
+        tree.update(None, Some(&registry));
+
+        let path = http::canister_path(canister_id);
+        let witness = tree.http_witness(&path);
+        assert_eq!(witness.reconstruct(), tree.root_hash());
+
+        let body_hash: Hash = Sha256::digest(http::canister_body(&registry)).into();
+        assert_eq!(lookup(&witness, &[HTTP_ASSETS_LABEL, path.as_bytes()]), Some(&body_hash[..]));
+
+        tree.update(Some(&registry), None);
+        assert_eq!(tree.root_hash(), CertifiedTree::default().root_hash());
+    }
+
+    #[test]
+    fn test_http_witness_certifies_not_found() {
+        let mut tree = CertifiedTree::default();
+        for byte in 0..16 {
+            tree.update(None, Some(&registry(Principal::from_slice(&[byte]), StrikeStatus::Trusted)));
+        }
+
+        let path = http::canister_path(Principal::from_slice(&[0xff]));
+        let witness = tree.http_witness(&path);
+        assert_eq!(witness.reconstruct(), tree.root_hash());
+        assert_eq!(lookup(&witness, &[HTTP_ASSETS_LABEL, path.as_bytes()]), None);
+
+        let body_hash: Hash = Sha256::digest(http::not_found_body()).into();
+        assert_eq!(
+            lookup(&witness, &[HTTP_ASSETS_LABEL, NOT_FOUND_PATH.as_bytes()]),
+            Some(&body_hash[..])
+        );
+    }
+}
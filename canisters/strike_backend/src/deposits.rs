@@ -0,0 +1,389 @@
+use candid::{CandidType, Nat, Principal};
+use ic_cdk::caller;
+use ic_stable_structures::{storable::Bound, Storable};
+use icrc_ledger_types::icrc1::account::Account;
+use icrc_ledger_types::icrc1::transfer::{Memo, TransferArg, TransferError};
+use icrc_ledger_types::icrc2::transfer_from::{TransferFromArgs, TransferFromError};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::encoding::{self, Record};
+use crate::error::StrikeError;
+use crate::memory::{DEPOSITS, STATE};
+use crate::roles;
+
+/// When a deposit goes back to the submitter. Deposits of blocked entries are always kept.
+#[derive(Serialize, Deserialize, CandidType, Debug, PartialEq, Clone, Copy)]
+pub enum RefundPolicy {
+    /// Refunded once the entry is trusted
+    OnTrusted,
+    /// Also refunded when the submitter withdraws the entry before it's blocked
+    OnTrustedOrWithdrawn,
+    Never,
+}
+
+/// The deposit `add_registry` collects through `icrc2_transfer_from`, the submitter has to approve
+/// `amount` plus the ledger fee first
+#[derive(Serialize, Deserialize, CandidType, Debug, PartialEq, Clone)]
+pub struct DepositConfig {
+    pub ledger: Principal,
+    pub amount: Nat,
+    pub refund_policy: RefundPolicy,
+}
+
+#[derive(Serialize, Deserialize, CandidType, Debug, PartialEq, Clone)]
+pub enum DepositStatus {
+    /// The deposit is being transferred from the submitter, the entry is dropped if that fails
+    Collecting,
+    Held,
+    /// A refund is in flight
+    Refunding,
+    /// The ledger didn't answer the collection, which may have gone through. The next submission
+    /// of the depositor sends the same transfer, with `collected_at` as its `created_at_time`.
+    Pending,
+    Refunded {
+        block_index: Nat,
+    },
+    /// Kept because the entry was blocked or withdrawn. A forfeited deposit is still refunded if the
+    /// entry is trusted later, e.g. after an appeal.
+    Forfeited,
+}
+
+/// The deposit backing the current submission of a canister
+#[derive(Serialize, Deserialize, CandidType, Debug, Clone)]
+pub struct Deposit {
+    pub canister_id: Principal,
+    pub depositor: Principal,
+    pub ledger: Principal,
+    pub amount: Nat,
+    pub block_index: Nat,
+    pub collected_at: u64,
+    pub status: DepositStatus,
+    /// The last refund that may have reached the ledger without an answer. Retries send the same
+    /// transfer, which the ledger deduplicates.
+    pub refund_attempt: Option<TransferAttempt>,
+}
+
+#[derive(Serialize, Deserialize, CandidType, Debug, PartialEq, Clone)]
+pub struct TransferAttempt {
+    pub created_at_time: u64,
+    pub fee: Nat,
+}
+
+impl Record for Deposit {
+    const VERSION: u8 = 1;
+}
+
+impl Storable for Deposit {
+    fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
+        encoding::record_to_bytes(self)
+    }
+
+    fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
+        encoding::record_from_bytes(&bytes)
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+pub fn get_deposit_config() -> Option<DepositConfig> {
+    STATE.with(|s| s.borrow().deposit.clone())
+}
+
+/// Sets the deposit new submissions require, none to accept submissions without a deposit
+pub fn set_deposit_config(config: Option<DepositConfig>) -> Result<Option<DepositConfig>, StrikeError> {
+    if config.as_ref().map_or(false, |config| config.amount == 0u64) {
+        return Err(StrikeError::invalid_field(
+            "amount",
+            "must be positive, disable the deposit instead",
+        ));
+    }
+
+    Ok(STATE.with(|s| std::mem::replace(&mut s.borrow_mut().deposit, config)))
+}
+
+pub fn get_deposit(canister_id: Principal) -> Option<Deposit> {
+    DEPOSITS.with(|s| s.borrow().get(&canister_id))
+}
+
+/// The deposit of a canister is visible to whoever paid it and to the staff
+pub fn get_deposit_for_caller(canister_id: Principal) -> Option<Deposit> {
+    let caller = caller();
+    get_deposit(canister_id).filter(|deposit| deposit.depositor == caller || roles::effective_role(caller).is_some())
+}
+
+fn save_deposit(deposit: Deposit) {
+    DEPOSITS.with(|s| s.borrow_mut().insert(deposit.canister_id, deposit));
+}
+
+/// Puts back the deposit a failed collection replaced, e.g. the refunded one of an earlier submission
+fn restore_deposit(canister_id: Principal, previous: Option<Deposit>) {
+    match previous {
+        Some(previous) => save_deposit(previous),
+        None => {
+            DEPOSITS.with(|s| s.borrow_mut().remove(&canister_id));
+        }
+    }
+}
+
+fn memo(canister_id: Principal) -> Option<Memo> {
+    Some(Memo::from(canister_id.as_slice().to_vec()))
+}
+
+/// Collects the configured deposit from `depositor` for a submission of `canister_id`, returns
+/// whether anything was collected. Nothing is when deposits are disabled or the current submission
+/// already holds one.
+pub async fn collect(canister_id: Principal, depositor: Principal) -> Result<bool, StrikeError> {
+    let Some(config) = get_deposit_config() else {
+        return Ok(false);
+    };
+
+    let previous = get_deposit(canister_id);
+    let mut deposit = match previous.clone() {
+        Some(deposit) if deposit.status == DepositStatus::Held => return Ok(false),
+        Some(deposit) if deposit.status == DepositStatus::Collecting => {
+            return Err(StrikeError::Unavailable {
+                reason: "A deposit for this canister is already being collected".to_string(),
+            })
+        }
+        // Still counts as paid until the refund settles, either way
+        Some(deposit) if deposit.status == DepositStatus::Refunding => {
+            return Err(StrikeError::Unavailable {
+                reason: "The deposit of an earlier submission of this canister is being refunded".to_string(),
+            })
+        }
+        Some(deposit) if deposit.status == DepositStatus::Pending && deposit.depositor != depositor => {
+            return Err(StrikeError::Unavailable {
+                reason: "An earlier deposit for this canister is still unsettled".to_string(),
+            })
+        }
+        // The same transfer again, which the ledger deduplicates if the first one went through
+        Some(deposit) if deposit.status == DepositStatus::Pending => deposit,
+        _ => Deposit {
+            canister_id,
+            depositor,
+            ledger: config.ledger,
+            amount: config.amount,
+            block_index: Nat::from(0u64),
+            collected_at: ic_cdk::api::time(),
+            status: DepositStatus::Collecting,
+            refund_attempt: None,
+        },
+    };
+    // Claimed before the first await, so that concurrent submissions can't charge twice
+    deposit.status = DepositStatus::Collecting;
+    save_deposit(deposit.clone());
+
+    let args = TransferFromArgs {
+        spender_subaccount: None,
+        from: Account::from(deposit.depositor),
+        to: Account::from(ic_cdk::id()),
+        amount: deposit.amount.clone(),
+        fee: None,
+        memo: memo(canister_id),
+        created_at_time: Some(deposit.collected_at),
+    };
+    let result = ic_cdk::call::<_, (Result<Nat, TransferFromError>,)>(deposit.ledger, "icrc2_transfer_from", (args,)).await;
+
+    match result {
+        Ok((Ok(block_index),))
+        | Ok((Err(TransferFromError::Duplicate {
+            duplicate_of: block_index,
+        }),)) => {
+            deposit.block_index = block_index;
+            deposit.status = DepositStatus::Held;
+            save_deposit(deposit);
+            Ok(true)
+        }
+        Ok((Err(err),)) => {
+            // Drops a pending claim too, the ledger would have answered with a duplicate
+            restore_deposit(
+                canister_id,
+                previous.filter(|previous| previous.status != DepositStatus::Pending),
+            );
+            Err(StrikeError::CallFailed {
+                canister_id: deposit.ledger,
+                reason: format!("{:?}", err),
+            })
+        }
+        // A reject leaves it unknown whether the ledger executed the transfer
+        Err(err) => {
+            let ledger = deposit.ledger;
+            deposit.status = DepositStatus::Pending;
+            save_deposit(deposit);
+            Err(StrikeError::rejected(ledger, err))
+        }
+    }
+}
+
+/// Sends the deposit of `canister_id` back to the depositor, minus the fee of the refund
+pub async fn refund(canister_id: Principal) -> Result<Nat, StrikeError> {
+    let mut deposit = get_deposit(canister_id).ok_or(StrikeError::NotFound)?;
+    if !matches!(deposit.status, DepositStatus::Held | DepositStatus::Forfeited) {
+        return Err(StrikeError::AlreadyFinalized);
+    }
+
+    // Claimed before the first await, so that concurrent calls can't refund twice
+    let previous = std::mem::replace(&mut deposit.status, DepositStatus::Refunding);
+    save_deposit(deposit.clone());
+
+    let result = transfer_back(&mut deposit).await;
+
+    deposit.status = match &result {
+        Ok(block_index) => DepositStatus::Refunded {
+            block_index: block_index.clone(),
+        },
+        Err(_) => previous,
+    };
+    save_deposit(deposit);
+
+    result
+}
+
+async fn transfer_back(deposit: &mut Deposit) -> Result<Nat, StrikeError> {
+    let attempt = match deposit.refund_attempt.clone() {
+        Some(attempt) => attempt,
+        None => {
+            let (fee,): (Nat,) = ic_cdk::call(deposit.ledger, "icrc1_fee", ())
+                .await
+                .map_err(|err| StrikeError::rejected(deposit.ledger, err))?;
+            TransferAttempt {
+                created_at_time: ic_cdk::api::time(),
+                fee,
+            }
+        }
+    };
+    if deposit.amount <= attempt.fee {
+        return Err(StrikeError::invalid_field("amount", "doesn't cover the ledger fee"));
+    }
+
+    // Saved before the transfer, a reject leaves it unknown whether the ledger executed it
+    deposit.refund_attempt = Some(attempt.clone());
+    save_deposit(deposit.clone());
+
+    let args = TransferArg {
+        from_subaccount: None,
+        to: Account::from(deposit.depositor),
+        fee: Some(attempt.fee.clone()),
+        created_at_time: Some(attempt.created_at_time),
+        memo: memo(deposit.canister_id),
+        amount: deposit.amount.clone() - attempt.fee,
+    };
+    let (result,): (Result<Nat, TransferError>,) = ic_cdk::call(deposit.ledger, "icrc1_transfer", (args,))
+        .await
+        .map_err(|err| StrikeError::rejected(deposit.ledger, err))?;
+
+    // The ledger answered, so a retry needs a new transfer
+    deposit.refund_attempt = None;
+    match result {
+        Ok(block_index)
+        | Err(TransferError::Duplicate {
+            duplicate_of: block_index,
+        }) => Ok(block_index),
+        Err(err) => Err(StrikeError::CallFailed {
+            canister_id: deposit.ledger,
+            reason: format!("{:?}", err),
+        }),
+    }
+}
+
+/// Keeps the deposit of `canister_id`, if it's still held, returns whether it was
+pub fn forfeit(canister_id: Principal) -> bool {
+    let Some(mut deposit) = get_deposit(canister_id).filter(|deposit| deposit.status == DepositStatus::Held) else {
+        return false;
+    };
+    deposit.status = DepositStatus::Forfeited;
+    save_deposit(deposit);
+    true
+}
+
+/// Refunds the deposit of `canister_id` in the background. A failed refund leaves the deposit
+/// as it was, for an admin to retry with `refund_deposit`.
+pub fn spawn_refund(canister_id: Principal) {
+    let refundable = get_deposit(canister_id).map_or(false, |deposit| {
+        matches!(deposit.status, DepositStatus::Held | DepositStatus::Forfeited)
+    });
+    if !refundable {
+        return;
+    }
+
+    ic_cdk::spawn(async move {
+        if let Err(err) = refund(canister_id).await {
+            warn!("Failed to refund the deposit of {}: {}", canister_id, err);
+        }
+    });
+}
+
+/// Whether the refund policy returns the deposit of an entry its submitter withdrew
+pub fn refunds_withdrawals() -> bool {
+    get_deposit_config().map_or(false, |config| config.refund_policy == RefundPolicy::OnTrustedOrWithdrawn)
+}
+
+/// Whether the refund policy returns the deposit of a trusted entry. Deposits still held once
+/// deposits are disabled are refunded as usual.
+pub fn refunds_trusted() -> bool {
+    get_deposit_config().map_or(true, |config| config.refund_policy != RefundPolicy::Never)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deposit_roundtrip() {
+        let deposit = Deposit {
+            canister_id: Principal::from_slice(&[0; 10]),
+            depositor: Principal::from_slice(&[1; 29]),
+            ledger: Principal::from_slice(&[2; 10]),
+            amount: Nat::from(100_000_000u64),
+            block_index: Nat::from(7u64),
+            collected_at: 0,
+            status: DepositStatus::Refunded {
+                block_index: Nat::from(8u64),
+            },
+            refund_attempt: Some(TransferAttempt {
+                created_at_time: 9,
+                fee: Nat::from(10_000u64),
+            }),
+        };
+
+        let decoded = Deposit::from_bytes(deposit.to_bytes());
+        assert_eq!(decoded.amount, deposit.amount);
+        assert_eq!(decoded.status, deposit.status);
+        assert_eq!(decoded.refund_attempt, deposit.refund_attempt);
+    }
+
+    #[test]
+    fn test_rejects_unknown_versions() {
+        let deposit = Deposit {
+            canister_id: Principal::from_slice(&[0; 10]),
+            depositor: Principal::from_slice(&[1; 29]),
+            ledger: Principal::from_slice(&[2; 10]),
+            amount: Nat::from(100_000_000u64),
+            block_index: Nat::from(7u64),
+            collected_at: 0,
+            status: DepositStatus::Held,
+            refund_attempt: None,
+        };
+
+        let mut bytes = deposit.to_bytes().into_owned();
+        assert!(encoding::try_decode_record::<Deposit>(&bytes).is_some());
+        // The version follows the magic
+        bytes[4] = Deposit::VERSION + 1;
+        assert!(encoding::try_decode_record::<Deposit>(&bytes).is_none());
+        assert!(encoding::try_decode_record::<Deposit>(b"DIDL").is_none());
+    }
+
+    #[test]
+    fn test_config_survives_upgrades() {
+        let config = DepositConfig {
+            ledger: Principal::from_slice(&[2; 10]),
+            amount: Nat::from(100_000_000u64),
+            refund_policy: RefundPolicy::OnTrustedOrWithdrawn,
+        };
+
+        // The heap state is serialized with MessagePack on upgrade
+        let bytes = rmp_serde::to_vec_named(&config).unwrap();
+        assert_eq!(rmp_serde::from_slice::<DepositConfig>(&bytes).unwrap(), config);
+    }
+}
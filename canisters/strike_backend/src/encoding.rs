@@ -0,0 +1,536 @@
+use candid::{CandidType, Decode, Encode, Principal};
+use ic_stable_structures::{storable::Bound, Storable};
+use serde::de::DeserializeOwned;
+use serde::de::{self, IgnoredAny, MapAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
+use std::fmt;
+use tracing::info;
+
+use crate::memory::REGISTRY;
+use crate::strike::{StrikeRegistry, StrikeStatus};
+
+/// Starts every versioned entry, followed by the version and the payload. Entries written before
+/// versioning are bare Candid, which starts with `DIDL` instead.
+const MAGIC: &[u8] = b"SREG";
+
+/// The version of the entries `to_bytes` writes. Every past version keeps a decoder below, so
+/// that entries are readable until `migrate_registry` has rewritten them. Changing it takes a new
+/// migration step that calls `migrate_registry`.
+pub const REGISTRY_VERSION: u8 = 2;
+
+/// The keys of the CBOR map of version 2. A tag is never reused, not even once its field is
+/// removed, and decoding skips the tags it doesn't know.
+mod tag {
+    pub const CANISTER_ID: u8 = 0;
+    pub const MODULE_HASH: u8 = 1;
+    pub const NAME: u8 = 2;
+    pub const EMAIL: u8 = 3;
+    pub const TELEGRAM: u8 = 4;
+    pub const TWITTER: u8 = 5;
+    pub const PROJECT_NAME: u8 = 6;
+    pub const DESCRIPTION: u8 = 7;
+    pub const WEBSITE_URL: u8 = 8;
+    pub const CREATED_AT: u8 = 9;
+    pub const ADDED_BY: u8 = 10;
+    pub const STATUS: u8 = 11;
+    pub const STATUS_REASON: u8 = 12;
+    pub const REPORT_COUNT: u8 = 13;
+}
+
+/// Entries as written before versioning, in bare Candid. Releases until then only added `opt`
+/// fields, which Candid decodes as none when they're missing, so this layout covers all of them.
+#[derive(CandidType, Deserialize)]
+struct StrikeRegistryV0 {
+    canister_id: Principal,
+    module_hash: Option<String>,
+    name: String,
+    email: String,
+    telegram: Option<String>,
+    twitter: Option<String>,
+    project_name: String,
+    description: String,
+    website_url: Option<String>,
+    created_at: u64,
+    added_by: Principal,
+    status: StrikeStatus,
+    status_reason: Option<String>,
+    report_count: Option<u32>,
+}
+
+impl From<StrikeRegistryV0> for StrikeRegistry {
+    fn from(v0: StrikeRegistryV0) -> Self {
+        Self {
+            canister_id: v0.canister_id,
+            module_hash: v0.module_hash,
+            name: v0.name,
+            email: v0.email,
+            telegram: v0.telegram,
+            twitter: v0.twitter,
+            project_name: v0.project_name,
+            description: v0.description,
+            website_url: v0.website_url,
+            created_at: v0.created_at,
+            added_by: v0.added_by,
+            status: v0.status,
+            status_reason: v0.status_reason,
+            report_count: v0.report_count,
+        }
+    }
+}
+
+/// Version 1: the layout above in Candid, written from versioning until the CBOR encoding. Frozen
+/// like version 0, so that changes to `StrikeRegistry` don't change how these entries decode.
+type StrikeRegistryV1 = StrikeRegistryV0;
+
+/// Version 2: a CBOR map from the tags above to the fields, leaving out the fields that are none.
+/// Unlike Candid it carries no type table, so a typical entry is a third smaller and decodes
+/// without a type check.
+struct Compact<'a>(&'a StrikeRegistry);
+
+impl Serialize for Compact<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let registry = self.0;
+        let optional = [
+            registry.module_hash.is_some(),
+            registry.telegram.is_some(),
+            registry.twitter.is_some(),
+            registry.website_url.is_some(),
+            registry.status_reason.is_some(),
+            registry.report_count.is_some(),
+        ];
+        let len = 8 + optional.iter().filter(|present| **present).count();
+
+        let mut map = serializer.serialize_map(Some(len))?;
+        map.serialize_entry(&tag::CANISTER_ID, &registry.canister_id)?;
+        if let Some(module_hash) = &registry.module_hash {
+            map.serialize_entry(&tag::MODULE_HASH, module_hash)?;
+        }
+        map.serialize_entry(&tag::NAME, &registry.name)?;
+        map.serialize_entry(&tag::EMAIL, &registry.email)?;
+        if let Some(telegram) = &registry.telegram {
+            map.serialize_entry(&tag::TELEGRAM, telegram)?;
+        }
+        if let Some(twitter) = &registry.twitter {
+            map.serialize_entry(&tag::TWITTER, twitter)?;
+        }
+        map.serialize_entry(&tag::PROJECT_NAME, &registry.project_name)?;
+        map.serialize_entry(&tag::DESCRIPTION, &registry.description)?;
+        if let Some(website_url) = &registry.website_url {
+            map.serialize_entry(&tag::WEBSITE_URL, website_url)?;
+        }
+        map.serialize_entry(&tag::CREATED_AT, &registry.created_at)?;
+        map.serialize_entry(&tag::ADDED_BY, &registry.added_by)?;
+        map.serialize_entry(&tag::STATUS, &(registry.status as u8))?;
+        if let Some(status_reason) = &registry.status_reason {
+            map.serialize_entry(&tag::STATUS_REASON, status_reason)?;
+        }
+        if let Some(report_count) = &registry.report_count {
+            map.serialize_entry(&tag::REPORT_COUNT, report_count)?;
+        }
+        map.end()
+    }
+}
+
+struct CompactVisitor;
+
+impl<'de> Visitor<'de> for CompactVisitor {
+    type Value = StrikeRegistry;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a map of registry fields")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut canister_id = None;
+        let mut module_hash = None;
+        let mut name = None;
+        let mut email = None;
+        let mut telegram = None;
+        let mut twitter = None;
+        let mut project_name = None;
+        let mut description = None;
+        let mut website_url = None;
+        let mut created_at = None;
+        let mut added_by = None;
+        let mut status = None;
+        let mut status_reason = None;
+        let mut report_count = None;
+
+        while let Some(key) = map.next_key::<u8>()? {
+            match key {
+                tag::CANISTER_ID => canister_id = Some(map.next_value()?),
+                tag::MODULE_HASH => module_hash = Some(map.next_value()?),
+                tag::NAME => name = Some(map.next_value()?),
+                tag::EMAIL => email = Some(map.next_value()?),
+                tag::TELEGRAM => telegram = Some(map.next_value()?),
+                tag::TWITTER => twitter = Some(map.next_value()?),
+                tag::PROJECT_NAME => project_name = Some(map.next_value()?),
+                tag::DESCRIPTION => description = Some(map.next_value()?),
+                tag::WEBSITE_URL => website_url = Some(map.next_value()?),
+                tag::CREATED_AT => created_at = Some(map.next_value()?),
+                tag::ADDED_BY => added_by = Some(map.next_value()?),
+                tag::STATUS => status = Some(map.next_value::<u8>()?),
+                tag::STATUS_REASON => status_reason = Some(map.next_value()?),
+                tag::REPORT_COUNT => report_count = Some(map.next_value()?),
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        let status = match status {
+            Some(status @ 0..=2) => StrikeStatus::from_u8(status),
+            Some(status) => return Err(de::Error::custom(format!("invalid strike status {}", status))),
+            None => return Err(de::Error::missing_field("status")),
+        };
+        Ok(StrikeRegistry {
+            canister_id: canister_id.ok_or_else(|| de::Error::missing_field("canister_id"))?,
+            module_hash,
+            name: name.ok_or_else(|| de::Error::missing_field("name"))?,
+            email: email.ok_or_else(|| de::Error::missing_field("email"))?,
+            telegram,
+            twitter,
+            project_name: project_name.ok_or_else(|| de::Error::missing_field("project_name"))?,
+            description: description.ok_or_else(|| de::Error::missing_field("description"))?,
+            website_url,
+            created_at: created_at.ok_or_else(|| de::Error::missing_field("created_at"))?,
+            added_by: added_by.ok_or_else(|| de::Error::missing_field("added_by"))?,
+            status,
+            status_reason,
+            report_count,
+        })
+    }
+}
+
+struct Decoded(StrikeRegistry);
+
+impl<'de> Deserialize<'de> for Decoded {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(CompactVisitor).map(Decoded)
+    }
+}
+
+/// Splits stored bytes into their version and payload
+fn split(bytes: &[u8]) -> (u8, &[u8]) {
+    match bytes.strip_prefix(MAGIC) {
+        Some([version, payload @ ..]) => (*version, payload),
+        _ => (0, bytes),
+    }
+}
+
+fn encode(version: u8, payload: Vec<u8>) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(MAGIC.len() + 1 + payload.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.push(version);
+    bytes.extend(payload);
+    bytes
+}
+
+/// Entries written before the length limits of the text fields may be arbitrarily large, so the
+/// values stay unbounded. The keys are principals, which are bounded.
+impl Storable for StrikeRegistry {
+    fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
+        let mut payload = Vec::new();
+        ciborium::into_writer(&Compact(self), &mut payload).expect("failed to encode the registry entry");
+        Cow::Owned(encode(REGISTRY_VERSION, payload))
+    }
+
+    fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
+        // Only a downgrade leaves entries newer than the code
+        try_decode(bytes.as_ref())
+            .unwrap_or_else(|| panic!("failed to decode a registry entry of version {}", split(bytes.as_ref()).0))
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Decodes an entry of any version, none if the bytes aren't one. For entries that don't come
+/// from the registry, e.g. in a snapshot.
+pub fn try_decode(bytes: &[u8]) -> Option<StrikeRegistry> {
+    match split(bytes) {
+        (0, payload) => Decode!(payload, StrikeRegistryV0).ok().map(Into::into),
+        (1, payload) => Decode!(payload, StrikeRegistryV1).ok().map(Into::into),
+        (2, payload) => ciborium::from_reader::<Decoded, _>(payload).ok().map(|decoded| decoded.0),
+        _ => None,
+    }
+}
+
+/// A stable record other than the registry entries, kept in the same envelope with a Candid
+/// payload. `VERSION` decodes into the record itself, so a change to its layout takes a new
+/// version, and the old layout moves to a frozen struct that `decode_past` decodes.
+pub trait Record: CandidType + DeserializeOwned {
+    /// The version `record_to_bytes` writes, from 1
+    const VERSION: u8;
+
+    /// Decodes the payload of a past version, none if `version` isn't one
+    fn decode_past(_version: u8, _payload: &[u8]) -> Option<Self> {
+        None
+    }
+}
+
+pub fn record_to_bytes<T: Record>(record: &T) -> Cow<'static, [u8]> {
+    let payload = Encode!(record).expect("failed to encode a stable record");
+    Cow::Owned(encode(T::VERSION, payload))
+}
+
+/// Decodes a record of any version, none if the bytes aren't one
+pub fn try_decode_record<T: Record>(bytes: &[u8]) -> Option<T> {
+    match split(bytes) {
+        (version, payload) if version == T::VERSION => Decode!(payload, T).ok(),
+        (version, payload) => T::decode_past(version, payload),
+    }
+}
+
+pub fn record_from_bytes<T: Record>(bytes: &[u8]) -> T {
+    // Only a downgrade leaves records newer than the code
+    try_decode_record(bytes).unwrap_or_else(|| {
+        panic!(
+            "failed to decode a {} of version {}",
+            std::any::type_name::<T>(),
+            split(bytes).0
+        )
+    })
+}
+
+/// Rewrites the registry entries in the current version. The indexes don't depend on the encoding
+/// and stay as they are.
+pub fn migrate_registry() {
+    let keys: Vec<Principal> = REGISTRY.with(|s| s.borrow().iter().map(|(key, _)| key).collect());
+    info!("Rewriting {} registry entries in version {}", keys.len(), REGISTRY_VERSION);
+
+    REGISTRY.with(|s| {
+        let mut registry = s.borrow_mut();
+        for key in keys {
+            if let Some(entry) = registry.get(&key) {
+                registry.insert(key, entry);
+            }
+        }
+    });
+}
+
+/// Instruction counts of the registry encodings, measured in a canister built with the `bench`
+/// feature
+#[cfg(feature = "bench")]
+pub mod bench {
+    use super::*;
+    use candid::Encode;
+    use ic_cdk::api::performance_counter;
+    use ic_stable_structures::{StableBTreeMap, VectorMemory};
+
+    const ENTRIES: u64 = 1000;
+    const PAGE_SIZE: usize = 100;
+
+    #[derive(CandidType, Deserialize, Debug)]
+    pub struct EncodingCost {
+        pub entry_bytes: u64,
+        pub lookup_instructions: u64,
+        pub listing_instructions: u64,
+    }
+
+    #[derive(CandidType, Deserialize, Debug)]
+    pub struct EncodingBenchmark {
+        pub candid: EncodingCost,
+        pub cbor: EncodingCost,
+    }
+
+    /// An entry in version 1, the Candid encoding
+    struct CandidEntry(StrikeRegistry);
+
+    impl Storable for CandidEntry {
+        fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
+            Cow::Owned(encode(1, Encode!(&self.0).unwrap()))
+        }
+
+        fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
+            Self(StrikeRegistry::from_bytes(bytes))
+        }
+
+        const BOUND: Bound = Bound::Unbounded;
+    }
+
+    /// An entry with every field set, at typical lengths
+    fn entry(i: u64) -> StrikeRegistry {
+        StrikeRegistry {
+            canister_id: Principal::from_slice(&i.to_be_bytes()),
+            module_hash: Some("ab".repeat(32)),
+            name: "Ali Example".to_string(),
+            email: "ali@example.com".to_string(),
+            telegram: Some("@strike_bot".to_string()),
+            twitter: Some("@strike".to_string()),
+            project_name: format!("Project {}", i),
+            description: "A wallet for the Internet Computer. ".repeat(8),
+            website_url: Some("https://example.com".to_string()),
+            created_at: 1_700_000_000_000_000_000 + i,
+            added_by: Principal::from_slice(&[1; 29]),
+            status: StrikeStatus::Trusted,
+            status_reason: Some("Approved by the reviewers".to_string()),
+            report_count: Some(0),
+        }
+    }
+
+    fn measure<V: Storable>(wrap: impl Fn(StrikeRegistry) -> V) -> EncodingCost {
+        let mut map: StableBTreeMap<Principal, V, VectorMemory> = StableBTreeMap::init(VectorMemory::default());
+        for i in 0..ENTRIES {
+            let value = wrap(entry(i));
+            map.insert(Principal::from_slice(&i.to_be_bytes()), value);
+        }
+        let entry_bytes = wrap(entry(0)).to_bytes().len() as u64;
+
+        let key = Principal::from_slice(&(ENTRIES / 2).to_be_bytes());
+        let start = performance_counter(0);
+        assert!(map.get(&key).is_some());
+        let lookup_instructions = performance_counter(0) - start;
+
+        let start = performance_counter(0);
+        assert_eq!(map.iter().take(PAGE_SIZE).count(), PAGE_SIZE);
+        let listing_instructions = performance_counter(0) - start;
+
+        EncodingCost {
+            entry_bytes,
+            lookup_instructions,
+            listing_instructions,
+        }
+    }
+
+    /// Looks up a single entry and lists a page of entries from a registry in each encoding
+    pub fn run() -> EncodingBenchmark {
+        EncodingBenchmark {
+            candid: measure(CandidEntry),
+            cbor: measure(|registry| registry),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candid::Encode;
+
+    fn entry() -> StrikeRegistry {
+        StrikeRegistry {
+            canister_id: Principal::from_slice(&[0; 10]),
+            module_hash: Some("ab".repeat(32)),
+            name: "Ali".to_string(),
+            email: "ali@example.com".to_string(),
+            telegram: None,
+            twitter: Some("@strike".to_string()),
+            project_name: "Strike".to_string(),
+            description: "A wallet\nfor everyone".to_string(),
+            website_url: Some("https://example.com".to_string()),
+            created_at: 42,
+            added_by: Principal::from_slice(&[1; 29]),
+            status: StrikeStatus::Blocked,
+            status_reason: None,
+            report_count: Some(3),
+        }
+    }
+
+    #[test]
+    fn test_cbor_roundtrip() {
+        let registry = entry();
+        let bytes = registry.to_bytes();
+        assert_eq!(split(&bytes).0, 2);
+
+        let decoded = StrikeRegistry::from_bytes(bytes);
+        assert_eq!(decoded.canister_id, registry.canister_id);
+        assert_eq!(decoded.module_hash, registry.module_hash);
+        assert_eq!(decoded.telegram, None);
+        assert_eq!(decoded.twitter, registry.twitter);
+        assert_eq!(decoded.description, registry.description);
+        assert_eq!(decoded.added_by, registry.added_by);
+        assert_eq!(decoded.status, StrikeStatus::Blocked);
+        assert_eq!(decoded.status_reason, None);
+        assert_eq!(decoded.report_count, Some(3));
+    }
+
+    #[test]
+    fn test_cbor_is_smaller_than_candid() {
+        let registry = entry();
+        let cbor = registry.to_bytes().len();
+        let candid = Encode!(&registry).unwrap().len();
+        assert!(cbor * 3 < candid * 2, "cbor {} bytes, candid {} bytes", cbor, candid);
+    }
+
+    #[test]
+    fn test_decodes_candid_entries() {
+        let registry = entry();
+        let bytes = encode(1, Encode!(&registry).unwrap());
+        let decoded = StrikeRegistry::from_bytes(Cow::Owned(bytes));
+        assert_eq!(decoded.project_name, registry.project_name);
+        assert_eq!(decoded.report_count, Some(3));
+    }
+
+    #[test]
+    fn test_skips_unknown_tags() {
+        let registry = entry();
+        let mut bytes = registry.to_bytes().into_owned();
+
+        // A field written by a later release, appended to the map and counted in its header
+        let header = MAGIC.len() + 1;
+        bytes[header] += 1;
+        bytes.extend_from_slice(&[0x18, 0xff, 0x63, b'n', b'e', b'w']);
+
+        let decoded = StrikeRegistry::from_bytes(Cow::Owned(bytes));
+        assert_eq!(decoded.name, registry.name);
+        assert_eq!(decoded.report_count, Some(3));
+    }
+
+    /// The layout of the first release
+    #[derive(CandidType)]
+    struct FirstReleaseRegistry {
+        canister_id: Principal,
+        module_hash: Option<String>,
+        name: String,
+        email: String,
+        telegram: Option<String>,
+        twitter: Option<String>,
+        project_name: String,
+        description: String,
+        website_url: Option<String>,
+        created_at: u64,
+        added_by: Principal,
+        status: StrikeStatus,
+    }
+
+    #[test]
+    fn test_decodes_entries_written_before_versioning() {
+        let legacy = FirstReleaseRegistry {
+            canister_id: Principal::from_slice(&[0; 10]),
+            module_hash: None,
+            name: "Ali".to_string(),
+            email: "ali@example.com".to_string(),
+            telegram: Some("@strike_bot".to_string()),
+            twitter: None,
+            project_name: "Strike".to_string(),
+            description: "A wallet".to_string(),
+            website_url: None,
+            created_at: 42,
+            added_by: Principal::from_slice(&[1; 29]),
+            status: StrikeStatus::Trusted,
+        };
+
+        let registry = StrikeRegistry::from_bytes(Cow::Owned(Encode!(&legacy).unwrap()));
+        assert_eq!(registry.canister_id, legacy.canister_id);
+        assert_eq!(registry.telegram, legacy.telegram);
+        assert_eq!(registry.created_at, 42);
+        assert_eq!(registry.status, StrikeStatus::Trusted);
+        assert_eq!(registry.status_reason, None);
+        assert_eq!(registry.report_count, None);
+
+        // Written back in the current version
+        let bytes = registry.to_bytes();
+        assert_eq!(split(&bytes).0, REGISTRY_VERSION);
+        let decoded = StrikeRegistry::from_bytes(bytes);
+        assert_eq!(decoded.project_name, "Strike");
+        assert_eq!(decoded.added_by, legacy.added_by);
+    }
+
+    #[test]
+    fn test_split() {
+        assert_eq!(split(b"DIDL\x00"), (0, &b"DIDL\x00"[..]));
+        assert_eq!(split(b"SREG\x01DIDL"), (1, &b"DIDL"[..]));
+        assert_eq!(split(b"SREG"), (0, &b"SREG"[..]));
+    }
+}
@@ -0,0 +1,70 @@
+use candid::{CandidType, Principal};
+use ic_cdk::api::call::RejectionCode;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::strike::StrikeStatus;
+
+/// The errors of the Candid API
+#[derive(Serialize, Deserialize, CandidType, Debug, PartialEq, Clone)]
+pub enum StrikeError {
+    /// The canister, or the appeal, revision, role or deposit the call refers to, doesn't exist
+    NotFound,
+    /// The caller isn't allowed to act on this canister or principal
+    Unauthorized,
+    /// An argument was rejected, `field` is its name in the Candid interface
+    InvalidField { field: String, reason: String },
+    /// The entry was already decided, e.g. resubmitting a trusted canister
+    AlreadyFinalized,
+    /// The same thing was done before, e.g. a second report of a canister by one reporter
+    AlreadyExists,
+    /// The status of the entry doesn't allow the call, e.g. appealing an entry that isn't blocked
+    InvalidStatus { status: StrikeStatus },
+    /// A quota is used up, the call may succeed again in `retry_after` seconds
+    RateLimited { retry_after: u64 },
+    /// A call to another canister, e.g. the management canister or the deposit ledger, failed
+    CallFailed { canister_id: Principal, reason: String },
+    /// The call can't be answered in this context, e.g. a certificate outside of a query
+    Unavailable { reason: String },
+}
+
+impl StrikeError {
+    pub fn invalid_field(field: &str, reason: impl Into<String>) -> Self {
+        Self::InvalidField {
+            field: field.to_string(),
+            reason: reason.into(),
+        }
+    }
+
+    pub fn empty(field: &str) -> Self {
+        Self::invalid_field(field, "cannot be empty")
+    }
+
+    pub fn invalid_cursor() -> Self {
+        Self::invalid_field("cursor", "is invalid")
+    }
+
+    /// For calls rejected by the system or the callee
+    pub fn rejected(canister_id: Principal, (code, message): (RejectionCode, String)) -> Self {
+        Self::CallFailed {
+            canister_id,
+            reason: format!("{:?} {}", code, message),
+        }
+    }
+}
+
+impl fmt::Display for StrikeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StrikeError::NotFound => write!(f, "Not found"),
+            StrikeError::Unauthorized => write!(f, "Unauthorized"),
+            StrikeError::InvalidField { field, reason } => write!(f, "{} {}", field, reason),
+            StrikeError::AlreadyFinalized => write!(f, "Canister already trusted or blocked"),
+            StrikeError::AlreadyExists => write!(f, "Already exists"),
+            StrikeError::InvalidStatus { status } => write!(f, "Not allowed while the canister is {:?}", status),
+            StrikeError::RateLimited { retry_after } => write!(f, "Rate limited, retry after {} seconds", retry_after),
+            StrikeError::CallFailed { canister_id, reason } => write!(f, "Call to {} failed: {}", canister_id, reason),
+            StrikeError::Unavailable { reason } => write!(f, "{}", reason),
+        }
+    }
+}
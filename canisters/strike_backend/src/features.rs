@@ -0,0 +1,68 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+use crate::error::StrikeError;
+use crate::memory::STATE;
+
+/// Parts of the canister an admin can pause, e.g. during an incident or a migration. Everything
+/// is enabled by default.
+#[derive(Serialize, Deserialize, CandidType, Debug, Clone, Copy, PartialEq)]
+pub struct FeatureToggles {
+    /// New submissions through `add_registry`
+    pub submissions: bool,
+    pub reports: bool,
+    pub appeals: bool,
+    /// The periodic check of the module hashes of trusted canisters
+    pub module_hash_monitor: bool,
+}
+
+impl Default for FeatureToggles {
+    fn default() -> Self {
+        Self {
+            submissions: true,
+            reports: true,
+            appeals: true,
+            module_hash_monitor: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Feature {
+    Submissions,
+    Reports,
+    Appeals,
+    ModuleHashMonitor,
+}
+
+impl FeatureToggles {
+    pub fn is_enabled(&self, feature: Feature) -> bool {
+        match feature {
+            Feature::Submissions => self.submissions,
+            Feature::Reports => self.reports,
+            Feature::Appeals => self.appeals,
+            Feature::ModuleHashMonitor => self.module_hash_monitor,
+        }
+    }
+}
+
+pub fn get_feature_toggles() -> FeatureToggles {
+    STATE.with(|s| s.borrow().features)
+}
+
+pub fn set_feature_toggles(features: FeatureToggles) -> FeatureToggles {
+    STATE.with(|s| std::mem::replace(&mut s.borrow_mut().features, features))
+}
+
+pub fn is_enabled(feature: Feature) -> bool {
+    get_feature_toggles().is_enabled(feature)
+}
+
+pub fn ensure_enabled(feature: Feature) -> Result<(), StrikeError> {
+    if !is_enabled(feature) {
+        return Err(StrikeError::Unavailable {
+            reason: format!("{:?} are paused", feature),
+        });
+    }
+    Ok(())
+}
@@ -1,18 +1,38 @@
 use candid::Principal;
 use ic_cdk::caller;
 
-use crate::admins::is_admin;
+use crate::roles::{effective_role, Role};
 
 pub fn caller_is_admin() -> Result<(), String> {
     let caller = caller();
 
-    if is_admin(caller) {
+    if effective_role(caller).map_or(false, Role::is_admin) {
         Ok(())
     } else {
         Err("Caller is not a admin".to_string())
     }
 }
 
+pub fn caller_is_reviewer() -> Result<(), String> {
+    let caller = caller();
+
+    if effective_role(caller).map_or(false, Role::can_review) {
+        Ok(())
+    } else {
+        Err("Caller is not a reviewer".to_string())
+    }
+}
+
+pub fn caller_has_role() -> Result<(), String> {
+    let caller = caller();
+
+    if effective_role(caller).is_some() {
+        Ok(())
+    } else {
+        Err("Caller has no role".to_string())
+    }
+}
+
 pub fn caller_is_not_anonymous() -> Result<(), String> {
     let caller = caller();
 
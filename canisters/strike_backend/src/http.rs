@@ -0,0 +1,347 @@
+use candid::{CandidType, Principal};
+use serde::{Deserialize, Serialize};
+
+use crate::certification;
+use crate::strike::{self, GetRegistriesParams, StrikeRegistry, StrikeStatus};
+use crate::types::CursorPagination;
+
+// Page size of the listings when the `limit` query parameter is missing
+const DEFAULT_LIST_LIMIT: u32 = 50;
+
+// How long boundary nodes and browsers may cache successful responses, in seconds
+const LOOKUP_MAX_AGE: u32 = 60;
+const LIST_MAX_AGE: u32 = 30;
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    #[serde(with = "serde_bytes")]
+    pub body: Vec<u8>,
+    pub certificate_version: Option<u16>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct HttpResponse {
+    pub status_code: u16,
+    pub headers: Vec<(String, String)>,
+    #[serde(with = "serde_bytes")]
+    pub body: Vec<u8>,
+    pub upgrade: Option<bool>,
+}
+
+/// The public view of a registry entry, without the contact details of the submitter
+#[derive(Serialize)]
+struct CanisterView<'a> {
+    canister_id: Principal,
+    status: StrikeStatus,
+    status_reason: Option<&'a str>,
+    module_hash: Option<&'a str>,
+    project_name: &'a str,
+    description: &'a str,
+    website_url: Option<&'a str>,
+    twitter: Option<&'a str>,
+    telegram: Option<&'a str>,
+    created_at: u64,
+}
+
+#[derive(Serialize)]
+struct ListView<'a> {
+    items: Vec<CanisterView<'a>>,
+    total: u32,
+    next_cursor: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ErrorView<'a> {
+    error: &'a str,
+}
+
+impl<'a> From<&'a StrikeRegistry> for CanisterView<'a> {
+    fn from(registry: &'a StrikeRegistry) -> Self {
+        Self {
+            canister_id: registry.canister_id,
+            status: registry.status,
+            status_reason: registry.status_reason.as_deref(),
+            module_hash: registry.module_hash.as_deref(),
+            project_name: &registry.project_name,
+            description: &registry.description,
+            website_url: registry.website_url.as_deref(),
+            twitter: registry.twitter.as_deref(),
+            telegram: registry.telegram.as_deref(),
+            created_at: registry.created_at,
+        }
+    }
+}
+
+impl HttpResponse {
+    fn json<T: Serialize>(status_code: u16, value: &T, max_age: Option<u32>) -> Self {
+        let body = serde_json::to_vec(value).expect("failed to serialize the response");
+        Self::json_body(status_code, body, max_age)
+    }
+
+    fn json_body(status_code: u16, body: Vec<u8>, max_age: Option<u32>) -> Self {
+        let cache_control = match max_age {
+            Some(max_age) => format!("public, max-age={}", max_age),
+            None => "no-store".to_string(),
+        };
+
+        let mut headers = cors_headers();
+        headers.push(("Content-Type".to_string(), "application/json; charset=utf-8".to_string()));
+        headers.push(("Cache-Control".to_string(), cache_control));
+        headers.push(("X-Content-Type-Options".to_string(), "nosniff".to_string()));
+
+        Self {
+            status_code,
+            headers,
+            body,
+            upgrade: None,
+        }
+    }
+
+    fn error(status_code: u16, message: &str) -> Self {
+        Self::json(status_code, &ErrorView { error: message }, None)
+    }
+
+    /// A 404 for `path`, certified through the fallback path when served from a query
+    fn not_found(path: &str, call: Call) -> Self {
+        let mut response = Self::json_body(404, not_found_body(), None);
+        if call == Call::Query {
+            response.headers.extend(certification::http_certificate_header(path));
+        }
+        response
+    }
+
+    fn preflight() -> Self {
+        let mut headers = cors_headers();
+        headers.push(("Access-Control-Max-Age".to_string(), "86400".to_string()));
+
+        Self {
+            status_code: 204,
+            headers,
+            body: vec![],
+            upgrade: None,
+        }
+    }
+
+    fn upgrade() -> Self {
+        Self {
+            status_code: 200,
+            headers: vec![],
+            body: vec![],
+            upgrade: Some(true),
+        }
+    }
+}
+
+fn cors_headers() -> Vec<(String, String)> {
+    vec![
+        ("Access-Control-Allow-Origin".to_string(), "*".to_string()),
+        ("Access-Control-Allow-Methods".to_string(), "GET, HEAD, OPTIONS".to_string()),
+        ("Access-Control-Allow-Headers".to_string(), "Content-Type".to_string()),
+    ]
+}
+
+/// Returns the value of a query string parameter, e.g. `cursor` in `cursor=abc&limit=10`
+fn query_param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+/// The path of the lookup of `canister_id`, which is certified under `http_assets`
+pub fn canister_path(canister_id: Principal) -> String {
+    format!("/v1/canisters/{}", canister_id)
+}
+
+/// The body of the lookup of `registry`, its hash is certified so it has to be deterministic
+pub fn canister_body(registry: &StrikeRegistry) -> Vec<u8> {
+    serde_json::to_vec(&CanisterView::from(registry)).expect("failed to serialize the response")
+}
+
+/// The body of every 404, certified under `NOT_FOUND_PATH` so that it also covers lookups of
+/// unknown canisters and unknown routes
+pub fn not_found_body() -> Vec<u8> {
+    serde_json::to_vec(&ErrorView { error: "Not found" }).expect("failed to serialize the response")
+}
+
+fn get_canister(path: &str, canister_id: &str, call: Call) -> HttpResponse {
+    let canister_id = match Principal::from_text(canister_id) {
+        // The gateway verifies the response against the requested path, which has to be the
+        // certified one
+        Ok(parsed) if call == Call::Update || parsed.to_text() == canister_id => parsed,
+        Err(_) if call == Call::Update => return HttpResponse::error(400, "Invalid canister ID"),
+        _ => return HttpResponse::upgrade(),
+    };
+
+    let Some(registry) = strike::get_strike_by_canister_id(canister_id) else {
+        return HttpResponse::not_found(path, call);
+    };
+
+    let mut response = HttpResponse::json_body(200, canister_body(&registry), Some(LOOKUP_MAX_AGE));
+    if let Some(header) = certification::http_certificate_header(&canister_path(canister_id)) {
+        response.headers.push(header);
+    }
+    response
+}
+
+fn list_canisters(status: StrikeStatus, query: &str) -> HttpResponse {
+    let limit = match query_param(query, "limit").map(str::parse) {
+        Some(Ok(limit)) => limit,
+        Some(Err(_)) => return HttpResponse::error(400, "Invalid limit"),
+        None => DEFAULT_LIST_LIMIT,
+    };
+    let start_after = query_param(query, "cursor").filter(|cursor| !cursor.is_empty());
+
+    let params = GetRegistriesParams {
+        status: Some(status),
+        pagination: CursorPagination {
+            start_after: start_after.map(str::to_string),
+            limit,
+        },
+        order: None,
+    };
+
+    match strike::get_registries(params) {
+        Ok(page) => {
+            let view = ListView {
+                items: page.items.iter().map(CanisterView::from).collect(),
+                total: page.total,
+                next_cursor: page.next_cursor,
+            };
+            HttpResponse::json(200, &view, Some(LIST_MAX_AGE))
+        }
+        Err(err) => HttpResponse::error(400, &err.to_string()),
+    }
+}
+
+/// Queries only answer with responses that the gateway can verify against the certified data and
+/// upgrade the others, e.g. listings and 400s, to update calls whose responses go through consensus
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Call {
+    Query,
+    Update,
+}
+
+fn route(request: &HttpRequest, call: Call) -> HttpResponse {
+    let (requested, query) = request.url.split_once('?').unwrap_or((&request.url, ""));
+    // Paths are certified without a trailing slash
+    let path = match requested.strip_suffix('/') {
+        Some(path) if !path.is_empty() => path,
+        _ => requested,
+    };
+    // The gateway looks up the decoded path, which the witness of the encoded one doesn't cover,
+    // and the requested one, which is only certified once normalized
+    if call == Call::Query && (path.contains('%') || path != requested) {
+        return HttpResponse::upgrade();
+    }
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+    let mut response = match (segments.as_slice(), call) {
+        (["v1", "canisters", canister_id], call) => get_canister(path, canister_id, call),
+        (["v1", "trusted" | "blocked"], Call::Query) => HttpResponse::upgrade(),
+        (["v1", "trusted"], Call::Update) => list_canisters(StrikeStatus::Trusted, query),
+        (["v1", "blocked"], Call::Update) => list_canisters(StrikeStatus::Blocked, query),
+        _ => HttpResponse::not_found(path, call),
+    };
+
+    if request.method == "HEAD" {
+        response.body.clear();
+    }
+    response
+}
+
+pub fn http_request(request: HttpRequest) -> HttpResponse {
+    match request.method.as_str() {
+        "GET" => route(&request, Call::Query),
+        // The certified hashes are those of the bodies HEAD leaves out, so it can't be verified
+        "HEAD" => HttpResponse::upgrade(),
+        "OPTIONS" => HttpResponse::preflight(),
+        // Let the boundary node retry as an update call, which answers with a proper error
+        _ => HttpResponse::upgrade(),
+    }
+}
+
+pub fn http_request_update(request: HttpRequest) -> HttpResponse {
+    match request.method.as_str() {
+        "GET" | "HEAD" => route(&request, Call::Update),
+        "OPTIONS" => HttpResponse::preflight(),
+        _ => HttpResponse::error(405, "Method not allowed"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: &str, url: &str) -> HttpRequest {
+        HttpRequest {
+            method: method.to_string(),
+            url: url.to_string(),
+            headers: vec![],
+            body: vec![],
+            certificate_version: None,
+        }
+    }
+
+    #[test]
+    fn test_query_param() {
+        assert_eq!(query_param("cursor=abc&limit=10", "limit"), Some("10"));
+        assert_eq!(query_param("cursor=&limit=10", "cursor"), Some(""));
+        assert_eq!(query_param("", "cursor"), None);
+    }
+
+    #[test]
+    fn test_unknown_routes() {
+        let response = http_request_update(request("GET", "/v2/whatever"));
+        assert_eq!(response.status_code, 404);
+        assert!(response
+            .headers
+            .contains(&("Access-Control-Allow-Origin".to_string(), "*".to_string())));
+        assert_eq!(response.body, not_found_body());
+
+        let response = http_request(request("GET", "/v1/canisters/not-a-principal"));
+        assert_eq!(response.upgrade, Some(true));
+        let response = http_request_update(request("GET", "/v1/canisters/not-a-principal"));
+        assert_eq!(response.status_code, 400);
+
+        let response = http_request(request("GET", "/v1/trusted?limit=10"));
+        assert_eq!(response.upgrade, Some(true));
+        let response = http_request(request("GET", "/v1/canisters/%61aaaa-aa"));
+        assert_eq!(response.upgrade, Some(true));
+
+        let response = http_request(request("OPTIONS", "/v1/trusted"));
+        assert_eq!(response.status_code, 204);
+
+        let response = http_request(request("POST", "/v1/trusted"));
+        assert_eq!(response.upgrade, Some(true));
+
+        let response = http_request_update(request("POST", "/v1/trusted"));
+        assert_eq!(response.status_code, 405);
+    }
+
+    #[test]
+    fn test_head_goes_through_updates() {
+        let response = http_request(request("HEAD", "/v1/canisters/aaaaa-aa"));
+        assert_eq!(response.upgrade, Some(true));
+
+        let response = http_request_update(request("HEAD", "/v2/whatever"));
+        assert_eq!(response.status_code, 404);
+        assert!(response.body.is_empty());
+    }
+
+    #[test]
+    fn test_trailing_slash() {
+        let response = http_request(request("GET", "/v1/canisters/aaaaa-aa/"));
+        assert_eq!(response.upgrade, Some(true));
+
+        let response = http_request_update(request("GET", "/v1/canisters/not-a-principal/"));
+        assert_eq!(response.status_code, 400);
+        let response = http_request_update(request("GET", "/v1/canisters/aaaaa-aa/"));
+        assert_eq!(response.status_code, 404);
+        assert_eq!(response.body, not_found_body());
+    }
+}
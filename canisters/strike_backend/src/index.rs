@@ -0,0 +1,624 @@
+use candid::Principal;
+use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
+use std::borrow::Cow;
+use std::ops::Bound::{Excluded, Included, Unbounded};
+use tracing::info;
+
+use crate::error::StrikeError;
+use crate::memory::{Memory, CREATED_INDEX, NAME_INDEX, OWNER_INDEX, REGISTRY, STATUS_COUNTS, STATUS_INDEX, STATUS_NAME_INDEX};
+use crate::strike::{StrikeRegistry, StrikeStatus};
+
+// Principals are stored length prefixed and zero padded, so that every key has a fixed size
+pub const PRINCIPAL_SIZE: usize = 1 + Principal::MAX_LENGTH_IN_BYTES;
+// Project names are folded and truncated to this many bytes for sorting, ties are broken by canister ID
+const NAME_SIZE: usize = 64;
+
+pub const MIN_PRINCIPAL: Principal = Principal::from_slice(&[]);
+pub const MAX_PRINCIPAL: Principal = Principal::from_slice(&[0xff; Principal::MAX_LENGTH_IN_BYTES]);
+
+/// Orders the entries of a status by creation time
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct StatusIndexKey {
+    pub status: StrikeStatus,
+    pub created_at: u64,
+    pub canister_id: Principal,
+}
+
+/// Orders all entries by creation time
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct CreatedIndexKey {
+    pub created_at: u64,
+    pub canister_id: Principal,
+}
+
+/// Orders the entries of a submitter by creation time
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct OwnerIndexKey {
+    pub added_by: Principal,
+    pub created_at: u64,
+    pub canister_id: Principal,
+}
+
+/// Orders all entries by project name
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub struct NameIndexKey {
+    pub name: String,
+    pub canister_id: Principal,
+}
+
+/// Orders the entries of a status by project name
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub struct StatusNameIndexKey {
+    pub status: StrikeStatus,
+    pub name: String,
+    pub canister_id: Principal,
+}
+
+/// An index key that points to a registry entry
+pub trait IndexKey: Storable + Ord + Clone {
+    fn canister_id(&self) -> Principal;
+
+    /// Whether `bytes` of the key's size can be passed to `from_bytes`, which panics on values
+    /// that no key encodes to. Cursors come from callers, so they're checked before decoding.
+    fn is_valid(_bytes: &[u8]) -> bool {
+        true
+    }
+}
+
+impl StatusIndexKey {
+    /// The smallest key of the given status, to start ascending scans from
+    pub fn first(status: StrikeStatus) -> Self {
+        Self {
+            status,
+            created_at: 0,
+            canister_id: MIN_PRINCIPAL,
+        }
+    }
+
+    /// The largest key of the given status, to start descending scans from
+    pub fn last(status: StrikeStatus) -> Self {
+        Self {
+            status,
+            created_at: u64::MAX,
+            canister_id: MAX_PRINCIPAL,
+        }
+    }
+}
+
+impl CreatedIndexKey {
+    pub fn first() -> Self {
+        Self {
+            created_at: 0,
+            canister_id: MIN_PRINCIPAL,
+        }
+    }
+
+    pub fn last() -> Self {
+        Self {
+            created_at: u64::MAX,
+            canister_id: MAX_PRINCIPAL,
+        }
+    }
+}
+
+impl OwnerIndexKey {
+    pub fn first(added_by: Principal) -> Self {
+        Self {
+            added_by,
+            created_at: 0,
+            canister_id: MIN_PRINCIPAL,
+        }
+    }
+
+    pub fn last(added_by: Principal) -> Self {
+        Self {
+            added_by,
+            created_at: u64::MAX,
+            canister_id: MAX_PRINCIPAL,
+        }
+    }
+}
+
+impl NameIndexKey {
+    pub fn first() -> Self {
+        Self {
+            name: String::new(),
+            canister_id: MIN_PRINCIPAL,
+        }
+    }
+}
+
+impl StatusNameIndexKey {
+    pub fn first(status: StrikeStatus) -> Self {
+        Self {
+            status,
+            name: String::new(),
+            canister_id: MIN_PRINCIPAL,
+        }
+    }
+}
+
+impl IndexKey for StatusIndexKey {
+    fn canister_id(&self) -> Principal {
+        self.canister_id
+    }
+
+    fn is_valid(bytes: &[u8]) -> bool {
+        StrikeStatus::try_from_u8(bytes[0]).is_some()
+    }
+}
+
+impl IndexKey for CreatedIndexKey {
+    fn canister_id(&self) -> Principal {
+        self.canister_id
+    }
+}
+
+impl IndexKey for OwnerIndexKey {
+    fn canister_id(&self) -> Principal {
+        self.canister_id
+    }
+}
+
+impl IndexKey for NameIndexKey {
+    fn canister_id(&self) -> Principal {
+        self.canister_id
+    }
+}
+
+impl IndexKey for StatusNameIndexKey {
+    fn canister_id(&self) -> Principal {
+        self.canister_id
+    }
+
+    fn is_valid(bytes: &[u8]) -> bool {
+        StrikeStatus::try_from_u8(bytes[0]).is_some()
+    }
+}
+
+/// Case folds a project name and truncates it to fit in an index key
+fn sort_name(project_name: &str) -> String {
+    let mut name = project_name.trim().to_lowercase();
+    let mut len = name.len().min(NAME_SIZE);
+    while !name.is_char_boundary(len) {
+        len -= 1;
+    }
+    name.truncate(len);
+    name
+}
+
+pub fn write_principal(bytes: &mut Vec<u8>, principal: &Principal) {
+    let slice = principal.as_slice();
+    let mut padded = [0u8; PRINCIPAL_SIZE];
+    padded[0] = slice.len() as u8;
+    padded[1..=slice.len()].copy_from_slice(slice);
+    bytes.extend_from_slice(&padded);
+}
+
+pub fn read_principal(bytes: &[u8]) -> Principal {
+    let len = (bytes[0] as usize).min(Principal::MAX_LENGTH_IN_BYTES);
+    Principal::from_slice(&bytes[1..=len])
+}
+
+fn write_name(bytes: &mut Vec<u8>, name: &str) {
+    let mut padded = [0u8; 1 + NAME_SIZE];
+    padded[0] = name.len() as u8;
+    padded[1..=name.len()].copy_from_slice(name.as_bytes());
+    bytes.extend_from_slice(&padded);
+}
+
+fn read_name(bytes: &[u8]) -> String {
+    let len = (bytes[0] as usize).min(NAME_SIZE);
+    String::from_utf8_lossy(&bytes[1..=len]).into_owned()
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    u64::from_be_bytes(bytes[..8].try_into().unwrap())
+}
+
+impl Storable for StatusIndexKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = Vec::with_capacity(1 + 8 + PRINCIPAL_SIZE);
+        bytes.push(self.status as u8);
+        bytes.extend_from_slice(&self.created_at.to_be_bytes());
+        write_principal(&mut bytes, &self.canister_id);
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Self {
+            status: StrikeStatus::from_u8(bytes[0]),
+            created_at: read_u64(&bytes[1..]),
+            canister_id: read_principal(&bytes[9..]),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: (1 + 8 + PRINCIPAL_SIZE) as u32,
+        is_fixed_size: true,
+    };
+}
+
+impl Storable for CreatedIndexKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = Vec::with_capacity(8 + PRINCIPAL_SIZE);
+        bytes.extend_from_slice(&self.created_at.to_be_bytes());
+        write_principal(&mut bytes, &self.canister_id);
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Self {
+            created_at: read_u64(&bytes),
+            canister_id: read_principal(&bytes[8..]),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: (8 + PRINCIPAL_SIZE) as u32,
+        is_fixed_size: true,
+    };
+}
+
+impl Storable for OwnerIndexKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = Vec::with_capacity(PRINCIPAL_SIZE + 8 + PRINCIPAL_SIZE);
+        write_principal(&mut bytes, &self.added_by);
+        bytes.extend_from_slice(&self.created_at.to_be_bytes());
+        write_principal(&mut bytes, &self.canister_id);
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Self {
+            added_by: read_principal(&bytes),
+            created_at: read_u64(&bytes[PRINCIPAL_SIZE..]),
+            canister_id: read_principal(&bytes[PRINCIPAL_SIZE + 8..]),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: (PRINCIPAL_SIZE + 8 + PRINCIPAL_SIZE) as u32,
+        is_fixed_size: true,
+    };
+}
+
+impl Storable for NameIndexKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = Vec::with_capacity(1 + NAME_SIZE + PRINCIPAL_SIZE);
+        write_name(&mut bytes, &self.name);
+        write_principal(&mut bytes, &self.canister_id);
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Self {
+            name: read_name(&bytes),
+            canister_id: read_principal(&bytes[1 + NAME_SIZE..]),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: (1 + NAME_SIZE + PRINCIPAL_SIZE) as u32,
+        is_fixed_size: true,
+    };
+}
+
+impl Storable for StatusNameIndexKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = Vec::with_capacity(1 + 1 + NAME_SIZE + PRINCIPAL_SIZE);
+        bytes.push(self.status as u8);
+        write_name(&mut bytes, &self.name);
+        write_principal(&mut bytes, &self.canister_id);
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Self {
+            status: StrikeStatus::from_u8(bytes[0]),
+            name: read_name(&bytes[1..]),
+            canister_id: read_principal(&bytes[2 + NAME_SIZE..]),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: (1 + 1 + NAME_SIZE + PRINCIPAL_SIZE) as u32,
+        is_fixed_size: true,
+    };
+}
+
+impl From<&StrikeRegistry> for StatusIndexKey {
+    fn from(registry: &StrikeRegistry) -> Self {
+        Self {
+            status: registry.status,
+            created_at: registry.created_at,
+            canister_id: registry.canister_id,
+        }
+    }
+}
+
+impl From<&StrikeRegistry> for CreatedIndexKey {
+    fn from(registry: &StrikeRegistry) -> Self {
+        Self {
+            created_at: registry.created_at,
+            canister_id: registry.canister_id,
+        }
+    }
+}
+
+impl From<&StrikeRegistry> for OwnerIndexKey {
+    fn from(registry: &StrikeRegistry) -> Self {
+        Self {
+            added_by: registry.added_by,
+            created_at: registry.created_at,
+            canister_id: registry.canister_id,
+        }
+    }
+}
+
+impl From<&StrikeRegistry> for NameIndexKey {
+    fn from(registry: &StrikeRegistry) -> Self {
+        Self {
+            name: sort_name(&registry.project_name),
+            canister_id: registry.canister_id,
+        }
+    }
+}
+
+impl From<&StrikeRegistry> for StatusNameIndexKey {
+    fn from(registry: &StrikeRegistry) -> Self {
+        Self {
+            status: registry.status,
+            name: sort_name(&registry.project_name),
+            canister_id: registry.canister_id,
+        }
+    }
+}
+
+/// Keeps the indexes consistent with a write to `REGISTRY`, replacing `old` with `new`
+pub fn reindex(old: Option<&StrikeRegistry>, new: Option<&StrikeRegistry>) {
+    if let Some(old) = old {
+        STATUS_INDEX.with(|s| s.borrow_mut().remove(&old.into()));
+        CREATED_INDEX.with(|s| s.borrow_mut().remove(&old.into()));
+        OWNER_INDEX.with(|s| s.borrow_mut().remove(&old.into()));
+        NAME_INDEX.with(|s| s.borrow_mut().remove(&old.into()));
+        STATUS_NAME_INDEX.with(|s| s.borrow_mut().remove(&old.into()));
+        update_status_count(old.status, |count| count.saturating_sub(1));
+    }
+
+    if let Some(new) = new {
+        STATUS_INDEX.with(|s| s.borrow_mut().insert(new.into(), ()));
+        CREATED_INDEX.with(|s| s.borrow_mut().insert(new.into(), ()));
+        OWNER_INDEX.with(|s| s.borrow_mut().insert(new.into(), ()));
+        NAME_INDEX.with(|s| s.borrow_mut().insert(new.into(), ()));
+        STATUS_NAME_INDEX.with(|s| s.borrow_mut().insert(new.into(), ()));
+        update_status_count(new.status, |count| count + 1);
+    }
+}
+
+/// A page of registry entries found by walking an index
+pub struct ScanPage {
+    pub canister_ids: Vec<Principal>,
+    pub next_cursor: Option<String>,
+}
+
+/// Walks up to `limit` keys of an index that satisfy `in_scope`. Ascending scans start at `start`
+/// and descending scans below it, unless a cursor from a previous page is given.
+pub fn scan<K: IndexKey>(
+    index: &StableBTreeMap<K, (), Memory>,
+    start: K,
+    start_after: Option<&str>,
+    limit: usize,
+    descending: bool,
+    in_scope: impl Fn(&K) -> bool,
+) -> Result<ScanPage, StrikeError> {
+    let start_after = start_after.map(decode_cursor::<K>).transpose()?;
+
+    // One more key than requested tells whether there is a next page
+    let mut keys = Vec::with_capacity(limit + 1);
+    if descending {
+        let mut bound = start_after.unwrap_or(start);
+        while keys.len() <= limit {
+            match index.iter_upper_bound(&bound).next() {
+                Some((key, _)) if in_scope(&key) => {
+                    bound = key.clone();
+                    keys.push(key);
+                }
+                _ => break,
+            }
+        }
+    } else {
+        let lower = match start_after {
+            Some(key) => Excluded(key),
+            None => Included(start),
+        };
+        keys.extend(
+            index
+                .range((lower, Unbounded))
+                .map(|(key, _)| key)
+                .take_while(|key| in_scope(key))
+                .take(limit + 1),
+        );
+    }
+
+    let next_cursor = if keys.len() > limit {
+        keys.truncate(limit);
+        keys.last().map(encode_cursor)
+    } else {
+        None
+    };
+
+    Ok(ScanPage {
+        canister_ids: keys.iter().map(IndexKey::canister_id).collect(),
+        next_cursor,
+    })
+}
+
+fn encode_cursor<K: Storable>(key: &K) -> String {
+    hex::encode(key.to_bytes())
+}
+
+fn decode_cursor<K: IndexKey>(cursor: &str) -> Result<K, StrikeError> {
+    let bytes = hex::decode(cursor).map_err(|_| StrikeError::invalid_cursor())?;
+    let is_key_sized = matches!(K::BOUND, Bound::Bounded { max_size, .. } if bytes.len() == max_size as usize);
+    if !is_key_sized || !K::is_valid(&bytes) {
+        return Err(StrikeError::invalid_cursor());
+    }
+
+    // Only keys written by `encode_cursor` are accepted, e.g. no padding after a principal
+    let key = K::from_bytes(Cow::Borrowed(&bytes));
+    if key.to_bytes().as_ref() != bytes.as_slice() {
+        return Err(StrikeError::invalid_cursor());
+    }
+    Ok(key)
+}
+
+fn update_status_count(status: StrikeStatus, f: impl FnOnce(u64) -> u64) {
+    STATUS_COUNTS.with(|s| {
+        let mut counts = s.borrow_mut();
+        let count = f(counts.get(&(status as u8)).unwrap_or_default());
+        counts.insert(status as u8, count);
+    });
+}
+
+pub fn status_count(status: StrikeStatus) -> u64 {
+    STATUS_COUNTS.with(|s| s.borrow().get(&(status as u8)).unwrap_or_default())
+}
+
+/// The number of entries a submitter added, counted from their range of the owner index
+pub fn owner_count(added_by: Principal) -> u64 {
+    OWNER_INDEX.with(|s| {
+        s.borrow()
+            .range(OwnerIndexKey::first(added_by)..=OwnerIndexKey::last(added_by))
+            .count() as u64
+    })
+}
+
+/// Builds the indexes from scratch when they don't cover the registry, e.g. after upgrading
+/// from a version that didn't maintain them
+pub fn rebuild_if_missing() {
+    let registry_len = REGISTRY.with(|s| s.borrow().len());
+    let index_lens = [
+        STATUS_INDEX.with(|s| s.borrow().len()),
+        CREATED_INDEX.with(|s| s.borrow().len()),
+        OWNER_INDEX.with(|s| s.borrow().len()),
+        NAME_INDEX.with(|s| s.borrow().len()),
+        STATUS_NAME_INDEX.with(|s| s.borrow().len()),
+    ];
+    if index_lens.iter().all(|len| *len == registry_len) {
+        return;
+    }
+
+    info!("Rebuilding registry indexes for {} entries", registry_len);
+
+    STATUS_INDEX.with(|s| s.borrow_mut().clear_new());
+    CREATED_INDEX.with(|s| s.borrow_mut().clear_new());
+    OWNER_INDEX.with(|s| s.borrow_mut().clear_new());
+    NAME_INDEX.with(|s| s.borrow_mut().clear_new());
+    STATUS_NAME_INDEX.with(|s| s.borrow_mut().clear_new());
+    STATUS_COUNTS.with(|s| s.borrow_mut().clear_new());
+
+    REGISTRY.with(|s| {
+        for (_, registry) in s.borrow().iter() {
+            reindex(None, Some(&registry));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALI: Principal = Principal::from_slice(&[0; 24]);
+    const BOB: Principal = Principal::from_slice(&[1; 10]);
+
+    #[test]
+    fn test_index_keys_roundtrip() {
+        let key = StatusIndexKey {
+            status: StrikeStatus::Blocked,
+            created_at: 42,
+            canister_id: ALI,
+        };
+        assert_eq!(StatusIndexKey::from_bytes(key.to_bytes()), key);
+
+        let key = CreatedIndexKey {
+            created_at: 42,
+            canister_id: BOB,
+        };
+        assert_eq!(CreatedIndexKey::from_bytes(key.to_bytes()), key);
+
+        let key = OwnerIndexKey {
+            added_by: ALI,
+            created_at: 42,
+            canister_id: BOB,
+        };
+        assert_eq!(OwnerIndexKey::from_bytes(key.to_bytes()), key);
+
+        let key = StatusNameIndexKey {
+            status: StrikeStatus::Trusted,
+            name: sort_name(" Ünïcode Project "),
+            canister_id: BOB,
+        };
+        assert_eq!(StatusNameIndexKey::from_bytes(key.to_bytes()), key);
+    }
+
+    #[test]
+    fn test_sort_name_truncates_at_char_boundary() {
+        assert_eq!(sort_name("  Strike "), "strike");
+
+        let name = sort_name(&"é".repeat(NAME_SIZE));
+        assert!(name.len() <= NAME_SIZE);
+        assert_eq!(name, "é".repeat(NAME_SIZE / 2));
+    }
+
+    #[test]
+    fn test_index_keys_order_matches_bytes() {
+        let first = StatusIndexKey {
+            status: StrikeStatus::Submitted,
+            created_at: u64::MAX,
+            canister_id: ALI,
+        };
+        let second = StatusIndexKey {
+            status: StrikeStatus::Trusted,
+            created_at: 1,
+            canister_id: BOB,
+        };
+        let third = StatusIndexKey {
+            status: StrikeStatus::Trusted,
+            created_at: 256,
+            canister_id: ALI,
+        };
+        assert!(first < second && first.to_bytes() < second.to_bytes());
+        assert!(second < third && second.to_bytes() < third.to_bytes());
+        assert!(StatusIndexKey::first(StrikeStatus::Trusted) < second);
+    }
+
+    #[test]
+    fn test_rejects_malformed_cursors() {
+        let key = StatusIndexKey {
+            status: StrikeStatus::Trusted,
+            created_at: 42,
+            canister_id: BOB,
+        };
+        let cursor = encode_cursor(&key);
+        assert_eq!(decode_cursor::<StatusIndexKey>(&cursor), Ok(key));
+
+        let mut bytes = key.to_bytes().into_owned();
+        bytes[0] = 7;
+        assert_eq!(
+            decode_cursor::<StatusIndexKey>(&hex::encode(&bytes)),
+            Err(StrikeError::invalid_cursor())
+        );
+
+        // Bytes after the principal have to be zero
+        let mut bytes = key.to_bytes().into_owned();
+        *bytes.last_mut().unwrap() = 1;
+        assert_eq!(
+            decode_cursor::<StatusIndexKey>(&hex::encode(&bytes)),
+            Err(StrikeError::invalid_cursor())
+        );
+
+        assert_eq!(decode_cursor::<StatusIndexKey>("00"), Err(StrikeError::invalid_cursor()));
+        assert_eq!(decode_cursor::<StatusIndexKey>("zz"), Err(StrikeError::invalid_cursor()));
+    }
+}
@@ -1,71 +1,451 @@
-mod admins;
+mod appeals;
+mod approvals;
+mod audit;
+mod certification;
+mod deposits;
+mod encoding;
+mod error;
+mod features;
 mod guards;
+mod http;
+mod index;
 mod lifecycle;
+mod limits;
+mod management;
 mod memory;
+mod monitor;
+mod reports;
+mod review;
+mod revisions;
+mod roles;
+mod search;
 mod serializer;
+mod snapshot;
+mod stats;
 mod strike;
 mod types;
+mod validation;
 
 #[cfg(test)]
 mod test;
 
 use crate::guards::*;
-use candid::Principal;
-use ic_cdk::{query, update};
-use strike::{AddRegistryParams, GetRegistriesParams, StrikeRegistry, UpdateRegistryStatusParams};
-use types::PaginatedResponse;
+use appeals::Appeal;
+use approvals::SubmitterApproval;
+use audit::{AuditAction, AuditEntry, AuditRecord, AuditValue, GetAuditLogParams};
+use candid::{Nat, Principal};
+use certification::CertifiedStrike;
+use deposits::{Deposit, DepositConfig};
+use error::StrikeError;
+use features::FeatureToggles;
+use http::{HttpRequest, HttpResponse};
+use ic_cdk::{inspect_message, query, update};
+use limits::SubmissionLimits;
+use monitor::MonitorRuns;
+use reports::{Report, ReportCategory, ReportQueueEntry};
+use review::Review;
+use revisions::{Revision, UpdateRegistryDetailsParams};
+use roles::{Role, RoleAssignment};
+use search::SearchFilters;
+use snapshot::{ImportProgress, SnapshotChunk, SnapshotCursor, SnapshotExport, SnapshotManifest};
+use stats::RegistryStats;
+use strike::{
+    AddRegistryParams, GetRegistriesParams, MyRegistration, StrikeRegistry, StrikeStatus, StrikeSummary,
+    UpdateRegistryStatusParams,
+};
+use types::{CursorPagination, PaginatedResponse};
 
-/// Admin
-#[update(guard = "caller_is_admin")]
-pub fn add_admin(admin: Principal) -> Result<(), String> {
+/// Roles
+#[update(guard = "caller_has_role")]
+pub fn grant_role(principal: Principal, role: Role) -> Result<(), StrikeError> {
     let caller = ic_cdk::api::caller();
-    admins::add_admins(caller, [admin].to_vec())
+    let old = roles::grant_role(roles::effective_role(caller), principal, role)?;
+
+    audit::record(
+        caller,
+        AuditRecord::role(principal, AuditAction::GrantRole).values(old.map(AuditValue::Role), Some(AuditValue::Role(role))),
+    );
+    Ok(())
 }
 
-#[update(guard = "caller_is_admin")]
-pub fn remove_admin(admin: Principal) -> Result<(), String> {
+#[update(guard = "caller_has_role")]
+pub fn revoke_role(principal: Principal) -> Result<(), StrikeError> {
     let caller = ic_cdk::api::caller();
-    admins::remove_admins(caller, [admin].to_vec())
+    let old = roles::revoke_role(roles::effective_role(caller), principal)?;
+
+    audit::record(
+        caller,
+        AuditRecord::role(principal, AuditAction::RevokeRole).values(Some(AuditValue::Role(old)), None),
+    );
+    Ok(())
+}
+
+#[query(guard = "caller_has_role")]
+pub fn list_roles() -> Vec<RoleAssignment> {
+    roles::list_roles()
+}
+
+/// Admin, kept for existing clients. Admins are the principals with the Owner or Admin role.
+#[update(guard = "caller_is_admin")]
+pub fn add_admin(admin: Principal) -> Result<(), StrikeError> {
+    grant_role(admin, Role::Admin)
+}
+
+#[update(guard = "caller_is_admin")]
+pub fn remove_admin(admin: Principal) -> Result<(), StrikeError> {
+    if roles::get_role(admin) != Some(Role::Admin) {
+        return Err(StrikeError::NotFound);
+    }
+
+    revoke_role(admin)
 }
 
 #[query]
 pub fn is_admin(user: Principal) -> bool {
-    admins::is_admin(user)
+    roles::effective_role(user).map_or(false, Role::is_admin)
 }
 
 #[update(guard = "caller_is_admin")]
 pub fn get_admins() -> Vec<Principal> {
-    admins::get_admins()
+    roles::list_roles()
+        .into_iter()
+        .filter(|assignment| assignment.role.is_admin())
+        .map(|assignment| assignment.principal)
+        .collect()
 }
 
 #[query]
 pub fn get_strike_by_canister_id(canister_id: Principal) -> Option<StrikeRegistry> {
-    strike::get_strike_by_canister_id(canister_id)
+    let caller = ic_cdk::api::caller();
+    strike::get_strike_by_canister_id(canister_id).map(|registry| roles::redact_for(caller, registry))
+}
+
+/// Summaries of up to `get_max_batch_lookup` canisters
+#[query]
+pub fn get_strikes_by_canister_ids(canister_ids: Vec<Principal>) -> Result<Vec<StrikeSummary>, StrikeError> {
+    strike::get_strikes_by_canister_ids(canister_ids)
+}
+
+/// The same as `get_strikes_by_canister_ids`, for composite queries of other canisters
+#[query(composite = true)]
+pub fn get_strikes_by_canister_ids_composite(canister_ids: Vec<Principal>) -> Result<Vec<StrikeSummary>, StrikeError> {
+    strike::get_strikes_by_canister_ids(canister_ids)
+}
+
+#[query]
+pub fn get_max_batch_lookup() -> u32 {
+    limits::get_max_batch_lookup()
+}
+
+#[update(guard = "caller_is_admin")]
+pub fn set_max_batch_lookup(max_batch_lookup: u32) -> Result<(), StrikeError> {
+    let old = limits::set_max_batch_lookup(max_batch_lookup)?;
+
+    audit::record(
+        ic_cdk::api::caller(),
+        AuditRecord::config(AuditAction::SetMaxBatchLookup).values(
+            Some(AuditValue::MaxBatchLookup(old)),
+            Some(AuditValue::MaxBatchLookup(max_batch_lookup)),
+        ),
+    );
+    Ok(())
+}
+
+#[query]
+pub fn get_certified_strike_by_canister_id(canister_id: Principal) -> Result<CertifiedStrike, StrikeError> {
+    let caller = ic_cdk::api::caller();
+    let mut certified = certification::get_certified_strike(canister_id)?;
+    certified.registry = certified.registry.map(|registry| roles::redact_for(caller, registry));
+    Ok(certified)
 }
 
 // user
 #[update(guard = "caller_is_not_anonymous")]
-pub fn add_registry(params: AddRegistryParams) -> Result<(), String> {
-    strike::add_registry(
-        params.canister_id,
-        params.name,
-        params.email,
-        params.telegram,
-        params.twitter,
-        params.project_name,
-        params.description,
-        params.website_url,
-    )
+pub async fn add_registry(params: AddRegistryParams) -> Result<(), StrikeError> {
+    strike::add_registry(params).await
+}
+
+/// The caller's own entries in full, with the notes reviewers left on them
+#[query(guard = "caller_is_not_anonymous")]
+pub fn get_my_registrations(cursor: CursorPagination) -> Result<PaginatedResponse<MyRegistration>, StrikeError> {
+    strike::get_my_registrations(cursor)
+}
+
+/// Lets `submitter` register a canister the caller controls, see `AddRegistryParams::controller`
+#[update(guard = "caller_is_not_anonymous")]
+pub async fn approve_submitter(canister_id: Principal, submitter: Principal) -> Result<(), StrikeError> {
+    approvals::approve_submitter(canister_id, submitter).await
+}
+
+#[query]
+pub fn get_submitter_approval(canister_id: Principal) -> Option<SubmitterApproval> {
+    approvals::get_submitter_approval(canister_id)
+}
+
+#[update(guard = "caller_is_not_anonymous")]
+pub fn withdraw_registry(canister_id: Principal, reason: Option<String>) -> Result<(), StrikeError> {
+    strike::withdraw_registry(canister_id, reason)
+}
+
+#[update(guard = "caller_is_admin")]
+pub fn purge_registry(canister_id: Principal, reason: String) -> Result<(), StrikeError> {
+    strike::purge_registry(canister_id, reason)
+}
+
+#[update(guard = "caller_is_not_anonymous")]
+pub fn update_registry_details(params: UpdateRegistryDetailsParams) -> Result<(), StrikeError> {
+    revisions::update_registry_details(params)
+}
+
+#[query]
+pub fn get_pending_revision(canister_id: Principal) -> Option<Revision> {
+    let caller = ic_cdk::api::caller();
+    let revision = revisions::get_revision(canister_id)?;
+
+    // Edits aren't public until they're approved
+    (revision.submitted_by == caller || roles::effective_role(caller).is_some()).then_some(revision)
+}
+
+#[query(guard = "caller_has_role")]
+pub fn get_pending_revisions(cursor: CursorPagination) -> Result<PaginatedResponse<Revision>, StrikeError> {
+    revisions::get_pending_revisions(cursor)
+}
+
+#[update(guard = "caller_is_reviewer")]
+pub fn approve_revision(canister_id: Principal) -> Result<(), StrikeError> {
+    revisions::approve_revision(canister_id)
+}
+
+#[update(guard = "caller_is_reviewer")]
+pub fn reject_revision(canister_id: Principal, reason: Option<String>) -> Result<(), StrikeError> {
+    revisions::reject_revision(canister_id, reason)
+}
+
+/// Votes on the status of an entry, which changes once the review quorum is met
+#[update(guard = "caller_is_reviewer")]
+pub async fn update_registry_status(params: UpdateRegistryStatusParams) -> Result<(), StrikeError> {
+    review::vote(params.canister_id, params.status, params.reason).await
+}
+
+#[query(guard = "caller_has_role")]
+pub fn get_pending_reviews(cursor: CursorPagination) -> Result<PaginatedResponse<Review>, StrikeError> {
+    review::get_pending_reviews(cursor)
+}
+
+#[query]
+pub fn get_review_quorum() -> u32 {
+    review::get_review_quorum()
+}
+
+#[update(guard = "caller_is_admin")]
+pub fn set_review_quorum(quorum: u32) -> Result<(), StrikeError> {
+    let old = review::set_review_quorum(quorum)?;
+
+    audit::record(
+        ic_cdk::api::caller(),
+        AuditRecord::config(AuditAction::SetReviewQuorum)
+            .values(Some(AuditValue::Quorum(old)), Some(AuditValue::Quorum(quorum))),
+    );
+    Ok(())
+}
+
+#[query]
+pub fn get_submission_limits() -> SubmissionLimits {
+    limits::get_submission_limits()
+}
+
+#[update(guard = "caller_is_admin")]
+pub fn set_submission_limits(submission_limits: SubmissionLimits) -> Result<(), StrikeError> {
+    let old = limits::set_submission_limits(submission_limits)?;
+
+    audit::record(
+        ic_cdk::api::caller(),
+        AuditRecord::config(AuditAction::SetSubmissionLimits).values(
+            Some(AuditValue::SubmissionLimits(old)),
+            Some(AuditValue::SubmissionLimits(submission_limits)),
+        ),
+    );
+    Ok(())
+}
+
+#[query]
+pub fn get_feature_toggles() -> FeatureToggles {
+    features::get_feature_toggles()
+}
+
+#[update(guard = "caller_is_admin")]
+pub fn set_feature_toggles(toggles: FeatureToggles) -> Result<(), StrikeError> {
+    let old = features::set_feature_toggles(toggles);
+
+    audit::record(
+        ic_cdk::api::caller(),
+        AuditRecord::config(AuditAction::SetFeatureToggles).values(
+            Some(AuditValue::FeatureToggles(old)),
+            Some(AuditValue::FeatureToggles(toggles)),
+        ),
+    );
+    Ok(())
+}
+
+#[query(guard = "caller_has_role")]
+pub fn get_module_hash_monitor_runs() -> MonitorRuns {
+    monitor::get_monitor_runs()
+}
+
+#[query]
+pub fn get_deposit_config() -> Option<DepositConfig> {
+    deposits::get_deposit_config()
+}
+
+#[update(guard = "caller_is_admin")]
+pub fn set_deposit_config(config: Option<DepositConfig>) -> Result<(), StrikeError> {
+    let old = deposits::set_deposit_config(config.clone())?;
+
+    audit::record(
+        ic_cdk::api::caller(),
+        AuditRecord::config(AuditAction::SetDepositConfig)
+            .values(old.map(AuditValue::DepositConfig), config.map(AuditValue::DepositConfig)),
+    );
+    Ok(())
+}
+
+#[query]
+pub fn get_deposit(canister_id: Principal) -> Option<Deposit> {
+    deposits::get_deposit_for_caller(canister_id)
+}
+
+/// Refunds a deposit regardless of the refund policy, e.g. to retry a failed refund
+#[update(guard = "caller_is_admin")]
+pub async fn refund_deposit(canister_id: Principal) -> Result<Nat, StrikeError> {
+    let block_index = deposits::refund(canister_id).await?;
+
+    audit::record(
+        ic_cdk::api::caller(),
+        AuditRecord::registry(canister_id, AuditAction::RefundDeposit),
+    );
+    Ok(block_index)
+}
+
+#[query(guard = "caller_has_role")]
+pub fn get_registries(params: GetRegistriesParams) -> Result<PaginatedResponse<StrikeRegistry>, StrikeError> {
+    let caller = ic_cdk::api::caller();
+    strike::get_registries(params).map(|page| page.map(|registry| roles::redact_for(caller, registry)))
+}
+
+#[query]
+pub fn search_registries(
+    query: String,
+    filters: SearchFilters,
+    cursor: CursorPagination,
+) -> Result<PaginatedResponse<StrikeRegistry>, StrikeError> {
+    let caller = ic_cdk::api::caller();
+    search::search_registries(query, filters, cursor).map(|page| page.map(|registry| roles::redact_for(caller, registry)))
+}
+
+/// Moderation numbers for the transparency page
+#[query]
+pub fn get_stats() -> RegistryStats {
+    stats::get_stats(ic_cdk::api::time())
+}
+
+#[update(guard = "caller_is_not_anonymous")]
+pub async fn appeal_block(canister_id: Principal, statement: String) -> Result<(), StrikeError> {
+    appeals::appeal_block(canister_id, statement).await
+}
+
+#[query]
+pub fn get_appeals(canister_id: Principal) -> Result<Vec<Appeal>, StrikeError> {
+    appeals::get_appeal_thread(canister_id)
+}
+
+#[query(guard = "caller_has_role")]
+pub fn get_open_appeals(cursor: CursorPagination) -> Result<PaginatedResponse<Appeal>, StrikeError> {
+    appeals::get_open_appeals(cursor)
+}
+
+#[update(guard = "caller_is_admin")]
+pub async fn accept_appeal(canister_id: Principal, status: StrikeStatus, reason: Option<String>) -> Result<(), StrikeError> {
+    appeals::accept_appeal(canister_id, status, reason).await
 }
 
 #[update(guard = "caller_is_admin")]
-pub fn update_registry_status(params: UpdateRegistryStatusParams) -> Result<(), String> {
-    strike::update_registry_status(params.canister_id, params.status)
+pub fn reject_appeal(canister_id: Principal, reason: String) -> Result<(), StrikeError> {
+    appeals::reject_appeal(canister_id, reason)
+}
+
+#[update(guard = "caller_is_not_anonymous")]
+pub fn report_canister(
+    canister_id: Principal,
+    category: ReportCategory,
+    evidence_url: Option<String>,
+    note: Option<String>,
+) -> Result<(), StrikeError> {
+    reports::report_canister(canister_id, category, evidence_url, note)
+}
+
+#[query(guard = "caller_has_role")]
+pub fn get_report_queue(cursor: CursorPagination) -> Result<PaginatedResponse<ReportQueueEntry>, StrikeError> {
+    reports::get_report_queue(cursor)
+}
+
+#[query(guard = "caller_has_role")]
+pub fn get_reports(canister_id: Principal) -> Vec<Report> {
+    reports::get_reports(canister_id)
 }
 
 #[query(guard = "caller_is_admin")]
-pub fn get_registries(params: GetRegistriesParams) -> PaginatedResponse<StrikeRegistry> {
-    strike::get_registries(params)
+pub fn get_audit_log(params: GetAuditLogParams) -> Result<PaginatedResponse<AuditEntry>, StrikeError> {
+    audit::get_audit_log(params)
+}
+
+#[cfg(feature = "bench")]
+#[query]
+pub fn bench_registry_encoding() -> encoding::bench::EncodingBenchmark {
+    encoding::bench::run()
+}
+
+/// Backups
+#[query(guard = "caller_is_admin")]
+pub fn export_snapshot(cursor: Option<SnapshotCursor>) -> SnapshotExport {
+    snapshot::export_snapshot(cursor)
+}
+
+#[update(guard = "caller_is_admin")]
+pub fn import_snapshot_chunk(chunk: SnapshotChunk) -> Result<(), StrikeError> {
+    snapshot::import_snapshot_chunk(chunk)
+}
+
+#[update(guard = "caller_is_admin")]
+pub fn finalize_import(manifest: SnapshotManifest, manifest_hash: String) -> Result<ImportProgress, StrikeError> {
+    snapshot::finalize_import(manifest, manifest_hash)
+}
+
+#[update(guard = "caller_is_admin")]
+pub fn cancel_import() -> Result<(), StrikeError> {
+    snapshot::cancel_import()
+}
+
+// http gateway
+#[query]
+pub fn http_request(request: HttpRequest) -> HttpResponse {
+    http::http_request(request)
+}
+
+#[update]
+pub fn http_request_update(request: HttpRequest) -> HttpResponse {
+    http::http_request_update(request)
+}
+
+// ingress
+#[inspect_message]
+fn inspect_message() {
+    let method = ic_cdk::api::call::method_name();
+    let arg_size = ic_cdk::api::call::arg_data_raw_size();
+
+    match limits::inspect_message(&method, arg_size, ic_cdk::api::caller(), ic_cdk::api::time()) {
+        Ok(()) => ic_cdk::api::call::accept_message(),
+        Err(err) => ic_cdk::trap(&err.to_string()),
+    }
 }
 
 ic_cdk::export_candid!();
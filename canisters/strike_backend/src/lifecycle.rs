@@ -1,5 +1,8 @@
 mod init;
+mod migrations;
 mod post_upgrade;
 mod pre_upgrade;
 
+pub use migrations::SCHEMA_VERSION;
+
 const UPGRADE_BUFFER_SIZE: usize = 1024 * 1024; // 1MB
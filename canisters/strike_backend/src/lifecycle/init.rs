@@ -1,12 +1,24 @@
 use ic_cdk::init;
 use tracing::info;
 
-use crate::admins;
+use crate::{
+    audit::{self, AuditAction, AuditRecord, AuditValue},
+    certification, monitor,
+    roles::{self, Role},
+};
 
 #[init]
 fn init() {
     let caller = ic_cdk::api::caller();
-    admins::add_admins_unchecked([caller].to_vec()).unwrap();
+    roles::set_role_unchecked(caller, Role::Owner);
+    audit::record(
+        caller,
+        AuditRecord::role(caller, AuditAction::GrantRole).values(None, Some(AuditValue::Role(Role::Owner))),
+    );
+
+    certification::rebuild();
+
+    monitor::start_module_hash_monitor();
 
     info!("Initialization complete");
 }
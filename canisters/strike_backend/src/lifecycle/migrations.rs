@@ -0,0 +1,103 @@
+use tracing::info;
+
+use crate::{appeals, audit, encoding, memory::STATE, roles, search, stats};
+
+/// A one-off change to the stable memory, applied by the first upgrade to a release that has it
+struct Migration {
+    /// The schema version once applied. Versions only increase, a released one is never reused.
+    version: u32,
+    description: &'static str,
+    apply: fn(),
+}
+
+/// In the order they're applied. Steps must be safe to run against a state that already has
+/// their change, since states saved before versioning start from zero.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Move the admin map to roles",
+        apply: roles::migrate_admins,
+    },
+    Migration {
+        version: 2,
+        description: "Rewrite the registry entries in the current encoding",
+        apply: encoding::migrate_registry,
+    },
+    Migration {
+        version: 3,
+        description: "Count the moderation history of the audit log",
+        apply: stats::backfill,
+    },
+    Migration {
+        version: 4,
+        description: "Index and count the audit log by canister and by caller",
+        apply: audit::reindex_log,
+    },
+    Migration {
+        version: 5,
+        description: "Index the open appeals",
+        apply: appeals::reindex_open,
+    },
+    Migration {
+        version: 6,
+        description: "Drop the names of submitters from the search index",
+        apply: search::rebuild,
+    },
+];
+
+/// The schema version of this release
+pub const SCHEMA_VERSION: u32 = 6;
+
+/// Fails for a state saved by a newer release. Its migrations may have changed the stable memory
+/// in ways this release can't read, so the upgrade has to be rolled back.
+pub fn check_downgrade(stored_version: u32) -> Result<(), String> {
+    if stored_version > SCHEMA_VERSION {
+        return Err(format!(
+            "Cannot downgrade from schema version {} to {}",
+            stored_version, SCHEMA_VERSION
+        ));
+    }
+    Ok(())
+}
+
+fn pending(stored_version: u32) -> impl Iterator<Item = &'static Migration> {
+    MIGRATIONS.iter().filter(move |migration| migration.version > stored_version)
+}
+
+/// Applies the migrations the restored state hasn't seen yet
+pub fn migrate() {
+    let stored_version = STATE.with(|s| s.borrow().schema_version);
+
+    for migration in pending(stored_version) {
+        (migration.apply)();
+        STATE.with(|s| s.borrow_mut().schema_version = migration.version);
+
+        info!("Applied migration {}: {}", migration.version, migration.description);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrations_are_ordered() {
+        assert!(MIGRATIONS.windows(2).all(|pair| pair[0].version < pair[1].version));
+        assert_eq!(MIGRATIONS.last().map(|migration| migration.version), Some(SCHEMA_VERSION));
+    }
+
+    #[test]
+    fn test_pending() {
+        let versions = |stored_version| pending(stored_version).map(|migration| migration.version).collect::<Vec<_>>();
+        assert_eq!(versions(0), vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(versions(1), vec![2, 3, 4, 5, 6]);
+        assert!(versions(SCHEMA_VERSION).is_empty());
+    }
+
+    #[test]
+    fn test_rejects_downgrades() {
+        assert!(check_downgrade(0).is_ok());
+        assert!(check_downgrade(SCHEMA_VERSION).is_ok());
+        assert!(check_downgrade(SCHEMA_VERSION + 1).is_err());
+    }
+}
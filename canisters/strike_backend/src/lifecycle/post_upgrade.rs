@@ -1,21 +1,36 @@
 use ic_cdk::post_upgrade;
+use ic_stable_structures::memory_manager::MemoryId;
 use ic_stable_structures::reader::{BufferedReader, Reader};
+use serde::{de::DeserializeOwned, Deserialize};
 use tracing::info;
 
 use crate::{
-    lifecycle::UPGRADE_BUFFER_SIZE,
+    certification, index,
+    lifecycle::{migrations, UPGRADE_BUFFER_SIZE},
     memory::{self, STATE, UPGRADES_MEMORY_ID},
-    serializer,
+    monitor, search, serializer,
 };
 
+/// The schema version of a saved state, which decodes whatever the rest of the state looks like
+#[derive(Deserialize)]
+struct StoredVersion {
+    #[serde(default)]
+    schema_version: u32,
+}
+
+fn read<T: DeserializeOwned>(memory_id: MemoryId) -> T {
+    let memory = memory::get_memory(memory_id);
+
+    let reader = BufferedReader::new(UPGRADE_BUFFER_SIZE, Reader::new(&memory, 0));
+
+    // Trapping rolls the upgrade back, leaving the previous release running on intact memory
+    serializer::deserialize(reader).unwrap_or_else(|err| ic_cdk::trap(&format!("Failed to decode the saved state: {}", err)))
+}
+
 macro_rules! restore_state {
     ($x:ident,$y:expr) => {
         $x.with(|s| {
-            let memory = memory::get_memory($y);
-
-            let reader = BufferedReader::new(UPGRADE_BUFFER_SIZE, Reader::new(&memory, 0));
-
-            let state = serializer::deserialize(reader).unwrap();
+            let state = read($y);
             *s.borrow_mut() = state;
         });
     };
@@ -24,7 +39,21 @@ macro_rules! restore_state {
 // A post-upgrade hook for deserializing the data back into the heap.
 #[post_upgrade]
 fn post_upgrade() {
+    let stored: StoredVersion = read(UPGRADES_MEMORY_ID);
+    if let Err(err) = migrations::check_downgrade(stored.schema_version) {
+        ic_cdk::trap(&err);
+    }
+
     restore_state!(STATE, UPGRADES_MEMORY_ID);
 
+    migrations::migrate();
+
+    index::rebuild_if_missing();
+    search::rebuild_if_missing();
+
+    certification::rebuild();
+
+    monitor::start_module_hash_monitor();
+
     info!("Upgrade complete");
 }
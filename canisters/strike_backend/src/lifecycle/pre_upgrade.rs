@@ -16,7 +16,8 @@ macro_rules! store_state {
             let mut memory = memory::get_memory($y);
 
             let writer = BufferedWriter::new(UPGRADE_BUFFER_SIZE, Writer::new(&mut memory, 0));
-            serializer::serialize(state, writer).unwrap();
+            serializer::serialize(state, writer)
+                .unwrap_or_else(|err| ic_cdk::trap(&format!("Failed to save the state: {}", err)));
         });
     };
 }
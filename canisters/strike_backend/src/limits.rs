@@ -0,0 +1,453 @@
+use candid::{CandidType, Principal};
+use ic_stable_structures::{storable::Bound, Storable};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+use crate::error::StrikeError;
+use crate::features::{self, Feature};
+use crate::index::{read_principal, write_principal, MIN_PRINCIPAL, PRINCIPAL_SIZE};
+use crate::memory::{QUOTA_USES, QUOTA_USES_BY_PRINCIPAL, STATE};
+use crate::roles::{self, Role};
+use crate::snapshot::MAX_CHUNK_BYTES;
+
+// Maximum lengths of the text fields, in bytes
+pub const MAX_NAME_LEN: usize = 100;
+pub const MAX_EMAIL_LEN: usize = 254;
+pub const MAX_HANDLE_LEN: usize = 64;
+pub const MAX_PROJECT_NAME_LEN: usize = 100;
+pub const MAX_DESCRIPTION_LEN: usize = 2000;
+pub const MAX_URL_LEN: usize = 2048;
+pub const MAX_REASON_LEN: usize = 1000;
+pub const MAX_NOTE_LEN: usize = 1000;
+pub const MAX_STATEMENT_LEN: usize = 2000;
+
+/// Ingress messages with larger arguments are rejected before they're executed. Every argument
+/// of the interface fits well below this once the text fields are within their limits.
+pub const MAX_INGRESS_ARG_SIZE: usize = 16 * 1024;
+
+/// The limit for the chunks of a snapshot import, which only admins may send
+pub const MAX_IMPORT_ARG_SIZE: usize = MAX_CHUNK_BYTES + 64 * 1024;
+
+/// Canisters a batch lookup may ask for unless an admin changes it, and the most an admin may allow
+pub const DEFAULT_MAX_BATCH_LOOKUP: u32 = 100;
+pub const MAX_BATCH_LOOKUP_LIMIT: u32 = 1000;
+
+const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+/// How many submissions the registry accepts, each over a sliding window
+#[derive(Serialize, Deserialize, CandidType, Debug, Clone, Copy, PartialEq)]
+pub struct SubmissionLimits {
+    /// Submissions a single principal may make per window
+    pub per_principal: u32,
+    pub per_principal_window_secs: u64,
+    /// Submissions of all principals together per window
+    pub global: u32,
+    pub global_window_secs: u64,
+}
+
+impl Default for SubmissionLimits {
+    fn default() -> Self {
+        Self {
+            per_principal: 5,
+            per_principal_window_secs: 24 * 60 * 60,
+            global: 100,
+            global_window_secs: 60 * 60,
+        }
+    }
+}
+
+/// The rate limits whose uses are kept in `QUOTA_USES`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Quota {
+    /// Every submission, counted against the quota of its submitter
+    Submissions = 0,
+    Reports = 1,
+    /// Submissions of a canister the submitter may register, counted against the global cap
+    VerifiedSubmissions = 2,
+}
+
+/// Uses of a quota in the order they happened
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct QuotaUseKey {
+    pub quota: u8,
+    pub time: u64,
+    pub principal: Principal,
+}
+
+/// Uses of a quota grouped by principal
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct PrincipalQuotaUseKey {
+    pub quota: u8,
+    pub principal: Principal,
+    pub time: u64,
+}
+
+impl Storable for QuotaUseKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = Vec::with_capacity(1 + 8 + PRINCIPAL_SIZE);
+        bytes.push(self.quota);
+        bytes.extend_from_slice(&self.time.to_be_bytes());
+        write_principal(&mut bytes, &self.principal);
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Self {
+            quota: bytes[0],
+            time: u64::from_be_bytes(bytes[1..9].try_into().unwrap()),
+            principal: read_principal(&bytes[9..]),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: (1 + 8 + PRINCIPAL_SIZE) as u32,
+        is_fixed_size: true,
+    };
+}
+
+impl Storable for PrincipalQuotaUseKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = Vec::with_capacity(1 + PRINCIPAL_SIZE + 8);
+        bytes.push(self.quota);
+        write_principal(&mut bytes, &self.principal);
+        bytes.extend_from_slice(&self.time.to_be_bytes());
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Self {
+            quota: bytes[0],
+            principal: read_principal(&bytes[1..]),
+            time: u64::from_be_bytes(bytes[1 + PRINCIPAL_SIZE..].try_into().unwrap()),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: (1 + PRINCIPAL_SIZE + 8) as u32,
+        is_fixed_size: true,
+    };
+}
+
+/// Fails on the first field that exceeds its maximum length
+pub fn check_lengths(fields: &[(&str, Option<&str>, usize)]) -> Result<(), StrikeError> {
+    for (field, value, max) in fields {
+        if value.map_or(false, |value| value.len() > *max) {
+            return Err(StrikeError::invalid_field(field, format!("cannot exceed {} bytes", max)));
+        }
+    }
+    Ok(())
+}
+
+pub fn get_submission_limits() -> SubmissionLimits {
+    STATE.with(|s| s.borrow().submission_limits)
+}
+
+pub fn set_submission_limits(limits: SubmissionLimits) -> Result<SubmissionLimits, StrikeError> {
+    let fields = [
+        ("per_principal", limits.per_principal as u64),
+        ("per_principal_window_secs", limits.per_principal_window_secs),
+        ("global", limits.global as u64),
+        ("global_window_secs", limits.global_window_secs),
+    ];
+    if let Some((field, _)) = fields.iter().find(|(_, value)| *value == 0) {
+        return Err(StrikeError::invalid_field(field, "must be at least 1"));
+    }
+
+    Ok(STATE.with(|s| std::mem::replace(&mut s.borrow_mut().submission_limits, limits)))
+}
+
+pub fn get_max_batch_lookup() -> u32 {
+    STATE.with(|s| s.borrow().max_batch_lookup)
+}
+
+pub fn set_max_batch_lookup(max_batch_lookup: u32) -> Result<u32, StrikeError> {
+    if !(1..=MAX_BATCH_LOOKUP_LIMIT).contains(&max_batch_lookup) {
+        return Err(StrikeError::invalid_field(
+            "max_batch_lookup",
+            format!("must be between 1 and {}", MAX_BATCH_LOOKUP_LIMIT),
+        ));
+    }
+
+    Ok(STATE.with(|s| std::mem::replace(&mut s.borrow_mut().max_batch_lookup, max_batch_lookup)))
+}
+
+fn within(time: u64, now: u64, window_secs: u64) -> bool {
+    now.saturating_sub(time) < window_secs.saturating_mul(NANOS_PER_SEC)
+}
+
+/// Times of the uses of `quota` within the window, oldest first, by `principal` or by anyone if none
+pub fn recent_uses(quota: Quota, principal: Option<Principal>, now: u64, window_secs: u64) -> Vec<u64> {
+    let quota = quota as u8;
+    let since = now.saturating_sub(window_secs.saturating_mul(NANOS_PER_SEC));
+
+    let uses: Vec<(u64, u32)> = match principal {
+        None => QUOTA_USES.with(|s| {
+            s.borrow()
+                .range(
+                    QuotaUseKey {
+                        quota,
+                        time: since,
+                        principal: MIN_PRINCIPAL,
+                    }..,
+                )
+                .take_while(|(key, _)| key.quota == quota)
+                .map(|(key, count)| (key.time, count))
+                .collect()
+        }),
+        Some(principal) => QUOTA_USES_BY_PRINCIPAL.with(|s| {
+            s.borrow()
+                .range(
+                    PrincipalQuotaUseKey {
+                        quota,
+                        principal,
+                        time: since,
+                    }..,
+                )
+                .take_while(|(key, _)| key.quota == quota && key.principal == principal)
+                .map(|(key, count)| (key.time, count))
+                .collect()
+        }),
+    };
+
+    uses.into_iter()
+        .filter(|(time, _)| within(*time, now, window_secs))
+        .flat_map(|(time, count)| std::iter::repeat(time).take(count as usize))
+        .collect()
+}
+
+/// Counts a use of `quota` by `principal` at `now`
+pub fn record_use(quota: Quota, principal: Principal, now: u64) {
+    let key = QuotaUseKey {
+        quota: quota as u8,
+        time: now,
+        principal,
+    };
+    QUOTA_USES.with(|s| {
+        let mut uses = s.borrow_mut();
+        let count = uses.get(&key).unwrap_or_default();
+        uses.insert(key, count + 1);
+    });
+
+    let key = PrincipalQuotaUseKey {
+        quota: quota as u8,
+        principal,
+        time: now,
+    };
+    QUOTA_USES_BY_PRINCIPAL.with(|s| {
+        let mut uses = s.borrow_mut();
+        let count = uses.get(&key).unwrap_or_default();
+        uses.insert(key, count + 1);
+    });
+}
+
+/// Forgets the uses of `quota` that have left a window of `window_secs`. Uses are ordered by time,
+/// so the expired ones are always the first ones.
+pub fn prune_uses(quota: Quota, now: u64, window_secs: u64) {
+    let quota = quota as u8;
+    let first = QuotaUseKey {
+        quota,
+        time: 0,
+        principal: MIN_PRINCIPAL,
+    };
+
+    QUOTA_USES.with(|s| {
+        let mut uses = s.borrow_mut();
+        while let Some((key, _)) = uses
+            .range(first..)
+            .next()
+            .filter(|(key, _)| key.quota == quota && !within(key.time, now, window_secs))
+        {
+            uses.remove(&key);
+            QUOTA_USES_BY_PRINCIPAL.with(|s| {
+                s.borrow_mut().remove(&PrincipalQuotaUseKey {
+                    quota,
+                    principal: key.principal,
+                    time: key.time,
+                })
+            });
+        }
+    });
+}
+
+/// Fails with the seconds until fewer than `limit` of `times` are within the window. `times` are in
+/// the order they happened.
+pub fn check_window(times: &[u64], now: u64, window_secs: u64, limit: u32) -> Result<(), StrikeError> {
+    let recent: Vec<u64> = times.iter().copied().filter(|time| within(*time, now, window_secs)).collect();
+    if recent.len() < limit as usize {
+        return Ok(());
+    }
+
+    // The submission whose expiry brings the count below the limit
+    let expiring = recent[recent.len() - limit as usize];
+    let expires_at = expiring.saturating_add(window_secs.saturating_mul(NANOS_PER_SEC));
+    Err(StrikeError::RateLimited {
+        retry_after: expires_at.saturating_sub(now).div_ceil(NANOS_PER_SEC),
+    })
+}
+
+fn check_global_quota(limits: &SubmissionLimits, now: u64) -> Result<(), StrikeError> {
+    let all = recent_uses(Quota::VerifiedSubmissions, None, now, limits.global_window_secs);
+    check_window(&all, now, limits.global_window_secs, limits.global)
+}
+
+fn check_quota(limits: &SubmissionLimits, principal: Principal, now: u64) -> Result<(), StrikeError> {
+    check_global_quota(limits, now)?;
+
+    let own = recent_uses(Quota::Submissions, Some(principal), now, limits.per_principal_window_secs);
+    check_window(&own, now, limits.per_principal_window_secs, limits.per_principal)
+}
+
+/// Whether `principal` may submit at `now`, without counting the submission
+pub fn check_submission_quota(principal: Principal, now: u64) -> Result<(), StrikeError> {
+    check_quota(&get_submission_limits(), principal, now)
+}
+
+/// Counts a submission against the quota of `principal`, failing once either quota is used up.
+/// The global cap is only checked, principals that may not register the canister mustn't use it up.
+fn consume(limits: &SubmissionLimits, principal: Principal, now: u64) -> Result<(), StrikeError> {
+    prune_uses(Quota::Submissions, now, limits.per_principal_window_secs);
+    prune_uses(Quota::VerifiedSubmissions, now, limits.global_window_secs);
+
+    check_quota(limits, principal, now)?;
+    record_use(Quota::Submissions, principal, now);
+    Ok(())
+}
+
+/// Counts a submission whose submitter turned out to be allowed to register the canister against
+/// the global cap
+fn consume_global(limits: &SubmissionLimits, principal: Principal, now: u64) -> Result<(), StrikeError> {
+    prune_uses(Quota::VerifiedSubmissions, now, limits.global_window_secs);
+
+    check_global_quota(limits, now)?;
+    record_use(Quota::VerifiedSubmissions, principal, now);
+    Ok(())
+}
+
+pub fn consume_submission_quota(principal: Principal, now: u64) -> Result<(), StrikeError> {
+    consume(&get_submission_limits(), principal, now)
+}
+
+pub fn consume_global_submission_quota(principal: Principal, now: u64) -> Result<(), StrikeError> {
+    consume_global(&get_submission_limits(), principal, now)
+}
+
+/// Decides whether an ingress message is worth executing. Only a single replica runs this, so it
+/// saves cycles but isn't a security boundary: the update itself enforces the same limits.
+pub fn inspect_message(method: &str, arg_size: usize, caller: Principal, now: u64) -> Result<(), StrikeError> {
+    let max_arg_size = match method {
+        "import_snapshot_chunk" if roles::effective_role(caller).map_or(false, Role::is_admin) => MAX_IMPORT_ARG_SIZE,
+        _ => MAX_INGRESS_ARG_SIZE,
+    };
+    if arg_size > max_arg_size {
+        return Err(StrikeError::invalid_field(
+            "arguments",
+            format!("cannot exceed {} bytes", max_arg_size),
+        ));
+    }
+
+    if method == "add_registry" {
+        if caller == Principal::anonymous() {
+            return Err(StrikeError::Unauthorized);
+        }
+
+        features::ensure_enabled(Feature::Submissions)?;
+        check_submission_quota(caller, now)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALI: Principal = Principal::from_slice(&[1; 29]);
+    const BOB: Principal = Principal::from_slice(&[2; 29]);
+
+    #[test]
+    fn test_per_principal_window_slides() {
+        let limits = SubmissionLimits {
+            per_principal: 2,
+            per_principal_window_secs: 10,
+            global: 100,
+            global_window_secs: 10,
+        };
+        consume(&limits, ALI, 0).unwrap();
+        consume(&limits, ALI, 5 * NANOS_PER_SEC).unwrap();
+        assert_eq!(
+            consume(&limits, ALI, 9 * NANOS_PER_SEC),
+            Err(StrikeError::RateLimited { retry_after: 1 })
+        );
+        assert!(consume(&limits, BOB, 9 * NANOS_PER_SEC).is_ok());
+
+        // The first submission leaves the window, the second one still counts
+        consume(&limits, ALI, 10 * NANOS_PER_SEC).unwrap();
+        assert!(consume(&limits, ALI, 11 * NANOS_PER_SEC).is_err());
+    }
+
+    #[test]
+    fn test_global_cap() {
+        let limits = SubmissionLimits {
+            per_principal: 100,
+            per_principal_window_secs: 10,
+            global: 1,
+            global_window_secs: 10,
+        };
+        consume(&limits, ALI, 0).unwrap();
+        consume_global(&limits, ALI, 0).unwrap();
+        assert_eq!(consume(&limits, BOB, 0), Err(StrikeError::RateLimited { retry_after: 10 }));
+        assert_eq!(
+            consume_global(&limits, BOB, 0),
+            Err(StrikeError::RateLimited { retry_after: 10 })
+        );
+    }
+
+    #[test]
+    fn test_unverified_submissions_leave_global_cap() {
+        let limits = SubmissionLimits {
+            per_principal: 100,
+            per_principal_window_secs: 10,
+            global: 1,
+            global_window_secs: 10,
+        };
+        consume(&limits, ALI, 0).unwrap();
+        consume(&limits, BOB, 0).unwrap();
+        consume(&limits, BOB, 0).unwrap();
+        consume_global(&limits, ALI, 0).unwrap();
+    }
+
+    #[test]
+    fn test_uses_at_the_same_time_all_count() {
+        record_use(Quota::Reports, ALI, 7);
+        record_use(Quota::Reports, ALI, 7);
+        record_use(Quota::Reports, BOB, 8);
+        assert_eq!(recent_uses(Quota::Reports, Some(ALI), 8, 1), vec![7, 7]);
+        assert_eq!(recent_uses(Quota::Reports, None, 8, 1), vec![7, 7, 8]);
+        assert!(recent_uses(Quota::Submissions, None, 8, 1).is_empty());
+
+        prune_uses(Quota::Reports, NANOS_PER_SEC + 7, 1);
+        assert_eq!(recent_uses(Quota::Reports, None, 8, 1), vec![8]);
+        assert_eq!(QUOTA_USES_BY_PRINCIPAL.with(|s| s.borrow().len()), 1);
+    }
+
+    #[test]
+    fn test_check_lengths() {
+        assert!(check_lengths(&[("name", Some("strike"), MAX_NAME_LEN), ("twitter", None, MAX_HANDLE_LEN)]).is_ok());
+        assert_eq!(
+            check_lengths(&[("name", Some(&"a".repeat(MAX_NAME_LEN + 1)), MAX_NAME_LEN)]),
+            Err(StrikeError::invalid_field(
+                "name",
+                format!("cannot exceed {} bytes", MAX_NAME_LEN)
+            ))
+        );
+    }
+
+    #[test]
+    fn test_inspect_rejects_oversized_arguments() {
+        assert!(inspect_message("get_review_quorum", MAX_INGRESS_ARG_SIZE + 1, ALI, 0).is_err());
+        assert_eq!(
+            inspect_message("add_registry", 100, Principal::anonymous(), 0),
+            Err(StrikeError::Unauthorized)
+        );
+    }
+}
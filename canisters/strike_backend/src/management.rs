@@ -0,0 +1,28 @@
+use candid::Principal;
+use ic_cdk::api::management_canister::main::{canister_info, CanisterInfoRequest, CanisterInfoResponse};
+
+use crate::error::StrikeError;
+
+/// Fetches the controllers and module hash of a canister from the management canister
+pub async fn get_canister_info(canister_id: Principal) -> Result<CanisterInfoResponse, StrikeError> {
+    let request = CanisterInfoRequest {
+        canister_id,
+        num_requested_changes: None,
+    };
+
+    canister_info(request)
+        .await
+        .map(|(info,)| info)
+        .map_err(|err| StrikeError::rejected(Principal::management_canister(), err))
+}
+
+/// Checks that `principal` is among the controllers of `canister_id`
+pub async fn ensure_controller(canister_id: Principal, principal: Principal) -> Result<(), StrikeError> {
+    let info = get_canister_info(canister_id).await?;
+
+    if info.controllers.contains(&principal) {
+        Ok(())
+    } else {
+        Err(StrikeError::Unauthorized)
+    }
+}
@@ -1,11 +1,26 @@
 use candid::Principal;
 use ic_stable_structures::{
     memory_manager::{MemoryId, MemoryManager, VirtualMemory},
-    DefaultMemoryImpl, StableBTreeMap,
+    DefaultMemoryImpl, StableBTreeMap, StableLog,
 };
 use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
 
+use crate::appeals::{Appeal, AppealKey, OpenAppealKey};
+use crate::approvals::SubmitterApproval;
+use crate::audit::{AuditEntry, AuditIndexKey};
+use crate::deposits::{Deposit, DepositConfig};
+use crate::features::FeatureToggles;
+use crate::index::{CreatedIndexKey, NameIndexKey, OwnerIndexKey, StatusIndexKey, StatusNameIndexKey};
+use crate::lifecycle::SCHEMA_VERSION;
+use crate::limits::{PrincipalQuotaUseKey, QuotaUseKey, SubmissionLimits, DEFAULT_MAX_BATCH_LOOKUP};
+use crate::monitor::MonitorRuns;
+use crate::reports::{Report, ReportKey, ReportQueueKey};
+use crate::review::Review;
+use crate::revisions::Revision;
+use crate::search::SearchIndexKey;
+use crate::snapshot::ImportProgress;
+use crate::stats::DailyCounts;
 use crate::StrikeRegistry;
 
 // A memory for upgrades, where data from the heap can be serialized/deserialized.
@@ -15,24 +30,86 @@ pub const UPGRADES_MEMORY_ID: MemoryId = MemoryId::new(0);
 // every additional stable structure.
 const ADMINS_MEMORY_ID: MemoryId = MemoryId::new(1);
 const REGISTRY_MEMORY_ID: MemoryId = MemoryId::new(2);
+const AUDIT_LOG_INDEX_MEMORY_ID: MemoryId = MemoryId::new(3);
+const AUDIT_LOG_DATA_MEMORY_ID: MemoryId = MemoryId::new(4);
+const STATUS_INDEX_MEMORY_ID: MemoryId = MemoryId::new(5);
+const CREATED_INDEX_MEMORY_ID: MemoryId = MemoryId::new(6);
+const OWNER_INDEX_MEMORY_ID: MemoryId = MemoryId::new(7);
+const STATUS_COUNTS_MEMORY_ID: MemoryId = MemoryId::new(8);
+const NAME_INDEX_MEMORY_ID: MemoryId = MemoryId::new(9);
+const STATUS_NAME_INDEX_MEMORY_ID: MemoryId = MemoryId::new(10);
+const SEARCH_INDEX_MEMORY_ID: MemoryId = MemoryId::new(11);
+const ROLES_MEMORY_ID: MemoryId = MemoryId::new(12);
+const REVIEWS_MEMORY_ID: MemoryId = MemoryId::new(13);
+const REPORTS_MEMORY_ID: MemoryId = MemoryId::new(14);
+const REPORT_COUNTS_MEMORY_ID: MemoryId = MemoryId::new(15);
+const REPORT_QUEUE_MEMORY_ID: MemoryId = MemoryId::new(16);
+const APPEALS_MEMORY_ID: MemoryId = MemoryId::new(17);
+const REVISIONS_MEMORY_ID: MemoryId = MemoryId::new(18);
+const DEPOSITS_MEMORY_ID: MemoryId = MemoryId::new(19);
+const IMPORT_CHUNKS_MEMORY_ID: MemoryId = MemoryId::new(20);
+const DAILY_COUNTS_MEMORY_ID: MemoryId = MemoryId::new(21);
+const REVIEW_TIMES_MEMORY_ID: MemoryId = MemoryId::new(22);
+const SUBMITTER_APPROVALS_MEMORY_ID: MemoryId = MemoryId::new(23);
+const AUDIT_BY_CANISTER_MEMORY_ID: MemoryId = MemoryId::new(24);
+const AUDIT_BY_ACTOR_MEMORY_ID: MemoryId = MemoryId::new(25);
+const QUOTA_USES_MEMORY_ID: MemoryId = MemoryId::new(26);
+const QUOTA_USES_BY_PRINCIPAL_MEMORY_ID: MemoryId = MemoryId::new(27);
+const OPEN_APPEALS_MEMORY_ID: MemoryId = MemoryId::new(28);
+const AUDIT_COUNT_BY_CANISTER_MEMORY_ID: MemoryId = MemoryId::new(29);
+const AUDIT_COUNT_BY_ACTOR_MEMORY_ID: MemoryId = MemoryId::new(30);
 
 pub type Memory = VirtualMemory<DefaultMemoryImpl>;
 
-// The state of the canister.
+// The configuration of the canister, kept on the heap and serialized on every upgrade. Every
+// field has a serde default so that states saved by older releases still decode, the migrations
+// in `lifecycle::migrations` take care of anything a default can't express.
 #[derive(Serialize, Deserialize)]
 pub struct State {
-    // Data that lives on the heap.
-    // This is an example for data that would need to be serialized/deserialized
-    // on every upgrade for it to be persisted. it is kind of reserved
-    data_on_the_heap: Vec<u8>,
-    // Data stored in `StableVec` doesn't need to be
-    // serialized/deserialized in upgrades, so we tell serde to skip it.
+    // Migrations applied to the stable memory, zero for states saved before migrations
+    #[serde(default)]
+    pub schema_version: u32,
+    #[serde(default)]
+    pub features: FeatureToggles,
+    // Distinct approvals needed before an entry becomes trusted
+    #[serde(default = "default_review_quorum")]
+    pub review_quorum: u32,
+    // Quotas on new submissions
+    #[serde(default)]
+    pub submission_limits: SubmissionLimits,
+    // Deposit required to submit, none when submissions are free
+    #[serde(default)]
+    pub deposit: Option<DepositConfig>,
+    // Canisters a single batch lookup may ask for
+    #[serde(default = "default_max_batch_lookup")]
+    pub max_batch_lookup: u32,
+    // Runs of the module hash monitor, whose timer doesn't survive upgrades
+    #[serde(default)]
+    pub module_hash_monitor: MonitorRuns,
+    // The snapshot import being committed, if any
+    #[serde(default)]
+    pub import: Option<ImportProgress>,
+}
+
+fn default_review_quorum() -> u32 {
+    1
+}
+
+fn default_max_batch_lookup() -> u32 {
+    DEFAULT_MAX_BATCH_LOOKUP
 }
 
 impl Default for State {
     fn default() -> Self {
         Self {
-            data_on_the_heap: vec![],
+            schema_version: SCHEMA_VERSION,
+            features: FeatureToggles::default(),
+            review_quorum: default_review_quorum(),
+            submission_limits: SubmissionLimits::default(),
+            deposit: None,
+            max_batch_lookup: default_max_batch_lookup(),
+            module_hash_monitor: MonitorRuns::default(),
+            import: None,
         }
     }
 }
@@ -43,14 +120,142 @@ thread_local! {
     static MEMORY_MANAGER: RefCell<MemoryManager<DefaultMemoryImpl>> =
         RefCell::new(MemoryManager::init(DefaultMemoryImpl::default()));
 
+    // Admins of the versions before roles, moved to `ROLES` on upgrade
     pub static ADMINS: RefCell<StableBTreeMap<Principal, u8, Memory>> = RefCell::new(
         StableBTreeMap::init(get_memory(ADMINS_MEMORY_ID))
     );
 
+    // The value is a `Role`
+    pub static ROLES: RefCell<StableBTreeMap<Principal, u8, Memory>> = RefCell::new(
+        StableBTreeMap::init(get_memory(ROLES_MEMORY_ID))
+    );
+
     pub static REGISTRY: RefCell<StableBTreeMap<Principal, StrikeRegistry, Memory>> = RefCell::new(
         StableBTreeMap::init(get_memory(REGISTRY_MEMORY_ID))
     );
 
+    // Secondary indexes over `REGISTRY`, maintained by `strike::save_registry`
+    pub static STATUS_INDEX: RefCell<StableBTreeMap<StatusIndexKey, (), Memory>> = RefCell::new(
+        StableBTreeMap::init(get_memory(STATUS_INDEX_MEMORY_ID))
+    );
+
+    pub static CREATED_INDEX: RefCell<StableBTreeMap<CreatedIndexKey, (), Memory>> = RefCell::new(
+        StableBTreeMap::init(get_memory(CREATED_INDEX_MEMORY_ID))
+    );
+
+    pub static OWNER_INDEX: RefCell<StableBTreeMap<OwnerIndexKey, (), Memory>> = RefCell::new(
+        StableBTreeMap::init(get_memory(OWNER_INDEX_MEMORY_ID))
+    );
+
+    pub static NAME_INDEX: RefCell<StableBTreeMap<NameIndexKey, (), Memory>> = RefCell::new(
+        StableBTreeMap::init(get_memory(NAME_INDEX_MEMORY_ID))
+    );
+
+    pub static STATUS_NAME_INDEX: RefCell<StableBTreeMap<StatusNameIndexKey, (), Memory>> = RefCell::new(
+        StableBTreeMap::init(get_memory(STATUS_NAME_INDEX_MEMORY_ID))
+    );
+
+    pub static STATUS_COUNTS: RefCell<StableBTreeMap<u8, u64, Memory>> = RefCell::new(
+        StableBTreeMap::init(get_memory(STATUS_COUNTS_MEMORY_ID))
+    );
+
+    // Inverted token index over `REGISTRY`, the value is the status of the entry
+    pub static SEARCH_INDEX: RefCell<StableBTreeMap<SearchIndexKey, u8, Memory>> = RefCell::new(
+        StableBTreeMap::init(get_memory(SEARCH_INDEX_MEMORY_ID))
+    );
+
+    // Votes on entries that haven't been decided yet
+    pub static REVIEWS: RefCell<StableBTreeMap<Principal, Review, Memory>> = RefCell::new(
+        StableBTreeMap::init(get_memory(REVIEWS_MEMORY_ID))
+    );
+
+    // Abuse reports filed by users, one per reporter and canister
+    pub static REPORTS: RefCell<StableBTreeMap<ReportKey, Report, Memory>> = RefCell::new(
+        StableBTreeMap::init(get_memory(REPORTS_MEMORY_ID))
+    );
+
+    pub static REPORT_COUNTS: RefCell<StableBTreeMap<Principal, u32, Memory>> = RefCell::new(
+        StableBTreeMap::init(get_memory(REPORT_COUNTS_MEMORY_ID))
+    );
+
+    // Reported canisters by report count, maintained by `reports::report_canister`
+    pub static REPORT_QUEUE: RefCell<StableBTreeMap<ReportQueueKey, (), Memory>> = RefCell::new(
+        StableBTreeMap::init(get_memory(REPORT_QUEUE_MEMORY_ID))
+    );
+
+    // Appeals against blocks, the thread of a canister is a contiguous range
+    pub static APPEALS: RefCell<StableBTreeMap<AppealKey, Appeal, Memory>> = RefCell::new(
+        StableBTreeMap::init(get_memory(APPEALS_MEMORY_ID))
+    );
+
+    // Appeals awaiting a decision by filing time, maintained by `appeals`
+    pub static OPEN_APPEALS: RefCell<StableBTreeMap<OpenAppealKey, (), Memory>> = RefCell::new(
+        StableBTreeMap::init(get_memory(OPEN_APPEALS_MEMORY_ID))
+    );
+
+    // Pending edits of trusted entries
+    pub static REVISIONS: RefCell<StableBTreeMap<Principal, Revision, Memory>> = RefCell::new(
+        StableBTreeMap::init(get_memory(REVISIONS_MEMORY_ID))
+    );
+
+    // Deposits of the current submission of each canister
+    pub static DEPOSITS: RefCell<StableBTreeMap<Principal, Deposit, Memory>> = RefCell::new(
+        StableBTreeMap::init(get_memory(DEPOSITS_MEMORY_ID))
+    );
+
+    // Chunks of a snapshot staged for import, by index
+    pub static IMPORT_CHUNKS: RefCell<StableBTreeMap<u32, Vec<u8>, Memory>> = RefCell::new(
+        StableBTreeMap::init(get_memory(IMPORT_CHUNKS_MEMORY_ID))
+    );
+
+    // Submitters approved by a controller of the canister, by canister
+    pub static SUBMITTER_APPROVALS: RefCell<StableBTreeMap<Principal, SubmitterApproval, Memory>> = RefCell::new(
+        StableBTreeMap::init(get_memory(SUBMITTER_APPROVALS_MEMORY_ID))
+    );
+
+    // Moderation counters for `stats::get_stats`, maintained on every status change. Daily counts
+    // are kept for the stats window, review times are a histogram of hour buckets.
+    pub static DAILY_COUNTS: RefCell<StableBTreeMap<u64, DailyCounts, Memory>> = RefCell::new(
+        StableBTreeMap::init(get_memory(DAILY_COUNTS_MEMORY_ID))
+    );
+
+    pub static REVIEW_TIMES: RefCell<StableBTreeMap<u32, u64, Memory>> = RefCell::new(
+        StableBTreeMap::init(get_memory(REVIEW_TIMES_MEMORY_ID))
+    );
+
+    // Recent uses of the rate limits by time and by principal, the value is the number of uses at
+    // that time. Kept here rather than on the heap so that upgrades don't reset the quotas.
+    pub static QUOTA_USES: RefCell<StableBTreeMap<QuotaUseKey, u32, Memory>> = RefCell::new(
+        StableBTreeMap::init(get_memory(QUOTA_USES_MEMORY_ID))
+    );
+
+    pub static QUOTA_USES_BY_PRINCIPAL: RefCell<StableBTreeMap<PrincipalQuotaUseKey, u32, Memory>> = RefCell::new(
+        StableBTreeMap::init(get_memory(QUOTA_USES_BY_PRINCIPAL_MEMORY_ID))
+    );
+
+    pub static AUDIT_LOG: RefCell<StableLog<AuditEntry, Memory, Memory>> = RefCell::new(
+        StableLog::init(get_memory(AUDIT_LOG_INDEX_MEMORY_ID), get_memory(AUDIT_LOG_DATA_MEMORY_ID))
+            .expect("failed to initialize the audit log")
+    );
+
+    // Indexes over `AUDIT_LOG` by canister and by caller, maintained by `audit::record`
+    pub static AUDIT_BY_CANISTER: RefCell<StableBTreeMap<AuditIndexKey, (), Memory>> = RefCell::new(
+        StableBTreeMap::init(get_memory(AUDIT_BY_CANISTER_MEMORY_ID))
+    );
+
+    pub static AUDIT_BY_ACTOR: RefCell<StableBTreeMap<AuditIndexKey, (), Memory>> = RefCell::new(
+        StableBTreeMap::init(get_memory(AUDIT_BY_ACTOR_MEMORY_ID))
+    );
+
+    // Number of entries of each principal in the indexes above, so listings don't count them
+    pub static AUDIT_COUNT_BY_CANISTER: RefCell<StableBTreeMap<Principal, u64, Memory>> = RefCell::new(
+        StableBTreeMap::init(get_memory(AUDIT_COUNT_BY_CANISTER_MEMORY_ID))
+    );
+
+    pub static AUDIT_COUNT_BY_ACTOR: RefCell<StableBTreeMap<Principal, u64, Memory>> = RefCell::new(
+        StableBTreeMap::init(get_memory(AUDIT_COUNT_BY_ACTOR_MEMORY_ID))
+    );
+
     pub static STATE: RefCell<State> = RefCell::new(State::default());
 
 }
@@ -0,0 +1,195 @@
+use candid::{CandidType, Principal};
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::time::Duration;
+use tracing::info;
+
+use crate::audit::{self, AuditAction, AuditRecord};
+use crate::features::{self, Feature};
+use crate::index::StatusIndexKey;
+use crate::management;
+use crate::memory::{STATE, STATUS_INDEX};
+use crate::strike::{self, StrikeStatus};
+
+/// How often the module hashes of trusted canisters are compared against the pinned ones
+const MODULE_HASH_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Calls to the management canister in flight at once during a check
+const CHECK_BATCH_SIZE: usize = 20;
+
+thread_local! {
+    // When the check in progress started, if any. Runs take several rounds of calls, so the next
+    // timer can fire while one is still running.
+    static RUNNING_SINCE: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+/// Marks a check as running until it's dropped
+struct RunningGuard;
+
+impl RunningGuard {
+    /// None while another check is running. A run that didn't finish within an interval is
+    /// considered gone, e.g. when a callback trapped before the guard was dropped.
+    fn acquire(now: u64) -> Option<Self> {
+        let running = RUNNING_SINCE.with(|r| r.get()).map_or(false, |since| {
+            Duration::from_nanos(now.saturating_sub(since)) < MODULE_HASH_CHECK_INTERVAL
+        });
+        if running {
+            return None;
+        }
+        RUNNING_SINCE.with(|r| r.set(Some(now)));
+        Some(Self)
+    }
+}
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        RUNNING_SINCE.with(|r| r.set(None));
+    }
+}
+
+/// Bookkeeping of the module hash check, kept across upgrades
+#[derive(Serialize, Deserialize, CandidType, Debug, Clone, Copy, Default, PartialEq)]
+pub struct MonitorRuns {
+    pub last_started_at: Option<u64>,
+    pub last_completed_at: Option<u64>,
+    /// Trusted canisters moved back to review since installation
+    pub demoted: u64,
+}
+
+pub fn get_monitor_runs() -> MonitorRuns {
+    STATE.with(|s| s.borrow().module_hash_monitor)
+}
+
+fn update_runs(update: impl FnOnce(&mut MonitorRuns)) {
+    STATE.with(|s| update(&mut s.borrow_mut().module_hash_monitor));
+}
+
+/// How long until the next check is due, so that upgrades don't postpone it by a full interval
+fn first_check_delay(last_started_at: Option<u64>, now: u64) -> Duration {
+    let elapsed = Duration::from_nanos(now.saturating_sub(last_started_at.unwrap_or(now)));
+    MODULE_HASH_CHECK_INTERVAL.saturating_sub(elapsed)
+}
+
+/// Starts the periodic module hash check. Timers don't survive upgrades, so this has to be
+/// called from both `init` and `post_upgrade`.
+pub fn start_module_hash_monitor() {
+    let delay = first_check_delay(get_monitor_runs().last_started_at, ic_cdk::api::time());
+    ic_cdk_timers::set_timer(delay, || {
+        ic_cdk::spawn(check_trusted_module_hashes());
+        ic_cdk_timers::set_timer_interval(MODULE_HASH_CHECK_INTERVAL, || ic_cdk::spawn(check_trusted_module_hashes()));
+    });
+}
+
+/// Moves every trusted canister whose installed code differs from the reviewed one back to review
+pub async fn check_trusted_module_hashes() {
+    if !features::is_enabled(Feature::ModuleHashMonitor) {
+        return;
+    }
+    let now = ic_cdk::api::time();
+    let Some(_running) = RunningGuard::acquire(now) else {
+        info!("Skipping module hash check, the previous one is still running");
+        return;
+    };
+    update_runs(|runs| runs.last_started_at = Some(now));
+
+    let trusted: Vec<Principal> = STATUS_INDEX.with(|s| {
+        s.borrow()
+            .range(StatusIndexKey::first(StrikeStatus::Trusted)..)
+            .take_while(|(key, _)| key.status == StrikeStatus::Trusted)
+            .map(|(key, _)| key.canister_id)
+            .collect()
+    });
+
+    for batch in trusted.chunks(CHECK_BATCH_SIZE) {
+        // The hashes the installed ones are compared against, as pinned before the calls
+        let pinned: Vec<Option<String>> = batch
+            .iter()
+            .map(|canister_id| strike::get_strike_by_canister_id(*canister_id).and_then(|registry| registry.module_hash))
+            .collect();
+        let infos =
+            futures::future::join_all(batch.iter().map(|canister_id| management::get_canister_info(*canister_id))).await;
+
+        for ((canister_id, pinned), info) in batch.iter().zip(pinned).zip(infos) {
+            match info {
+                Ok(info) => demote_if_changed(*canister_id, pinned, info.module_hash.map(hex::encode)),
+                // Transient failures shouldn't demote anything, we'll try again on the next run
+                Err(err) => info!("Skipping module hash check: {}", err),
+            }
+        }
+    }
+
+    update_runs(|runs| runs.last_completed_at = Some(ic_cdk::api::time()));
+}
+
+/// Compares the installed `module_hash` of a trusted canister against the `pinned` one
+fn demote_if_changed(canister_id: Principal, pinned: Option<String>, module_hash: Option<String>) {
+    // The entry may have been reviewed again, and another hash pinned, while we were waiting for
+    // the management canister. The installed hash says nothing about that one.
+    let Some(mut registry) = strike::get_strike_by_canister_id(canister_id) else {
+        return;
+    };
+    if registry.status != StrikeStatus::Trusted || registry.module_hash != pinned || registry.module_hash == module_hash {
+        return;
+    }
+
+    // Entries trusted before module hashes were pinned have none, their current code is taken
+    // as the reviewed one instead of demoting all of them on the first run
+    if registry.module_hash.is_none() {
+        let Some(module_hash) = module_hash else {
+            return;
+        };
+        info!("Pinning the module hash of {} to {}", canister_id, module_hash);
+
+        registry.module_hash = Some(module_hash.clone());
+        strike::save_registry(registry);
+
+        audit::record(
+            ic_cdk::id(),
+            AuditRecord::registry(canister_id, AuditAction::PinModuleHash).reason(Some(module_hash)),
+        );
+        return;
+    }
+
+    let reason = format!(
+        "Module hash changed from {} to {}",
+        registry.module_hash.as_deref().unwrap_or("none"),
+        module_hash.as_deref().unwrap_or("none"),
+    );
+    info!("Moving {} back to review: {}", canister_id, reason);
+
+    match strike::change_registry_status(ic_cdk::id(), canister_id, StrikeStatus::Submitted, Some(reason), None) {
+        Ok(()) => update_runs(|runs| runs.demoted += 1),
+        Err(err) => info!("Failed to move {} back to review: {}", canister_id, err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+    #[test]
+    fn test_first_check_delay() {
+        let now = 10 * 60 * 60 * NANOS_PER_SEC;
+        assert_eq!(first_check_delay(None, now), MODULE_HASH_CHECK_INTERVAL);
+        assert_eq!(
+            first_check_delay(Some(now - 15 * 60 * NANOS_PER_SEC), now),
+            Duration::from_secs(45 * 60)
+        );
+        assert_eq!(first_check_delay(Some(0), now), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_checks_dont_overlap() {
+        let now = 10 * 60 * 60 * NANOS_PER_SEC;
+        let running = RunningGuard::acquire(now).unwrap();
+        assert!(RunningGuard::acquire(now + NANOS_PER_SEC).is_none());
+
+        drop(running);
+        let stuck = RunningGuard::acquire(now).unwrap();
+        std::mem::forget(stuck);
+        assert!(RunningGuard::acquire(now + 60 * NANOS_PER_SEC).is_none());
+        assert!(RunningGuard::acquire(now + MODULE_HASH_CHECK_INTERVAL.as_nanos() as u64).is_some());
+    }
+}
@@ -0,0 +1,316 @@
+use candid::{CandidType, Principal};
+use ic_cdk::caller;
+use ic_stable_structures::{storable::Bound, Storable};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+use crate::encoding::{self, Record};
+use crate::error::StrikeError;
+use crate::features::{self, Feature};
+use crate::index::{self, read_principal, write_principal, IndexKey, MAX_PRINCIPAL, PRINCIPAL_SIZE};
+use crate::limits::{self, Quota, MAX_NOTE_LEN, MAX_URL_LEN};
+use crate::memory::{REPORTS, REPORT_COUNTS, REPORT_QUEUE};
+use crate::strike::{self, StrikeStatus};
+use crate::types::{CursorPagination, PaginatedResponse};
+
+// Reports a single caller may file per window
+const REPORTS_PER_WINDOW: u32 = 10;
+const REPORT_WINDOW_SECS: u64 = 24 * 60 * 60;
+
+#[derive(Serialize, Deserialize, CandidType, Debug, PartialEq, Clone, Copy)]
+pub enum ReportCategory {
+    Phishing,
+    Drainer,
+    Impersonation,
+    Scam,
+    Other,
+}
+
+#[derive(Serialize, Deserialize, CandidType, Debug, Clone)]
+pub struct Report {
+    pub canister_id: Principal,
+    pub reporter: Principal,
+    pub category: ReportCategory,
+    pub evidence_url: Option<String>,
+    pub note: Option<String>,
+    pub created_at: u64,
+}
+
+impl Record for Report {
+    const VERSION: u8 = 1;
+}
+
+impl Storable for Report {
+    fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
+        encoding::record_to_bytes(self)
+    }
+
+    fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
+        encoding::record_from_bytes(&bytes)
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// A reporter files at most one report per canister
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct ReportKey {
+    pub canister_id: Principal,
+    pub reporter: Principal,
+}
+
+impl Storable for ReportKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = Vec::with_capacity(2 * PRINCIPAL_SIZE);
+        write_principal(&mut bytes, &self.canister_id);
+        write_principal(&mut bytes, &self.reporter);
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Self {
+            canister_id: read_principal(&bytes),
+            reporter: read_principal(&bytes[PRINCIPAL_SIZE..]),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: (2 * PRINCIPAL_SIZE) as u32,
+        is_fixed_size: true,
+    };
+}
+
+/// Orders the reported canisters by report count, the most reported last
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct ReportQueueKey {
+    pub report_count: u32,
+    pub canister_id: Principal,
+}
+
+impl ReportQueueKey {
+    pub fn last() -> Self {
+        Self {
+            report_count: u32::MAX,
+            canister_id: MAX_PRINCIPAL,
+        }
+    }
+}
+
+impl IndexKey for ReportQueueKey {
+    fn canister_id(&self) -> Principal {
+        self.canister_id
+    }
+}
+
+impl Storable for ReportQueueKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = Vec::with_capacity(4 + PRINCIPAL_SIZE);
+        bytes.extend_from_slice(&self.report_count.to_be_bytes());
+        write_principal(&mut bytes, &self.canister_id);
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Self {
+            report_count: u32::from_be_bytes(bytes[..4].try_into().unwrap()),
+            canister_id: read_principal(&bytes[4..]),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: (4 + PRINCIPAL_SIZE) as u32,
+        is_fixed_size: true,
+    };
+}
+
+#[derive(CandidType, Deserialize, Debug)]
+pub struct ReportQueueEntry {
+    pub canister_id: Principal,
+    pub report_count: u32,
+    /// The status of the canister in the registry, none if it isn't registered
+    pub status: Option<StrikeStatus>,
+}
+
+/// Counts a report against the quota of `reporter`, failing once the quota of the window is used up
+fn consume_quota(reporter: Principal, now: u64) -> Result<(), StrikeError> {
+    limits::prune_uses(Quota::Reports, now, REPORT_WINDOW_SECS);
+
+    let recent = limits::recent_uses(Quota::Reports, Some(reporter), now, REPORT_WINDOW_SECS);
+    limits::check_window(&recent, now, REPORT_WINDOW_SECS, REPORTS_PER_WINDOW)?;
+
+    limits::record_use(Quota::Reports, reporter, now);
+    Ok(())
+}
+
+pub fn report_count(canister_id: Principal) -> u32 {
+    REPORT_COUNTS.with(|s| s.borrow().get(&canister_id).unwrap_or_default())
+}
+
+/// Files a report against any canister, registered or not, since the ones users run into while
+/// being phished rarely are. The quota of the reporter and one report per reporter and canister
+/// bound what the queue can grow by.
+pub fn report_canister(
+    canister_id: Principal,
+    category: ReportCategory,
+    evidence_url: Option<String>,
+    note: Option<String>,
+) -> Result<(), StrikeError> {
+    features::ensure_enabled(Feature::Reports)?;
+
+    let reporter = caller();
+
+    limits::check_lengths(&[
+        ("evidence_url", evidence_url.as_deref(), MAX_URL_LEN),
+        ("note", note.as_deref(), MAX_NOTE_LEN),
+    ])?;
+
+    let key = ReportKey { canister_id, reporter };
+    if REPORTS.with(|s| s.borrow().contains_key(&key)) {
+        return Err(StrikeError::AlreadyExists);
+    }
+
+    let now = ic_cdk::api::time();
+    consume_quota(reporter, now)?;
+
+    let report = Report {
+        canister_id,
+        reporter,
+        category,
+        evidence_url,
+        note,
+        created_at: now,
+    };
+    REPORTS.with(|s| s.borrow_mut().insert(key, report));
+
+    let old_count = report_count(canister_id);
+    let count = old_count + 1;
+    REPORT_COUNTS.with(|s| s.borrow_mut().insert(canister_id, count));
+    REPORT_QUEUE.with(|s| {
+        let mut queue = s.borrow_mut();
+        queue.remove(&ReportQueueKey {
+            report_count: old_count,
+            canister_id,
+        });
+        queue.insert(
+            ReportQueueKey {
+                report_count: count,
+                canister_id,
+            },
+            (),
+        );
+    });
+
+    strike::save_report_count(canister_id, count);
+
+    Ok(())
+}
+
+/// Removes the reports against a canister along with its place in the queue
+pub fn clear_reports(canister_id: Principal) {
+    let first = ReportKey {
+        canister_id,
+        reporter: index::MIN_PRINCIPAL,
+    };
+
+    REPORTS.with(|s| {
+        let mut reports = s.borrow_mut();
+        let keys: Vec<ReportKey> = reports
+            .range(first..)
+            .take_while(|(key, _)| key.canister_id == canister_id)
+            .map(|(key, _)| key)
+            .collect();
+        for key in keys {
+            reports.remove(&key);
+        }
+    });
+
+    if let Some(report_count) = REPORT_COUNTS.with(|s| s.borrow_mut().remove(&canister_id)) {
+        REPORT_QUEUE.with(|s| {
+            s.borrow_mut().remove(&ReportQueueKey {
+                report_count,
+                canister_id,
+            })
+        });
+    }
+}
+
+/// Lists the reported canisters, the most reported first
+pub fn get_report_queue(pagination: CursorPagination) -> Result<PaginatedResponse<ReportQueueEntry>, StrikeError> {
+    let page = REPORT_QUEUE.with(|s| {
+        index::scan(
+            &s.borrow(),
+            ReportQueueKey::last(),
+            pagination.start_after.as_deref(),
+            pagination.limit(),
+            true,
+            |_| true,
+        )
+    })?;
+
+    let items = page
+        .canister_ids
+        .into_iter()
+        .map(|canister_id| ReportQueueEntry {
+            canister_id,
+            report_count: report_count(canister_id),
+            status: strike::get_strike_by_canister_id(canister_id).map(|registry| registry.status),
+        })
+        .collect();
+
+    Ok(PaginatedResponse {
+        items,
+        total: REPORT_QUEUE.with(|s| s.borrow().len()) as u32,
+        next_cursor: page.next_cursor,
+    })
+}
+
+pub fn get_reports(canister_id: Principal) -> Vec<Report> {
+    let first = ReportKey {
+        canister_id,
+        reporter: index::MIN_PRINCIPAL,
+    };
+
+    REPORTS.with(|s| {
+        s.borrow()
+            .range(first..)
+            .take_while(|(key, _)| key.canister_id == canister_id)
+            .map(|(_, report)| report)
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALI: Principal = Principal::from_slice(&[1; 29]);
+    const BOB: Principal = Principal::from_slice(&[2; 29]);
+
+    #[test]
+    fn test_report_keys_roundtrip() {
+        let key = ReportKey {
+            canister_id: Principal::from_slice(&[0; 10]),
+            reporter: ALI,
+        };
+        assert_eq!(ReportKey::from_bytes(key.to_bytes()), key);
+
+        let key = ReportQueueKey {
+            report_count: 7,
+            canister_id: BOB,
+        };
+        assert_eq!(ReportQueueKey::from_bytes(key.to_bytes()), key);
+    }
+
+    #[test]
+    fn test_quota_resets_after_window() {
+        for _ in 0..REPORTS_PER_WINDOW {
+            consume_quota(ALI, 0).unwrap();
+        }
+        assert_eq!(
+            consume_quota(ALI, REPORT_WINDOW_SECS * 1_000_000_000 - 1),
+            Err(StrikeError::RateLimited { retry_after: 1 })
+        );
+        assert!(consume_quota(BOB, 1).is_ok());
+        assert!(consume_quota(ALI, REPORT_WINDOW_SECS * 1_000_000_000).is_ok());
+    }
+}
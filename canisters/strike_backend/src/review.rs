@@ -0,0 +1,265 @@
+use candid::{CandidType, Principal};
+use ic_cdk::caller;
+use ic_stable_structures::{storable::Bound, Storable};
+use serde::{Deserialize, Serialize};
+
+use crate::audit::{self, AuditAction, AuditRecord, AuditValue};
+use crate::encoding::{self, Record};
+use crate::error::StrikeError;
+use crate::limits::{self, MAX_REASON_LEN};
+use crate::memory::{REVIEWS, STATE};
+use crate::roles::{self, Role};
+use crate::strike::{self, StrikeStatus};
+use crate::types::{CursorPagination, PaginatedResponse};
+
+#[derive(Serialize, Deserialize, CandidType, Debug, Clone)]
+pub struct ReviewVote {
+    pub reviewer: Principal,
+    /// The status the entry should move to, `Submitted` to move a decided entry back to review
+    pub status: StrikeStatus,
+    pub reason: Option<String>,
+    pub timestamp: u64,
+}
+
+/// The votes cast on an entry since it was last decided
+#[derive(Serialize, Deserialize, CandidType, Debug, Clone)]
+pub struct Review {
+    pub canister_id: Principal,
+    pub votes: Vec<ReviewVote>,
+    /// Set when reviewers disagree, the entry is then decided by the next vote of an owner or admin
+    pub escalated: bool,
+}
+
+impl Record for Review {
+    const VERSION: u8 = 1;
+}
+
+impl Storable for Review {
+    fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
+        encoding::record_to_bytes(self)
+    }
+
+    fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
+        encoding::record_from_bytes(&bytes)
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl Review {
+    fn new(canister_id: Principal) -> Self {
+        Self {
+            canister_id,
+            votes: vec![],
+            escalated: false,
+        }
+    }
+
+    fn count(&self, status: StrikeStatus) -> u32 {
+        self.votes.iter().filter(|vote| vote.status == status).count() as u32
+    }
+
+    /// Records `vote`, replacing any earlier vote of the same reviewer, and returns the status the
+    /// entry has to move to, if the vote decides it. `decisive` votes settle escalated reviews.
+    fn cast(&mut self, vote: ReviewVote, quorum: u32, decisive: bool) -> Option<StrikeStatus> {
+        let status = vote.status;
+        self.votes.retain(|existing| existing.reviewer != vote.reviewer);
+        self.votes.push(vote);
+
+        if self.escalated || self.votes.iter().any(|vote| vote.status != status) {
+            if decisive {
+                return Some(status);
+            }
+
+            self.escalated = true;
+            return None;
+        }
+
+        (self.count(status) >= quorum).then_some(status)
+    }
+}
+
+pub fn get_review_quorum() -> u32 {
+    STATE.with(|s| s.borrow().review_quorum)
+}
+
+pub fn set_review_quorum(quorum: u32) -> Result<u32, StrikeError> {
+    if quorum == 0 {
+        return Err(StrikeError::invalid_field("quorum", "must be at least 1"));
+    }
+
+    Ok(STATE.with(|s| std::mem::replace(&mut s.borrow_mut().review_quorum, quorum)))
+}
+
+pub fn get_review(canister_id: Principal) -> Option<Review> {
+    REVIEWS.with(|s| s.borrow().get(&canister_id))
+}
+
+/// Drops the votes on an entry, e.g. when it's resubmitted with new details
+pub fn clear_review(canister_id: Principal) {
+    REVIEWS.with(|s| s.borrow_mut().remove(&canister_id));
+}
+
+/// Casts the caller's vote on an entry, which moves to the status once the votes reach the quorum.
+/// `Submitted` votes move a decided entry back to review.
+pub async fn vote(canister_id: Principal, status: StrikeStatus, reason: Option<String>) -> Result<(), StrikeError> {
+    let caller = caller();
+
+    limits::check_lengths(&[("reason", reason.as_deref(), MAX_REASON_LEN)])?;
+    ensure_status_changes(canister_id, status)?;
+
+    // Fetched before the vote is counted, so that no other vote can change the review between
+    // deciding on the status and applying it
+    let module_hash = strike::module_hash_to_pin(canister_id, status).await?;
+    ensure_status_changes(canister_id, status)?;
+
+    let decisive = roles::effective_role(caller).map_or(false, Role::is_admin);
+    let vote = ReviewVote {
+        reviewer: caller,
+        status,
+        reason: reason.clone(),
+        timestamp: ic_cdk::api::time(),
+    };
+
+    let decision = REVIEWS.with(|s| {
+        let mut reviews = s.borrow_mut();
+        let mut review = reviews.get(&canister_id).unwrap_or_else(|| Review::new(canister_id));
+        let decision = review.cast(vote, get_review_quorum(), decisive);
+        reviews.insert(canister_id, review);
+        decision
+    });
+
+    audit::record(
+        caller,
+        AuditRecord::registry(canister_id, AuditAction::ReviewVote)
+            .values(None, Some(AuditValue::Status(status)))
+            .reason(reason.clone()),
+    );
+
+    let Some(decision) = decision else {
+        return Ok(());
+    };
+
+    strike::set_registry_status(canister_id, decision, reason, module_hash)
+}
+
+fn ensure_status_changes(canister_id: Principal, status: StrikeStatus) -> Result<(), StrikeError> {
+    let registry = strike::get_strike_by_canister_id(canister_id).ok_or(StrikeError::NotFound)?;
+    if registry.status == status {
+        return Err(StrikeError::InvalidStatus { status });
+    }
+    Ok(())
+}
+
+/// Lists the entries with votes that haven't been decided yet, ordered by canister ID
+pub fn get_pending_reviews(pagination: CursorPagination) -> Result<PaginatedResponse<Review>, StrikeError> {
+    let start_after = pagination
+        .start_after
+        .as_deref()
+        .map(Principal::from_text)
+        .transpose()
+        .map_err(|_| StrikeError::invalid_cursor())?;
+
+    let limit = pagination.limit();
+    let (total, mut items) = REVIEWS.with(|s| {
+        let reviews = s.borrow();
+        let items: Vec<Review> = match start_after {
+            Some(start_after) => reviews
+                .range(start_after..)
+                .skip_while(|(canister_id, _)| *canister_id == start_after)
+                .take(limit + 1)
+                .map(|(_, review)| review)
+                .collect(),
+            None => reviews.iter().take(limit + 1).map(|(_, review)| review).collect(),
+        };
+        (reviews.len() as u32, items)
+    });
+
+    let next_cursor = if items.len() > limit {
+        items.truncate(limit);
+        items.last().map(|review| review.canister_id.to_text())
+    } else {
+        None
+    };
+
+    Ok(PaginatedResponse {
+        items,
+        total,
+        next_cursor,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CANISTER: Principal = Principal::from_slice(&[0; 10]);
+    const ALI: Principal = Principal::from_slice(&[1; 29]);
+    const BOB: Principal = Principal::from_slice(&[2; 29]);
+    const CAROL: Principal = Principal::from_slice(&[3; 29]);
+
+    fn vote(reviewer: Principal, status: StrikeStatus) -> ReviewVote {
+        ReviewVote {
+            reviewer,
+            status,
+            reason: None,
+            timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn test_trusted_once_quorum_is_met() {
+        let mut review = Review::new(CANISTER);
+
+        assert_eq!(review.cast(vote(ALI, StrikeStatus::Trusted), 2, false), None);
+        // A second approval of the same reviewer doesn't count
+        assert_eq!(review.cast(vote(ALI, StrikeStatus::Trusted), 2, false), None);
+        assert_eq!(
+            review.cast(vote(BOB, StrikeStatus::Trusted), 2, false),
+            Some(StrikeStatus::Trusted)
+        );
+    }
+
+    #[test]
+    fn test_blocked_once_quorum_is_met() {
+        let mut review = Review::new(CANISTER);
+
+        assert_eq!(review.cast(vote(ALI, StrikeStatus::Blocked), 2, false), None);
+        assert_eq!(
+            review.cast(vote(BOB, StrikeStatus::Blocked), 2, false),
+            Some(StrikeStatus::Blocked)
+        );
+    }
+
+    #[test]
+    fn test_conflicting_votes_escalate() {
+        let mut review = Review::new(CANISTER);
+
+        assert_eq!(review.cast(vote(ALI, StrikeStatus::Trusted), 2, false), None);
+        assert_eq!(review.cast(vote(BOB, StrikeStatus::Blocked), 2, false), None);
+        assert!(review.escalated);
+
+        // Reaching the quorum doesn't settle an escalated review, an admin has to
+        assert_eq!(review.cast(vote(CAROL, StrikeStatus::Trusted), 2, false), None);
+        assert_eq!(
+            review.cast(vote(CAROL, StrikeStatus::Blocked), 2, true),
+            Some(StrikeStatus::Blocked)
+        );
+    }
+
+    #[test]
+    fn test_moving_back_to_review_needs_quorum() {
+        let mut review = Review::new(CANISTER);
+
+        assert_eq!(review.cast(vote(ALI, StrikeStatus::Submitted), 2, true), None);
+        assert_eq!(
+            review.cast(vote(BOB, StrikeStatus::Submitted), 2, false),
+            Some(StrikeStatus::Submitted)
+        );
+
+        let mut review = Review::new(CANISTER);
+        assert_eq!(review.cast(vote(ALI, StrikeStatus::Submitted), 2, false), None);
+        assert_eq!(review.cast(vote(BOB, StrikeStatus::Blocked), 2, false), None);
+        assert!(review.escalated);
+    }
+}
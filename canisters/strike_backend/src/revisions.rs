@@ -0,0 +1,337 @@
+use candid::{CandidType, Principal};
+use ic_cdk::caller;
+use ic_stable_structures::{storable::Bound, Storable};
+use serde::{Deserialize, Serialize};
+
+use crate::audit::{self, AuditAction, AuditRecord};
+use crate::encoding::{self, Record};
+use crate::error::StrikeError;
+use crate::limits::{
+    self, MAX_DESCRIPTION_LEN, MAX_EMAIL_LEN, MAX_HANDLE_LEN, MAX_NAME_LEN, MAX_PROJECT_NAME_LEN, MAX_REASON_LEN, MAX_URL_LEN,
+};
+use crate::memory::REVISIONS;
+use crate::review;
+use crate::strike::{self, StrikeRegistry, StrikeStatus};
+use crate::types::{CursorPagination, PaginatedResponse};
+use crate::validation;
+
+/// Changes to the details of an entry, fields left out stay as they are. An empty string clears
+/// an optional field.
+#[derive(Serialize, Deserialize, CandidType, Debug, Clone, Default, PartialEq)]
+pub struct RegistryDetails {
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub telegram: Option<String>,
+    pub twitter: Option<String>,
+    pub project_name: Option<String>,
+    pub description: Option<String>,
+    pub website_url: Option<String>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct UpdateRegistryDetailsParams {
+    pub canister_id: Principal,
+    pub details: RegistryDetails,
+}
+
+/// An edit of a trusted entry, held until a reviewer approves it while the approved details stay live
+#[derive(Serialize, Deserialize, CandidType, Debug, Clone)]
+pub struct Revision {
+    pub canister_id: Principal,
+    pub details: RegistryDetails,
+    pub submitted_by: Principal,
+    pub submitted_at: u64,
+}
+
+impl Record for Revision {
+    const VERSION: u8 = 1;
+}
+
+impl Storable for Revision {
+    fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
+        encoding::record_to_bytes(self)
+    }
+
+    fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
+        encoding::record_from_bytes(&bytes)
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+fn optional(value: String) -> Option<String> {
+    (!value.trim().is_empty()).then_some(value)
+}
+
+/// Validates an optional field unless it's being cleared
+fn clearable(
+    field: &str,
+    value: Option<String>,
+    validate: fn(&str, &str) -> Result<String, StrikeError>,
+) -> Result<Option<String>, StrikeError> {
+    match value {
+        Some(value) if value.trim().is_empty() => Ok(Some(String::new())),
+        Some(value) => validate(field, &value).map(Some),
+        None => Ok(None),
+    }
+}
+
+fn required(
+    field: &str,
+    value: Option<String>,
+    validate: fn(&str, &str) -> Result<String, StrikeError>,
+) -> Result<Option<String>, StrikeError> {
+    value.map(|value| validate(field, &value)).transpose()
+}
+
+impl RegistryDetails {
+    /// Checks the given fields and brings them to their canonical form, e.g. handles to `@name`
+    pub fn validate(self) -> Result<Self, StrikeError> {
+        limits::check_lengths(&[
+            ("name", self.name.as_deref(), MAX_NAME_LEN),
+            ("email", self.email.as_deref(), MAX_EMAIL_LEN),
+            ("telegram", self.telegram.as_deref(), MAX_HANDLE_LEN),
+            ("twitter", self.twitter.as_deref(), MAX_HANDLE_LEN),
+            ("project_name", self.project_name.as_deref(), MAX_PROJECT_NAME_LEN),
+            ("description", self.description.as_deref(), MAX_DESCRIPTION_LEN),
+            ("website_url", self.website_url.as_deref(), MAX_URL_LEN),
+        ])?;
+
+        let details = RegistryDetails {
+            name: required("name", self.name, validation::text)?,
+            email: required("email", self.email, validation::email)?,
+            telegram: clearable("telegram", self.telegram, validation::telegram)?,
+            twitter: clearable("twitter", self.twitter, validation::twitter)?,
+            project_name: required("project_name", self.project_name, validation::text)?,
+            description: required("description", self.description, validation::multiline_text)?,
+            website_url: clearable("website_url", self.website_url, validation::website_url)?,
+        };
+
+        if details == RegistryDetails::default() {
+            return Err(StrikeError::invalid_field("details", "contain no changes"));
+        }
+
+        Ok(details)
+    }
+
+    /// Splits off the private contact details of the submitter, which don't affect how the entry is
+    /// reviewed. Social handles are public and point users somewhere, so they're reviewed like links.
+    fn split_cosmetic(self) -> (RegistryDetails, RegistryDetails) {
+        let cosmetic = RegistryDetails {
+            name: self.name,
+            email: self.email,
+            ..Default::default()
+        };
+        let reviewed = RegistryDetails {
+            telegram: self.telegram,
+            twitter: self.twitter,
+            project_name: self.project_name,
+            description: self.description,
+            website_url: self.website_url,
+            ..Default::default()
+        };
+        (cosmetic, reviewed)
+    }
+
+    pub fn apply_to(self, registry: &mut StrikeRegistry) {
+        if let Some(name) = self.name {
+            registry.name = name;
+        }
+        if let Some(email) = self.email {
+            registry.email = email;
+        }
+        if let Some(telegram) = self.telegram {
+            registry.telegram = optional(telegram);
+        }
+        if let Some(twitter) = self.twitter {
+            registry.twitter = optional(twitter);
+        }
+        if let Some(project_name) = self.project_name {
+            registry.project_name = project_name;
+        }
+        if let Some(description) = self.description {
+            registry.description = description;
+        }
+        if let Some(website_url) = self.website_url {
+            registry.website_url = optional(website_url);
+        }
+    }
+}
+
+pub fn get_revision(canister_id: Principal) -> Option<Revision> {
+    REVISIONS.with(|s| s.borrow().get(&canister_id))
+}
+
+/// Edits the details of an entry on behalf of its submitter. Entries under review are updated in
+/// place, edits of the public details of trusted entries wait for a reviewer.
+pub fn update_registry_details(params: UpdateRegistryDetailsParams) -> Result<(), StrikeError> {
+    let caller = caller();
+    let canister_id = params.canister_id;
+
+    let details = params.details.validate()?;
+
+    let mut registry = strike::get_strike_by_canister_id(canister_id).ok_or(StrikeError::NotFound)?;
+    if registry.added_by != caller {
+        return Err(StrikeError::Unauthorized);
+    }
+
+    let pending = match registry.status {
+        // Blocked entries are appealed instead
+        StrikeStatus::Blocked => return Err(StrikeError::InvalidStatus { status: registry.status }),
+        StrikeStatus::Submitted => {
+            details.apply_to(&mut registry);
+            // Votes were cast on the previous details, and edits made while it was trusted are moot
+            review::clear_review(canister_id);
+            clear_revision(canister_id);
+            None
+        }
+        StrikeStatus::Trusted => {
+            let (cosmetic, reviewed) = details.split_cosmetic();
+            cosmetic.apply_to(&mut registry);
+            (reviewed != RegistryDetails::default()).then_some(reviewed)
+        }
+    };
+
+    strike::save_registry(registry);
+
+    // A new revision replaces the pending one
+    if let Some(details) = pending {
+        let revision = Revision {
+            canister_id,
+            details,
+            submitted_by: caller,
+            submitted_at: ic_cdk::api::time(),
+        };
+        REVISIONS.with(|s| s.borrow_mut().insert(canister_id, revision));
+    }
+
+    audit::record(caller, AuditRecord::registry(canister_id, AuditAction::UpdateRegistryDetails));
+
+    Ok(())
+}
+
+pub fn approve_revision(canister_id: Principal) -> Result<(), StrikeError> {
+    let revision = get_revision(canister_id).ok_or(StrikeError::NotFound)?;
+    let mut registry = strike::get_strike_by_canister_id(canister_id).ok_or(StrikeError::NotFound)?;
+
+    // Revisions are cleared when an entry stops being trusted, this guards against any that weren't
+    if registry.status != StrikeStatus::Trusted {
+        return Err(StrikeError::InvalidStatus { status: registry.status });
+    }
+
+    revision.details.apply_to(&mut registry);
+    strike::save_registry(registry);
+    REVISIONS.with(|s| s.borrow_mut().remove(&canister_id));
+
+    audit::record(caller(), AuditRecord::registry(canister_id, AuditAction::ApproveRevision));
+
+    Ok(())
+}
+
+pub fn reject_revision(canister_id: Principal, reason: Option<String>) -> Result<(), StrikeError> {
+    limits::check_lengths(&[("reason", reason.as_deref(), MAX_REASON_LEN)])?;
+
+    if REVISIONS.with(|s| s.borrow_mut().remove(&canister_id)).is_none() {
+        return Err(StrikeError::NotFound);
+    }
+
+    audit::record(
+        caller(),
+        AuditRecord::registry(canister_id, AuditAction::RejectRevision).reason(reason),
+    );
+
+    Ok(())
+}
+
+/// Drops the pending edit of an entry, e.g. when it stops being trusted
+pub fn clear_revision(canister_id: Principal) {
+    REVISIONS.with(|s| s.borrow_mut().remove(&canister_id));
+}
+
+/// Lists the revisions awaiting a reviewer, ordered by canister ID
+pub fn get_pending_revisions(pagination: CursorPagination) -> Result<PaginatedResponse<Revision>, StrikeError> {
+    let start_after = pagination
+        .start_after
+        .as_deref()
+        .map(Principal::from_text)
+        .transpose()
+        .map_err(|_| StrikeError::invalid_cursor())?;
+
+    let limit = pagination.limit();
+    let (total, mut items) = REVISIONS.with(|s| {
+        let revisions = s.borrow();
+        let items: Vec<Revision> = match start_after {
+            Some(start_after) => revisions
+                .range(start_after..)
+                .skip_while(|(canister_id, _)| *canister_id == start_after)
+                .take(limit + 1)
+                .map(|(_, revision)| revision)
+                .collect(),
+            None => revisions.iter().take(limit + 1).map(|(_, revision)| revision).collect(),
+        };
+        (revisions.len() as u32, items)
+    });
+
+    let next_cursor = if items.len() > limit {
+        items.truncate(limit);
+        items.last().map(|revision| revision.canister_id.to_text())
+    } else {
+        None
+    };
+
+    Ok(PaginatedResponse {
+        items,
+        total,
+        next_cursor,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_details() {
+        assert_eq!(
+            RegistryDetails::default().validate(),
+            Err(StrikeError::invalid_field("details", "contain no changes"))
+        );
+
+        let details = RegistryDetails {
+            description: Some(" ".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(details.validate(), Err(StrikeError::empty("description")));
+
+        let details = RegistryDetails {
+            twitter: Some("https://x.com/Strike".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(details.validate().unwrap().twitter, Some("@strike".to_string()));
+
+        // Optional fields can be cleared
+        let details = RegistryDetails {
+            twitter: Some(String::new()),
+            ..Default::default()
+        };
+        assert_eq!(details.validate().unwrap().twitter, Some(String::new()));
+    }
+
+    #[test]
+    fn test_split_cosmetic() {
+        let details = RegistryDetails {
+            email: Some("ali@example.com".to_string()),
+            twitter: Some("@strike".to_string()),
+            website_url: Some("https://example.com".to_string()),
+            ..Default::default()
+        };
+
+        let (cosmetic, reviewed) = details.split_cosmetic();
+        assert_eq!(cosmetic.email, Some("ali@example.com".to_string()));
+        assert_eq!(cosmetic.twitter, None);
+        assert_eq!(cosmetic.website_url, None);
+        assert_eq!(reviewed.email, None);
+        assert_eq!(reviewed.twitter, Some("@strike".to_string()));
+        assert_eq!(reviewed.website_url, Some("https://example.com".to_string()));
+    }
+}
@@ -0,0 +1,232 @@
+use candid::{CandidType, Principal};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::error::StrikeError;
+use crate::memory::{ADMINS, ROLES};
+use crate::strike::StrikeRegistry;
+
+/// Roles of the staff, a principal has at most one. Controllers of the canister are implicit owners.
+#[derive(Serialize, Deserialize, CandidType, Debug, PartialEq, Eq, Clone, Copy)]
+#[repr(u8)]
+pub enum Role {
+    /// Manages every role, including other owners
+    Owner = 0,
+    /// Manages every role but owners, reviews entries and reads contact details
+    Admin = 1,
+    /// Changes the status of entries, but doesn't manage people
+    Reviewer = 2,
+    /// Reads the contact details of submitters
+    Support = 3,
+}
+
+impl Role {
+    pub fn try_from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Role::Owner),
+            1 => Some(Role::Admin),
+            2 => Some(Role::Reviewer),
+            3 => Some(Role::Support),
+            _ => None,
+        }
+    }
+
+    pub fn from_u8(value: u8) -> Self {
+        Self::try_from_u8(value).unwrap_or_else(|| panic!("invalid role {}", value))
+    }
+
+    pub fn can_manage(self, role: Role) -> bool {
+        match self {
+            Role::Owner => true,
+            Role::Admin => role != Role::Owner,
+            Role::Reviewer | Role::Support => false,
+        }
+    }
+
+    pub fn is_admin(self) -> bool {
+        matches!(self, Role::Owner | Role::Admin)
+    }
+
+    pub fn can_review(self) -> bool {
+        matches!(self, Role::Owner | Role::Admin | Role::Reviewer)
+    }
+
+    pub fn can_read_pii(self) -> bool {
+        matches!(self, Role::Owner | Role::Admin | Role::Support)
+    }
+}
+
+#[derive(CandidType, Deserialize, Debug, PartialEq)]
+pub struct RoleAssignment {
+    pub principal: Principal,
+    pub role: Role,
+}
+
+/// The role granted to `principal`, not counting the implicit ownership of controllers
+pub fn get_role(principal: Principal) -> Option<Role> {
+    ROLES.with(|roles| roles.borrow().get(&principal).map(Role::from_u8))
+}
+
+/// The role of `principal`, counting controllers of the canister as owners
+pub fn effective_role(principal: Principal) -> Option<Role> {
+    if ic_cdk::api::is_controller(&principal) {
+        return Some(Role::Owner);
+    }
+
+    get_role(principal)
+}
+
+pub fn set_role_unchecked(principal: Principal, role: Role) {
+    ROLES.with(|roles| roles.borrow_mut().insert(principal, role as u8));
+}
+
+/// Grants `role` to `principal` on behalf of a caller with the role `granter`, returns the replaced role
+pub fn grant_role(granter: Option<Role>, principal: Principal, role: Role) -> Result<Option<Role>, StrikeError> {
+    let old = get_role(principal);
+
+    let allowed = granter.map_or(false, |granter| {
+        granter.can_manage(role) && old.map_or(true, |old| granter.can_manage(old))
+    });
+    if !allowed {
+        return Err(StrikeError::Unauthorized);
+    }
+
+    if old == Some(role) {
+        return Err(StrikeError::AlreadyExists);
+    }
+
+    set_role_unchecked(principal, role);
+    Ok(old)
+}
+
+/// Revokes the role of `principal` on behalf of a caller with the role `revoker`, returns the revoked role
+pub fn revoke_role(revoker: Option<Role>, principal: Principal) -> Result<Role, StrikeError> {
+    let Some(old) = get_role(principal) else {
+        return Err(StrikeError::NotFound);
+    };
+
+    if !revoker.map_or(false, |revoker| revoker.can_manage(old)) {
+        return Err(StrikeError::Unauthorized);
+    }
+
+    ROLES.with(|roles| roles.borrow_mut().remove(&principal));
+    Ok(old)
+}
+
+pub fn list_roles() -> Vec<RoleAssignment> {
+    ROLES.with(|roles| {
+        roles
+            .borrow()
+            .iter()
+            .map(|(principal, role)| RoleAssignment {
+                principal,
+                role: Role::from_u8(role),
+            })
+            .collect()
+    })
+}
+
+/// Hides the contact details of a submitter from callers who are neither the submitter nor
+/// allowed to read them
+pub fn redact_for(caller: Principal, mut registry: StrikeRegistry) -> StrikeRegistry {
+    if registry.added_by == caller || effective_role(caller).map_or(false, Role::can_read_pii) {
+        return registry;
+    }
+
+    registry.name = String::new();
+    registry.email = String::new();
+    registry
+}
+
+/// Moves the principals of the admin map used before roles to the Admin role
+pub fn migrate_admins() {
+    let admins: Vec<Principal> = ADMINS.with(|admins| {
+        admins
+            .borrow()
+            .iter()
+            .filter(|(_, is_admin)| *is_admin == 1)
+            .map(|(principal, _)| principal)
+            .collect()
+    });
+    if admins.is_empty() {
+        return;
+    }
+
+    info!("Migrating {} admins to roles", admins.len());
+
+    for admin in admins {
+        if get_role(admin).is_none() {
+            set_role_unchecked(admin, Role::Admin);
+        }
+    }
+    ADMINS.with(|admins| admins.borrow_mut().clear_new());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALI: Principal = Principal::from_slice(&[0; 24]);
+    const BOB: Principal = Principal::from_slice(&[1; 24]);
+    const CAROL: Principal = Principal::from_slice(&[2; 24]);
+
+    #[test]
+    fn test_grant_role() {
+        set_role_unchecked(ALI, Role::Admin);
+
+        grant_role(get_role(ALI), BOB, Role::Reviewer).unwrap();
+        assert_eq!(get_role(BOB), Some(Role::Reviewer));
+
+        // Reviewers don't manage people and admins don't manage owners
+        assert_eq!(
+            grant_role(get_role(BOB), CAROL, Role::Support),
+            Err(StrikeError::Unauthorized)
+        );
+        assert_eq!(grant_role(get_role(ALI), CAROL, Role::Owner), Err(StrikeError::Unauthorized));
+        assert_eq!(grant_role(None, CAROL, Role::Support), Err(StrikeError::Unauthorized));
+        assert_eq!(
+            grant_role(get_role(ALI), BOB, Role::Reviewer),
+            Err(StrikeError::AlreadyExists)
+        );
+
+        assert_eq!(grant_role(Some(Role::Owner), BOB, Role::Admin), Ok(Some(Role::Reviewer)));
+        assert_eq!(get_role(BOB), Some(Role::Admin));
+    }
+
+    #[test]
+    fn test_revoke_role() {
+        set_role_unchecked(ALI, Role::Admin);
+        set_role_unchecked(BOB, Role::Owner);
+        set_role_unchecked(CAROL, Role::Support);
+
+        assert_eq!(revoke_role(get_role(ALI), BOB), Err(StrikeError::Unauthorized));
+        assert_eq!(revoke_role(get_role(ALI), CAROL), Ok(Role::Support));
+        assert_eq!(get_role(CAROL), None);
+        assert_eq!(revoke_role(get_role(ALI), CAROL), Err(StrikeError::NotFound));
+
+        assert_eq!(revoke_role(get_role(BOB), ALI), Ok(Role::Admin));
+        assert_eq!(
+            list_roles(),
+            vec![RoleAssignment {
+                principal: BOB,
+                role: Role::Owner
+            }]
+        );
+    }
+
+    #[test]
+    fn test_migrate_admins() {
+        ADMINS.with(|admins| {
+            let mut admins = admins.borrow_mut();
+            admins.insert(ALI, 1);
+            admins.insert(BOB, 1);
+        });
+        set_role_unchecked(BOB, Role::Owner);
+
+        migrate_admins();
+
+        assert_eq!(get_role(ALI), Some(Role::Admin));
+        assert_eq!(get_role(BOB), Some(Role::Owner));
+        assert!(ADMINS.with(|admins| admins.borrow().is_empty()));
+    }
+}
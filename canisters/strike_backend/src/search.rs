@@ -0,0 +1,326 @@
+use candid::{CandidType, Principal};
+use ic_stable_structures::{storable::Bound, Storable};
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use tracing::info;
+
+use crate::error::StrikeError;
+use crate::index::{read_principal, write_principal, MIN_PRINCIPAL, PRINCIPAL_SIZE};
+use crate::memory::{REGISTRY, SEARCH_INDEX};
+use crate::strike::{StrikeRegistry, StrikeStatus};
+use crate::types::{CursorPagination, PaginatedResponse};
+
+// Tokens are truncated to this many bytes, longer query terms still match them by prefix
+const TOKEN_SIZE: usize = 32;
+// Shorter tokens would match most of the index by prefix
+const MIN_TOKEN_LEN: usize = 2;
+// Upper bound of the entries matched by the most selective query term, to stay within the query
+// instruction limit. Less selective terms are checked against the entries of that one.
+const MAX_TERM_MATCHES: usize = 10_000;
+
+/// Maps a token to an entry containing it, the value is the status of the entry so that
+/// status filters don't need to load the entry
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub struct SearchIndexKey {
+    pub token: String,
+    pub canister_id: Principal,
+}
+
+#[derive(CandidType, Deserialize, Default)]
+pub struct SearchFilters {
+    pub status: Option<StrikeStatus>,
+}
+
+impl Storable for SearchIndexKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = vec![0u8; 1 + TOKEN_SIZE];
+        bytes[0] = self.token.len() as u8;
+        bytes[1..=self.token.len()].copy_from_slice(self.token.as_bytes());
+        write_principal(&mut bytes, &self.canister_id);
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let token_len = (bytes[0] as usize).min(TOKEN_SIZE);
+        Self {
+            token: String::from_utf8_lossy(&bytes[1..=token_len]).into_owned(),
+            canister_id: read_principal(&bytes[1 + TOKEN_SIZE..]),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: (1 + TOKEN_SIZE + PRINCIPAL_SIZE) as u32,
+        is_fixed_size: true,
+    };
+}
+
+/// Splits text into case folded alphanumeric tokens
+fn tokenize(text: &str, tokens: &mut BTreeSet<String>) {
+    for word in text.split(|c: char| !c.is_alphanumeric()) {
+        let mut token = word.to_lowercase();
+        if token.chars().count() < MIN_TOKEN_LEN {
+            continue;
+        }
+
+        let mut len = token.len().min(TOKEN_SIZE);
+        while !token.is_char_boundary(len) {
+            len -= 1;
+        }
+        token.truncate(len);
+        tokens.insert(token);
+    }
+}
+
+/// Returns the host of an http(s) URL, e.g. `app.example.com` for `https://app.example.com/path`
+fn url_host(url: &str) -> Option<&str> {
+    let url = url.trim();
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?.split(':').next()?;
+    (!host.is_empty()).then_some(host)
+}
+
+/// The tokens of the public fields of an entry. The contact details of the submitter stay out,
+/// since anyone can search and see which entries a name matches.
+fn registry_tokens(registry: &StrikeRegistry) -> BTreeSet<String> {
+    let mut tokens = BTreeSet::new();
+    tokenize(&registry.project_name, &mut tokens);
+    tokenize(&registry.description, &mut tokens);
+    if let Some(host) = registry.website_url.as_deref().and_then(url_host) {
+        tokenize(host, &mut tokens);
+    }
+    tokens
+}
+
+/// Keeps the search index consistent with a write to `REGISTRY`, replacing `old` with `new`
+pub fn reindex(old: Option<&StrikeRegistry>, new: Option<&StrikeRegistry>) {
+    SEARCH_INDEX.with(|s| {
+        let mut index = s.borrow_mut();
+
+        if let Some(old) = old {
+            for token in registry_tokens(old) {
+                index.remove(&SearchIndexKey {
+                    token,
+                    canister_id: old.canister_id,
+                });
+            }
+        }
+
+        if let Some(new) = new {
+            for token in registry_tokens(new) {
+                let key = SearchIndexKey {
+                    token,
+                    canister_id: new.canister_id,
+                };
+                index.insert(key, new.status as u8);
+            }
+        }
+    });
+}
+
+/// Builds the search index from scratch when it's empty, e.g. after upgrading from a version
+/// that didn't maintain it
+pub fn rebuild_if_missing() {
+    let missing = SEARCH_INDEX.with(|s| s.borrow().is_empty()) && REGISTRY.with(|s| !s.borrow().is_empty());
+    if !missing {
+        return;
+    }
+
+    rebuild();
+}
+
+/// Builds the search index from scratch, e.g. once the fields it covers have changed
+pub fn rebuild() {
+    info!("Rebuilding search index");
+
+    SEARCH_INDEX.with(|s| s.borrow_mut().clear_new());
+    REGISTRY.with(|s| {
+        for (_, registry) in s.borrow().iter() {
+            reindex(None, Some(&registry));
+        }
+    });
+}
+
+/// Entries matching a single query term by prefix, along with their status, or `None` when the
+/// term matches more than `MAX_TERM_MATCHES` of them
+fn term_matches(term: &str) -> Option<Vec<(Principal, StrikeStatus)>> {
+    let matches: Vec<_> = SEARCH_INDEX.with(|s| {
+        s.borrow()
+            .range(
+                SearchIndexKey {
+                    token: term.to_string(),
+                    canister_id: MIN_PRINCIPAL,
+                }..,
+            )
+            .take_while(|(key, _)| key.token.starts_with(term))
+            .take(MAX_TERM_MATCHES + 1)
+            .map(|(key, status)| (key.canister_id, StrikeStatus::from_u8(status)))
+            .collect()
+    });
+    (matches.len() <= MAX_TERM_MATCHES).then_some(matches)
+}
+
+/// Whether the entry has a token starting with every one of `terms`
+fn registry_matches(canister_id: &Principal, terms: &[&String]) -> bool {
+    REGISTRY.with(|s| {
+        s.borrow().get(canister_id).map_or(false, |registry| {
+            let tokens = registry_tokens(&registry);
+            terms
+                .iter()
+                .all(|term| tokens.iter().any(|token| token.starts_with(term.as_str())))
+        })
+    })
+}
+
+/// Finds the entries matching every term of the query by prefix, ordered by canister ID
+pub fn search_registries(
+    query: String,
+    filters: SearchFilters,
+    pagination: CursorPagination,
+) -> Result<PaginatedResponse<StrikeRegistry>, StrikeError> {
+    let mut terms = BTreeSet::new();
+    tokenize(&query, &mut terms);
+    if terms.is_empty() {
+        return Err(StrikeError::invalid_field(
+            "query",
+            format!("needs a term of at least {} characters", MIN_TOKEN_LEN),
+        ));
+    }
+
+    let start_after = pagination
+        .start_after
+        .as_deref()
+        .map(Principal::from_text)
+        .transpose()
+        .map_err(|_| StrikeError::invalid_cursor())?;
+
+    // Terms matching too many entries to collect are checked against the entries of the others
+    let mut matches: Option<BTreeSet<Principal>> = None;
+    let mut broad_terms = Vec::new();
+    for term in &terms {
+        let Some(term_matches) = term_matches(term) else {
+            broad_terms.push(term);
+            continue;
+        };
+        let term_matches: BTreeSet<Principal> = term_matches
+            .into_iter()
+            .filter(|(_, status)| filters.status.map_or(true, |filter| *status == filter))
+            .map(|(canister_id, _)| canister_id)
+            .collect();
+
+        matches = Some(match matches {
+            Some(matches) => matches.intersection(&term_matches).copied().collect(),
+            None => term_matches,
+        });
+    }
+    let Some(mut matches) = matches else {
+        return Err(StrikeError::invalid_field(
+            "query",
+            format!("matches more than {} entries, add a more specific term", MAX_TERM_MATCHES),
+        ));
+    };
+    if !broad_terms.is_empty() {
+        matches.retain(|canister_id| registry_matches(canister_id, &broad_terms));
+    }
+
+    let limit = pagination.limit();
+    let mut canister_ids: Vec<Principal> = matches
+        .iter()
+        .filter(|canister_id| start_after.map_or(true, |start_after| **canister_id > start_after))
+        .take(limit + 1)
+        .copied()
+        .collect();
+
+    let next_cursor = if canister_ids.len() > limit {
+        canister_ids.truncate(limit);
+        canister_ids.last().map(Principal::to_text)
+    } else {
+        None
+    };
+
+    let items = REGISTRY.with(|s| {
+        let registry_ref = s.borrow();
+        canister_ids.iter().filter_map(|id| registry_ref.get(id)).collect()
+    });
+
+    Ok(PaginatedResponse {
+        total: matches.len() as u32,
+        items,
+        next_cursor,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize_folds_case_and_skips_short_words() {
+        let mut tokens = BTreeSet::new();
+        tokenize("Strike: a DeFi-Wallet for ICP", &mut tokens);
+        assert_eq!(
+            tokens.into_iter().collect::<Vec<_>>(),
+            vec!["defi", "for", "icp", "strike", "wallet"]
+        );
+    }
+
+    #[test]
+    fn test_url_host() {
+        assert_eq!(url_host("https://app.example.com/path?q=1"), Some("app.example.com"));
+        assert_eq!(url_host("http://user@example.com:8080"), Some("example.com"));
+        assert_eq!(url_host("example.com"), Some("example.com"));
+        assert_eq!(url_host("https://"), None);
+    }
+
+    fn registry(canister_id: Principal, description: &str) -> StrikeRegistry {
+        StrikeRegistry {
+            canister_id,
+            module_hash: None,
+            name: "Ali".to_string(),
+            email: "ali@example.com".to_string(),
+            telegram: None,
+            twitter: None,
+            project_name: "Common".to_string(),
+            description: description.to_string(),
+            website_url: None,
+            created_at: 0,
+            added_by: Principal::anonymous(),
+            status: StrikeStatus::Trusted,
+            status_reason: None,
+            report_count: None,
+        }
+    }
+
+    fn first_page() -> CursorPagination {
+        CursorPagination {
+            start_after: None,
+            limit: 10,
+        }
+    }
+
+    #[test]
+    fn test_broad_terms_are_matched_exactly() {
+        let rare = Principal::from_slice(&[0xff; 8]);
+        let entries = (0..MAX_TERM_MATCHES as u64)
+            .map(|i| registry(Principal::from_slice(&i.to_be_bytes()), "A project"))
+            .chain([registry(rare, "A rare wallet")]);
+        REGISTRY.with(|s| {
+            for entry in entries {
+                reindex(None, Some(&entry));
+                s.borrow_mut().insert(entry.canister_id, entry);
+            }
+        });
+
+        let page = search_registries("common rare".to_string(), SearchFilters::default(), first_page());
+        let page = page.unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(page.items[0].canister_id, rare);
+
+        let page = search_registries("project".to_string(), SearchFilters::default(), first_page());
+        assert_eq!(page.unwrap().total, MAX_TERM_MATCHES as u32);
+
+        let result = search_registries("common".to_string(), SearchFilters::default(), first_page());
+        assert!(matches!(result, Err(StrikeError::InvalidField { .. })));
+    }
+}
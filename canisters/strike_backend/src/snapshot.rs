@@ -0,0 +1,448 @@
+use candid::{CandidType, Principal};
+use ic_stable_structures::Storable;
+use serde::{Deserialize, Serialize};
+use serde_bytes::ByteBuf;
+use sha2::{Digest, Sha256};
+use std::ops::Bound::{Excluded, Unbounded};
+use tracing::info;
+
+use crate::audit::{self, AuditAction, AuditRecord};
+use crate::encoding;
+use crate::error::StrikeError;
+use crate::memory::{IMPORT_CHUNKS, REGISTRY, ROLES, STATE};
+use crate::roles::{self, Role};
+use crate::strike::{self, StrikeRegistry};
+
+/// Changes whenever the records of a chunk change in a way older releases can't import
+pub const SNAPSHOT_FORMAT: u32 = 1;
+
+/// Upper bound of the data of a chunk, well within the limits of a reply and an ingress message
+pub const MAX_CHUNK_BYTES: usize = 1024 * 1024;
+
+// Bytes a record takes in a chunk besides its payload, an upper bound
+const RECORD_OVERHEAD: usize = 48;
+
+#[derive(Serialize, Deserialize, CandidType, Debug, Clone, Copy, PartialEq)]
+pub enum SnapshotSection {
+    Registry,
+    Roles,
+}
+
+/// Where the next chunk of an export starts, with what the manifest needs of the chunks before it
+#[derive(Serialize, Deserialize, CandidType, Debug, Clone, PartialEq)]
+pub struct SnapshotCursor {
+    pub index: u32,
+    pub section: SnapshotSection,
+    pub start_after: Option<Principal>,
+    /// When the first chunk was exported
+    pub created_at: u64,
+    pub registry_count: u64,
+    pub role_count: u64,
+    pub chunk_checksums: Vec<String>,
+}
+
+/// Describes a whole snapshot. Its hash is what an operator keeps to verify the snapshot before
+/// importing it.
+#[derive(Serialize, Deserialize, CandidType, Debug, Clone, PartialEq)]
+pub struct SnapshotManifest {
+    pub format: u32,
+    pub created_at: u64,
+    pub registry_count: u64,
+    pub role_count: u64,
+    /// Hex encoded SHA-256 of the data of each chunk, in order
+    pub chunk_checksums: Vec<String>,
+}
+
+impl SnapshotManifest {
+    /// Hex encoded SHA-256 over every field, in a layout that doesn't depend on an encoding
+    pub fn hash(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(b"strike-snapshot");
+        hasher.update(self.format.to_be_bytes());
+        hasher.update(self.created_at.to_be_bytes());
+        hasher.update(self.registry_count.to_be_bytes());
+        hasher.update(self.role_count.to_be_bytes());
+        hasher.update((self.chunk_checksums.len() as u64).to_be_bytes());
+        for checksum in &self.chunk_checksums {
+            hasher.update(checksum.as_bytes());
+        }
+        hex::encode(hasher.finalize())
+    }
+}
+
+#[derive(Serialize, Deserialize, CandidType, Debug, Clone, PartialEq)]
+pub struct SnapshotChunk {
+    pub index: u32,
+    /// CBOR array of records
+    #[serde(with = "serde_bytes")]
+    pub data: Vec<u8>,
+    /// Hex encoded SHA-256 of `data`
+    pub checksum: String,
+}
+
+#[derive(CandidType, Deserialize, Debug)]
+pub struct SnapshotExport {
+    /// Only in the reply with the last chunk
+    pub manifest: Option<SnapshotManifest>,
+    pub manifest_hash: Option<String>,
+    pub chunk: SnapshotChunk,
+    /// None once this was the last chunk
+    pub next: Option<SnapshotCursor>,
+}
+
+/// An import whose chunks are being written to the registry
+#[derive(Serialize, Deserialize, CandidType, Debug, Clone, PartialEq)]
+pub struct ImportProgress {
+    pub manifest_hash: String,
+    pub committed_chunks: u32,
+    pub total_chunks: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+enum SnapshotRecord {
+    /// An entry in the encoding of the registry, which keeps decoders for every past version
+    Registry(ByteBuf),
+    Role(Principal, u8),
+}
+
+fn checksum(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+fn next_record(section: SnapshotSection, start_after: Option<Principal>) -> Option<(Principal, SnapshotRecord, usize)> {
+    let start = start_after.map_or(Unbounded, Excluded);
+    match section {
+        SnapshotSection::Registry => REGISTRY.with(|s| {
+            s.borrow().range((start, Unbounded)).next().map(|(canister_id, registry)| {
+                let bytes = registry.to_bytes().into_owned();
+                let len = bytes.len();
+                (canister_id, SnapshotRecord::Registry(ByteBuf::from(bytes)), len)
+            })
+        }),
+        SnapshotSection::Roles => ROLES.with(|s| {
+            s.borrow()
+                .range((start, Unbounded))
+                .next()
+                .map(|(principal, role)| (principal, SnapshotRecord::Role(principal, role), principal.as_slice().len()))
+        }),
+    }
+}
+
+/// Builds the chunk at `cursor` and moves the cursor past it, returns whether it was the last one
+fn build_chunk(cursor: &mut SnapshotCursor) -> (SnapshotChunk, bool) {
+    let mut records = Vec::new();
+    let mut size = 0;
+
+    let last = loop {
+        match next_record(cursor.section, cursor.start_after) {
+            Some((key, record, len)) => {
+                // Every chunk has at least one record, whatever its size
+                if !records.is_empty() && size + len + RECORD_OVERHEAD > MAX_CHUNK_BYTES {
+                    break false;
+                }
+                size += len + RECORD_OVERHEAD;
+                match record {
+                    SnapshotRecord::Registry(_) => cursor.registry_count += 1,
+                    SnapshotRecord::Role(..) => cursor.role_count += 1,
+                }
+                records.push(record);
+                cursor.start_after = Some(key);
+            }
+            None if cursor.section == SnapshotSection::Registry => {
+                cursor.section = SnapshotSection::Roles;
+                cursor.start_after = None;
+            }
+            None => break true,
+        }
+    };
+
+    let mut data = Vec::new();
+    ciborium::into_writer(&records, &mut data).expect("failed to encode the snapshot chunk");
+    let chunk = SnapshotChunk {
+        index: cursor.index,
+        checksum: checksum(&data),
+        data,
+    };
+    cursor.index += 1;
+    cursor.chunk_checksums.push(chunk.checksum.clone());
+    (chunk, last)
+}
+
+/// Exports the registry and the roles, a chunk per call. The cursor carries the checksums of the
+/// chunks so far, and the reply with the last chunk returns the manifest of the whole snapshot.
+/// Records are exported in key order as of each call, so entries written during the export may be
+/// missing from it; pause submissions for a snapshot of a single point in time.
+pub fn export_snapshot(cursor: Option<SnapshotCursor>) -> SnapshotExport {
+    let mut cursor = cursor.unwrap_or_else(|| SnapshotCursor {
+        index: 0,
+        section: SnapshotSection::Registry,
+        start_after: None,
+        created_at: ic_cdk::api::time(),
+        registry_count: 0,
+        role_count: 0,
+        chunk_checksums: Vec::new(),
+    });
+
+    let (chunk, last) = build_chunk(&mut cursor);
+    if !last {
+        return SnapshotExport {
+            manifest: None,
+            manifest_hash: None,
+            chunk,
+            next: Some(cursor),
+        };
+    }
+
+    let manifest = SnapshotManifest {
+        format: SNAPSHOT_FORMAT,
+        created_at: cursor.created_at,
+        registry_count: cursor.registry_count,
+        role_count: cursor.role_count,
+        chunk_checksums: cursor.chunk_checksums,
+    };
+    SnapshotExport {
+        manifest_hash: Some(manifest.hash()),
+        manifest: Some(manifest),
+        chunk,
+        next: None,
+    }
+}
+
+fn get_import() -> Option<ImportProgress> {
+    STATE.with(|s| s.borrow().import.clone())
+}
+
+fn decode_records(data: &[u8]) -> Result<Vec<SnapshotRecord>, StrikeError> {
+    ciborium::from_reader(data).map_err(|_| StrikeError::invalid_field("data", "is not a snapshot chunk"))
+}
+
+fn decode_registry(bytes: &[u8]) -> Result<StrikeRegistry, StrikeError> {
+    encoding::try_decode(bytes).ok_or_else(|| StrikeError::invalid_field("data", "has a registry entry that doesn't decode"))
+}
+
+fn decode_role(role: u8) -> Result<Role, StrikeError> {
+    Role::try_from_u8(role).ok_or_else(|| StrikeError::invalid_field("data", format!("has an unknown role {}", role)))
+}
+
+/// Decodes every record of a chunk, so that committing it can't fail halfway through an import
+fn validate_records(data: &[u8]) -> Result<(), StrikeError> {
+    for record in decode_records(data)? {
+        match record {
+            SnapshotRecord::Registry(bytes) => {
+                decode_registry(&bytes)?;
+            }
+            SnapshotRecord::Role(_, role) => {
+                decode_role(role)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Stages a chunk of a snapshot, replacing a chunk staged before with the same index
+pub fn import_snapshot_chunk(chunk: SnapshotChunk) -> Result<(), StrikeError> {
+    if get_import().is_some() {
+        return Err(StrikeError::Unavailable {
+            reason: "An import is being committed".to_string(),
+        });
+    }
+    if checksum(&chunk.data) != chunk.checksum {
+        return Err(StrikeError::invalid_field("checksum", "doesn't match the data"));
+    }
+    validate_records(&chunk.data)?;
+
+    IMPORT_CHUNKS.with(|s| s.borrow_mut().insert(chunk.index, chunk.data));
+    Ok(())
+}
+
+/// Discards the staged chunks of an import that hasn't been finalized
+pub fn cancel_import() -> Result<(), StrikeError> {
+    if get_import().is_some() {
+        return Err(StrikeError::Unavailable {
+            reason: "An import is being committed".to_string(),
+        });
+    }
+
+    IMPORT_CHUNKS.with(|s| s.borrow_mut().clear_new());
+    Ok(())
+}
+
+fn verify_staged(manifest: &SnapshotManifest, manifest_hash: &str) -> Result<(), StrikeError> {
+    if manifest.format != SNAPSHOT_FORMAT {
+        return Err(StrikeError::invalid_field(
+            "manifest",
+            format!("has format {}, expected {}", manifest.format, SNAPSHOT_FORMAT),
+        ));
+    }
+    if manifest.hash() != manifest_hash {
+        return Err(StrikeError::invalid_field("manifest_hash", "doesn't match the manifest"));
+    }
+    if REGISTRY.with(|s| !s.borrow().is_empty()) {
+        return Err(StrikeError::Unavailable {
+            reason: "Snapshots can only be imported into an empty registry".to_string(),
+        });
+    }
+
+    let staged = IMPORT_CHUNKS.with(|s| s.borrow().len());
+    if staged != manifest.chunk_checksums.len() as u64 {
+        return Err(StrikeError::invalid_field(
+            "chunks",
+            format!("{} are staged, the manifest has {}", staged, manifest.chunk_checksums.len()),
+        ));
+    }
+    let mut registry_count = 0;
+    let mut role_count = 0;
+    for (index, expected) in manifest.chunk_checksums.iter().enumerate() {
+        let data = IMPORT_CHUNKS
+            .with(|s| s.borrow().get(&(index as u32)))
+            .filter(|data| checksum(data) == *expected)
+            .ok_or_else(|| {
+                StrikeError::invalid_field("chunks", format!("chunk {} is missing or differs from the manifest", index))
+            })?;
+        for record in decode_records(&data)? {
+            match record {
+                SnapshotRecord::Registry(_) => registry_count += 1,
+                SnapshotRecord::Role(..) => role_count += 1,
+            }
+        }
+    }
+
+    if registry_count != manifest.registry_count || role_count != manifest.role_count {
+        return Err(StrikeError::invalid_field(
+            "chunks",
+            format!(
+                "have {} entries and {} roles, the manifest has {} and {}",
+                registry_count, role_count, manifest.registry_count, manifest.role_count
+            ),
+        ));
+    }
+    Ok(())
+}
+
+fn commit_chunk(index: u32) -> Result<(), StrikeError> {
+    let data = IMPORT_CHUNKS.with(|s| s.borrow().get(&index)).ok_or(StrikeError::NotFound)?;
+
+    for record in decode_records(&data)? {
+        match record {
+            SnapshotRecord::Registry(bytes) => strike::save_registry(decode_registry(&bytes)?),
+            // The roles of the importing canister take precedence, so that its admins keep access
+            SnapshotRecord::Role(principal, role) => {
+                let role = decode_role(role)?;
+                if roles::get_role(principal).is_none() {
+                    roles::set_role_unchecked(principal, role);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Verifies the staged chunks against the manifest on the first call, then writes a chunk to the
+/// registry per call. The import is done once `committed_chunks` reaches `total_chunks`.
+pub fn finalize_import(manifest: SnapshotManifest, manifest_hash: String) -> Result<ImportProgress, StrikeError> {
+    let mut progress = match get_import() {
+        Some(progress) if progress.manifest_hash != manifest_hash => {
+            return Err(StrikeError::invalid_field(
+                "manifest_hash",
+                "differs from the import being committed",
+            ));
+        }
+        Some(progress) => progress,
+        None => {
+            verify_staged(&manifest, &manifest_hash)?;
+            info!("Importing snapshot {}", manifest_hash);
+            ImportProgress {
+                manifest_hash,
+                committed_chunks: 0,
+                total_chunks: manifest.chunk_checksums.len() as u32,
+            }
+        }
+    };
+
+    if progress.committed_chunks < progress.total_chunks {
+        commit_chunk(progress.committed_chunks)?;
+        progress.committed_chunks += 1;
+    }
+
+    if progress.committed_chunks == progress.total_chunks {
+        IMPORT_CHUNKS.with(|s| s.borrow_mut().clear_new());
+        STATE.with(|s| s.borrow_mut().import = None);
+        info!("Imported snapshot {}", progress.manifest_hash);
+
+        audit::record(
+            ic_cdk::api::caller(),
+            AuditRecord::config(AuditAction::ImportSnapshot).reason(Some(progress.manifest_hash.clone())),
+        );
+    } else {
+        STATE.with(|s| s.borrow_mut().import = Some(progress.clone()));
+    }
+
+    Ok(progress)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest() -> SnapshotManifest {
+        SnapshotManifest {
+            format: SNAPSHOT_FORMAT,
+            created_at: 42,
+            registry_count: 2,
+            role_count: 1,
+            chunk_checksums: vec![checksum(b"first"), checksum(b"second")],
+        }
+    }
+
+    #[test]
+    fn test_manifest_hash_covers_every_field() {
+        let hash = manifest().hash();
+        assert_eq!(hash, manifest().hash());
+
+        let mut changed = manifest();
+        changed.registry_count = 3;
+        assert_ne!(changed.hash(), hash);
+
+        let mut changed = manifest();
+        changed.chunk_checksums.swap(0, 1);
+        assert_ne!(changed.hash(), hash);
+    }
+
+    #[test]
+    fn test_records_roundtrip() {
+        let records = vec![
+            SnapshotRecord::Registry(ByteBuf::from(b"SREG\x02".to_vec())),
+            SnapshotRecord::Role(Principal::from_slice(&[1; 29]), Role::Admin as u8),
+        ];
+        let mut data = Vec::new();
+        ciborium::into_writer(&records, &mut data).unwrap();
+
+        let decoded = decode_records(&data).unwrap();
+        assert!(matches!(&decoded[0], SnapshotRecord::Registry(bytes) if bytes.as_slice() == b"SREG\x02"));
+        assert!(matches!(decoded[1], SnapshotRecord::Role(_, 1)));
+        assert!(decode_records(b"not cbor").is_err());
+    }
+
+    fn encode_records(records: &[SnapshotRecord]) -> Vec<u8> {
+        let mut data = Vec::new();
+        ciborium::into_writer(records, &mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn test_rejects_records_that_dont_decode() {
+        let principal = Principal::from_slice(&[1; 29]);
+
+        let data = encode_records(&[SnapshotRecord::Role(principal, Role::Reviewer as u8)]);
+        assert!(validate_records(&data).is_ok());
+
+        // A record in a version this release doesn't know, and one that isn't an entry at all
+        for bytes in [b"SREG\x09".to_vec(), b"SREG\x02garbage".to_vec()] {
+            let data = encode_records(&[SnapshotRecord::Registry(ByteBuf::from(bytes))]);
+            assert!(matches!(validate_records(&data), Err(StrikeError::InvalidField { field, .. }) if field == "data"));
+        }
+
+        let data = encode_records(&[SnapshotRecord::Role(principal, 42)]);
+        assert!(matches!(validate_records(&data), Err(StrikeError::InvalidField { field, .. }) if field == "data"));
+    }
+}
@@ -0,0 +1,306 @@
+use candid::{CandidType, Principal};
+use ic_stable_structures::{storable::Bound, Storable};
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use tracing::info;
+
+use crate::audit::{AuditAction, AuditValue};
+use crate::index;
+use crate::memory::{AUDIT_LOG, DAILY_COUNTS, REVIEW_TIMES};
+use crate::strike::StrikeStatus;
+
+/// Days of activity returned by `get_stats`, older days are dropped from the counters
+pub const STATS_WINDOW_DAYS: u64 = 30;
+
+const NANOS_PER_SEC: u64 = 1_000_000_000;
+const SECS_PER_HOUR: u64 = 60 * 60;
+const NANOS_PER_DAY: u64 = 24 * SECS_PER_HOUR * NANOS_PER_SEC;
+
+/// Review times are counted in buckets of an hour, longer ones all go to the last bucket
+const REVIEW_TIME_BUCKETS: u32 = 90 * 24;
+
+/// Moderation activity of a single day, keyed by the days since the epoch in `DAILY_COUNTS`
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct DailyCounts {
+    pub submissions: u64,
+    pub approvals: u64,
+    pub blocks: u64,
+}
+
+impl Storable for DailyCounts {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = Vec::with_capacity(3 * 8);
+        bytes.extend_from_slice(&self.submissions.to_be_bytes());
+        bytes.extend_from_slice(&self.approvals.to_be_bytes());
+        bytes.extend_from_slice(&self.blocks.to_be_bytes());
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let read_u64 = |offset: usize| u64::from_be_bytes(bytes[offset..offset + 8].try_into().unwrap());
+        Self {
+            submissions: read_u64(0),
+            approvals: read_u64(8),
+            blocks: read_u64(16),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 3 * 8,
+        is_fixed_size: true,
+    };
+}
+
+#[derive(CandidType, Deserialize, Debug, PartialEq, Clone)]
+pub struct StatusCount {
+    pub status: StrikeStatus,
+    pub count: u64,
+}
+
+#[derive(CandidType, Deserialize, Debug, PartialEq, Clone)]
+pub struct DailyStats {
+    /// Midnight UTC of the day, in nanoseconds since the epoch
+    pub day_start: u64,
+    /// New entries, resubmissions of entries under review aren't counted
+    pub submissions: u64,
+    /// Entries that became trusted, including accepted appeals
+    pub approvals: u64,
+    pub blocks: u64,
+}
+
+#[derive(CandidType, Deserialize, Debug, PartialEq, Clone)]
+pub struct RegistryStats {
+    pub status_counts: Vec<StatusCount>,
+    /// The last `STATS_WINDOW_DAYS` days up to today, oldest first. Days without activity are included.
+    pub daily: Vec<DailyStats>,
+    /// Entries decided after a review, over the whole history of the registry
+    pub reviewed: u64,
+    /// From the submission to the decision, rounded down to the hour, so 3h59m and 3h are both
+    /// 3h. Entries moved back to review count from their original submission.
+    pub median_review_time_secs: Option<u64>,
+}
+
+fn day(timestamp: u64) -> u64 {
+    timestamp / NANOS_PER_DAY
+}
+
+fn update_day(timestamp: u64, update: impl FnOnce(&mut DailyCounts)) {
+    let today = day(timestamp);
+    DAILY_COUNTS.with(|s| {
+        let mut counts = s.borrow_mut();
+
+        let mut day_counts = counts.get(&today).unwrap_or_default();
+        update(&mut day_counts);
+        counts.insert(today, day_counts);
+
+        // The window only moves forward, so the expired days are always the first ones
+        let cutoff = today.saturating_sub(STATS_WINDOW_DAYS - 1);
+        while let Some((expired, _)) = counts.first_key_value().filter(|(day, _)| *day < cutoff) {
+            counts.remove(&expired);
+        }
+    });
+}
+
+fn review_time_bucket(review_time: u64) -> u32 {
+    ((review_time / NANOS_PER_SEC / SECS_PER_HOUR) as u32).min(REVIEW_TIME_BUCKETS - 1)
+}
+
+/// Counts a new entry submitted at `timestamp`
+pub fn record_submission(timestamp: u64) {
+    update_day(timestamp, |counts| counts.submissions += 1);
+}
+
+/// Counts a status change at `timestamp` of an entry submitted at `submitted_at`
+pub fn record_status_change(old_status: StrikeStatus, new_status: StrikeStatus, submitted_at: u64, timestamp: u64) {
+    if old_status == new_status {
+        return;
+    }
+
+    match new_status {
+        StrikeStatus::Trusted => update_day(timestamp, |counts| counts.approvals += 1),
+        StrikeStatus::Blocked => update_day(timestamp, |counts| counts.blocks += 1),
+        StrikeStatus::Submitted => {}
+    }
+
+    if old_status == StrikeStatus::Submitted {
+        let bucket = review_time_bucket(timestamp.saturating_sub(submitted_at));
+        REVIEW_TIMES.with(|s| {
+            let mut review_times = s.borrow_mut();
+            let count = review_times.get(&bucket).unwrap_or_default();
+            review_times.insert(bucket, count + 1);
+        });
+    }
+}
+
+/// The bucket that holds the middle of the `total` counted in `buckets`, which are ordered by bucket
+fn median_bucket(total: u64, buckets: impl Iterator<Item = (u32, u64)>) -> Option<u32> {
+    let mut seen = 0;
+    for (bucket, count) in buckets {
+        seen += count;
+        if seen * 2 >= total {
+            return Some(bucket);
+        }
+    }
+    None
+}
+
+pub fn get_stats(now: u64) -> RegistryStats {
+    let status_counts = [StrikeStatus::Submitted, StrikeStatus::Trusted, StrikeStatus::Blocked]
+        .into_iter()
+        .map(|status| StatusCount {
+            status,
+            count: index::status_count(status),
+        })
+        .collect();
+
+    let today = day(now);
+    let daily = DAILY_COUNTS.with(|s| {
+        let counts = s.borrow();
+        (today.saturating_sub(STATS_WINDOW_DAYS - 1)..=today)
+            .map(|day| {
+                let day_counts = counts.get(&day).unwrap_or_default();
+                DailyStats {
+                    day_start: day * NANOS_PER_DAY,
+                    submissions: day_counts.submissions,
+                    approvals: day_counts.approvals,
+                    blocks: day_counts.blocks,
+                }
+            })
+            .collect()
+    });
+
+    let (reviewed, median_bucket) = REVIEW_TIMES.with(|s| {
+        let review_times = s.borrow();
+        let reviewed = review_times.iter().map(|(_, count)| count).sum();
+        (reviewed, median_bucket(reviewed, review_times.iter()))
+    });
+
+    RegistryStats {
+        status_counts,
+        daily,
+        reviewed,
+        median_review_time_secs: median_bucket.map(|bucket| bucket as u64 * SECS_PER_HOUR),
+    }
+}
+
+/// Rebuilds the counters from the audit log, for registries that were moderated before they were
+/// maintained. The counters are cleared first, so this can run again.
+pub fn backfill() {
+    DAILY_COUNTS.with(|s| s.borrow_mut().clear_new());
+    REVIEW_TIMES.with(|s| s.borrow_mut().clear_new());
+
+    // The latest submission of each entry, as `created_at` is reset on resubmission
+    let mut submitted_at: HashMap<Principal, u64> = HashMap::new();
+    let entries = AUDIT_LOG.with(|log| log.borrow().len());
+
+    AUDIT_LOG.with(|log| {
+        for entry in log.borrow().iter() {
+            let Some(canister_id) = entry.canister_id else {
+                continue;
+            };
+
+            match (entry.action, entry.old_value, entry.new_value) {
+                (AuditAction::AddRegistry, old_value, _) => {
+                    if old_value.is_none() {
+                        record_submission(entry.timestamp);
+                    }
+                    submitted_at.insert(canister_id, entry.timestamp);
+                }
+                (
+                    AuditAction::UpdateRegistryStatus,
+                    Some(AuditValue::Status(old_status)),
+                    Some(AuditValue::Status(new_status)),
+                ) => {
+                    // Entries restored from a snapshot have no submission in the log
+                    let submitted_at = submitted_at.get(&canister_id).copied().unwrap_or(entry.timestamp);
+                    record_status_change(old_status, new_status, submitted_at, entry.timestamp);
+                }
+                _ => {}
+            }
+        }
+    });
+
+    info!("Counted the moderation history of {} audit log entries", entries);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NANOS_PER_HOUR: u64 = SECS_PER_HOUR * NANOS_PER_SEC;
+
+    #[test]
+    fn test_daily_counts_roundtrip() {
+        let counts = DailyCounts {
+            submissions: 3,
+            approvals: 2,
+            blocks: 1,
+        };
+        assert_eq!(DailyCounts::from_bytes(counts.to_bytes()), counts);
+    }
+
+    #[test]
+    fn test_median_bucket() {
+        assert_eq!(median_bucket(0, [].into_iter()), None);
+        assert_eq!(median_bucket(1, [(4, 1)].into_iter()), Some(4));
+        assert_eq!(median_bucket(3, [(0, 1), (2, 1), (7, 1)].into_iter()), Some(2));
+        assert_eq!(median_bucket(4, [(0, 1), (5, 3)].into_iter()), Some(5));
+    }
+
+    #[test]
+    fn test_review_time_bucket() {
+        assert_eq!(review_time_bucket(0), 0);
+        assert_eq!(review_time_bucket(NANOS_PER_HOUR - 1), 0);
+        assert_eq!(review_time_bucket(5 * NANOS_PER_HOUR), 5);
+        assert_eq!(review_time_bucket(u64::MAX), REVIEW_TIME_BUCKETS - 1);
+    }
+
+    #[test]
+    fn test_counts_moderation_over_the_window() {
+        let day_one = 100 * NANOS_PER_DAY;
+
+        record_submission(day_one);
+        record_submission(day_one + NANOS_PER_HOUR);
+        record_status_change(
+            StrikeStatus::Submitted,
+            StrikeStatus::Trusted,
+            day_one,
+            day_one + 3 * NANOS_PER_HOUR,
+        );
+        record_status_change(
+            StrikeStatus::Submitted,
+            StrikeStatus::Blocked,
+            day_one + NANOS_PER_HOUR,
+            day_one + NANOS_PER_DAY,
+        );
+        // Moving back to review is neither an approval nor a block, and no review took place
+        record_status_change(
+            StrikeStatus::Trusted,
+            StrikeStatus::Submitted,
+            day_one,
+            day_one + NANOS_PER_DAY,
+        );
+
+        let stats = get_stats(day_one + NANOS_PER_DAY);
+        assert_eq!(stats.daily.len() as u64, STATS_WINDOW_DAYS);
+        let last_two: Vec<(u64, u64, u64)> = stats.daily[stats.daily.len() - 2..]
+            .iter()
+            .map(|day| (day.submissions, day.approvals, day.blocks))
+            .collect();
+        assert_eq!(last_two, vec![(2, 1, 0), (0, 0, 1)]);
+        assert_eq!(stats.daily.last().unwrap().day_start, day_one + NANOS_PER_DAY);
+        assert_eq!(stats.reviewed, 2);
+        // Exactly three hours and 23 hours, the median is the first
+        assert_eq!(stats.median_review_time_secs, Some(3 * SECS_PER_HOUR));
+
+        // Days that left the window are dropped once a later day is counted
+        record_submission(day_one + STATS_WINDOW_DAYS * NANOS_PER_DAY);
+        assert_eq!(
+            DAILY_COUNTS.with(|s| s.borrow().first_key_value()).unwrap().0,
+            day(day_one) + 1
+        );
+        let stats = get_stats(day_one + STATS_WINDOW_DAYS * NANOS_PER_DAY);
+        assert_eq!(stats.daily.iter().map(|day| day.submissions).sum::<u64>(), 1);
+    }
+}
@@ -1,13 +1,26 @@
-use candid::{CandidType, Decode, Encode, Principal};
+use candid::{CandidType, Principal};
 use ic_cdk::caller;
-use ic_stable_structures::{storable::Bound, Storable};
 use serde::{Deserialize, Serialize};
-use std::borrow::Cow;
 
-use crate::memory::REGISTRY;
-use crate::types::{Paginate, PaginatedResponse, Pagination};
+use crate::appeals;
+use crate::approvals;
+use crate::audit::{self, AuditAction, AuditRecord, AuditValue};
+use crate::certification;
+use crate::deposits;
+use crate::error::StrikeError;
+use crate::features::{self, Feature};
+use crate::index::{self, CreatedIndexKey, NameIndexKey, OwnerIndexKey, StatusIndexKey, StatusNameIndexKey};
+use crate::limits::{self, MAX_REASON_LEN};
+use crate::management;
+use crate::memory::{CREATED_INDEX, NAME_INDEX, OWNER_INDEX, REGISTRY, STATUS_INDEX, STATUS_NAME_INDEX};
+use crate::reports;
+use crate::review;
+use crate::revisions::{self, RegistryDetails, Revision};
+use crate::search;
+use crate::stats;
+use crate::types::{CursorPagination, PaginatedResponse, SortOrder};
 
-#[derive(Serialize, Deserialize, CandidType, Debug, PartialEq, Clone, Copy)]
+#[derive(Serialize, Deserialize, CandidType, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 #[repr(u8)]
 pub enum StrikeStatus {
     Submitted,
@@ -15,6 +28,21 @@ pub enum StrikeStatus {
     Blocked,
 }
 
+impl StrikeStatus {
+    pub fn try_from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(StrikeStatus::Submitted),
+            1 => Some(StrikeStatus::Trusted),
+            2 => Some(StrikeStatus::Blocked),
+            _ => None,
+        }
+    }
+
+    pub fn from_u8(value: u8) -> Self {
+        Self::try_from_u8(value).unwrap_or_else(|| panic!("invalid strike status {}", value))
+    }
+}
+
 /// Represents a registry entry for a canister in the Strike system
 #[derive(Serialize, Deserialize, CandidType, Debug)]
 pub struct StrikeRegistry {
@@ -30,6 +58,40 @@ pub struct StrikeRegistry {
     pub created_at: u64,
     pub added_by: Principal,
     pub status: StrikeStatus,
+    /// Why the entry is in its current status, e.g. when it was demoted automatically
+    pub status_reason: Option<String>,
+    /// Abuse reports filed by users against the canister, none for entries saved before reports
+    pub report_count: Option<u32>,
+}
+
+/// The public status of a canister, for callers that check many at once
+#[derive(CandidType, Deserialize, Debug, PartialEq, Clone)]
+pub struct StrikeSummary {
+    pub canister_id: Principal,
+    /// None when the canister isn't registered
+    pub status: Option<StrikeStatus>,
+    /// The module hash reviewed when the canister was trusted
+    pub module_hash: Option<String>,
+}
+
+/// A note a reviewer left on an entry while voting, shown to its submitter without the reviewer
+#[derive(CandidType, Deserialize, Debug, Clone)]
+pub struct ReviewNote {
+    /// The status the reviewer voted for
+    pub status: StrikeStatus,
+    pub reason: String,
+    pub timestamp: u64,
+}
+
+/// An entry as its submitter sees it on the manage page
+#[derive(CandidType, Deserialize, Debug)]
+pub struct MyRegistration {
+    pub registry: StrikeRegistry,
+    /// The notes of the votes cast since the entry was last decided, oldest first. The reason of
+    /// the last decision is the `status_reason` of the entry.
+    pub review_notes: Vec<ReviewNote>,
+    /// An edit of the entry that is waiting for a reviewer
+    pub pending_revision: Option<Revision>,
 }
 
 #[derive(CandidType, Deserialize)]
@@ -42,129 +104,427 @@ pub struct AddRegistryParams {
     pub project_name: String,
     pub description: String,
     pub website_url: Option<String>,
+    /// A controller that approved the caller with `approve_submitter`, for callers that don't
+    /// control the canister themselves
+    pub controller: Option<Principal>,
 }
 
 #[derive(CandidType, Deserialize)]
 pub struct UpdateRegistryStatusParams {
     pub canister_id: Principal,
     pub status: StrikeStatus,
+    pub reason: Option<String>,
 }
 
 #[derive(CandidType, Deserialize)]
 pub struct GetRegistriesParams {
     pub status: Option<StrikeStatus>,
-    pub pagination: Pagination,
+    pub pagination: CursorPagination,
+    /// Defaults to `CreatedAtAsc`
+    pub order: Option<SortOrder>,
 }
 
-impl Storable for StrikeRegistry {
-    fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
-        Cow::Owned(Encode!(self).unwrap())
-    }
+pub fn get_strike_by_canister_id(principal: Principal) -> Option<StrikeRegistry> {
+    REGISTRY.with(|s| s.borrow().get(&principal))
+}
 
-    fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
-        Decode!(bytes.as_ref(), Self).unwrap()
+/// Summarizes each of `canister_ids`, in the same order
+pub fn get_strikes_by_canister_ids(canister_ids: Vec<Principal>) -> Result<Vec<StrikeSummary>, StrikeError> {
+    let max_batch_lookup = limits::get_max_batch_lookup();
+    if canister_ids.len() > max_batch_lookup as usize {
+        return Err(StrikeError::invalid_field(
+            "canister_ids",
+            format!("cannot exceed {} canisters", max_batch_lookup),
+        ));
     }
 
-    const BOUND: Bound = Bound::Unbounded;
+    Ok(REGISTRY.with(|s| {
+        let registry = s.borrow();
+        canister_ids
+            .into_iter()
+            .map(|canister_id| {
+                let entry = registry.get(&canister_id);
+                StrikeSummary {
+                    canister_id,
+                    status: entry.as_ref().map(|entry| entry.status),
+                    module_hash: entry.and_then(|entry| entry.module_hash),
+                }
+            })
+            .collect()
+    }))
 }
 
-pub fn get_strike_by_canister_id(principal: Principal) -> Option<StrikeRegistry> {
-    REGISTRY.with(|s| s.borrow().get(&principal))
+/// Writes an entry to the registry. Every write but `save_report_count` has to go through here to
+/// keep the indexes and the search index consistent.
+pub fn save_registry(registry: StrikeRegistry) {
+    REGISTRY.with(|s| {
+        let mut registry_ref = s.borrow_mut();
+        let old = registry_ref.get(&registry.canister_id);
+        index::reindex(old.as_ref(), Some(&registry));
+        search::reindex(old.as_ref(), Some(&registry));
+        certification::recertify(old.as_ref(), Some(&registry));
+        registry_ref.insert(registry.canister_id, registry);
+    });
 }
 
-pub fn add_registry(
-    canister_id: Principal,
-    name: String,
-    email: String,
-    telegram: Option<String>,
-    twitter: Option<String>,
-    project_name: String,
-    description: String,
-    website_url: Option<String>,
-) -> Result<(), String> {
-    if name.trim().is_empty() {
-        return Err("Name cannot be empty".to_string());
-    }
+/// Updates the report count of an entry, if the canister is registered. The count isn't indexed,
+/// searched or certified, so reports don't pay for what `save_registry` maintains.
+pub fn save_report_count(canister_id: Principal, report_count: u32) {
+    REGISTRY.with(|s| {
+        let mut registry_ref = s.borrow_mut();
+        if let Some(mut registry) = registry_ref.get(&canister_id) {
+            registry.report_count = Some(report_count);
+            registry_ref.insert(canister_id, registry);
+        }
+    });
+}
 
-    if email.trim().is_empty() {
-        return Err("Email cannot be empty".to_string());
-    }
+/// Removes an entry from the registry along with everything derived from it. Like `save_registry`,
+/// every removal has to go through here.
+pub fn remove_registry(canister_id: Principal) -> Option<StrikeRegistry> {
+    let old = REGISTRY.with(|s| s.borrow_mut().remove(&canister_id))?;
+    index::reindex(Some(&old), None);
+    search::reindex(Some(&old), None);
+    certification::recertify(Some(&old), None);
+    review::clear_review(canister_id);
+    revisions::clear_revision(canister_id);
+    Some(old)
+}
+
+pub async fn add_registry(params: AddRegistryParams) -> Result<(), StrikeError> {
+    features::ensure_enabled(Feature::Submissions)?;
 
-    if project_name.trim().is_empty() {
-        return Err("Project name cannot be empty".to_string());
+    let caller = caller();
+    let canister_id = params.canister_id;
+    let controller = params.controller.unwrap_or(caller);
+    if controller != caller {
+        approvals::ensure_approved(canister_id, controller, caller, ic_cdk::api::time())?;
     }
 
-    if description.trim().is_empty() {
-        return Err("Description cannot be empty".to_string());
+    let details = RegistryDetails {
+        name: Some(params.name),
+        email: Some(params.email),
+        telegram: params.telegram,
+        twitter: params.twitter,
+        project_name: Some(params.project_name),
+        description: Some(params.description),
+        website_url: params.website_url,
     }
+    .validate()?;
 
-    let caller = caller();
+    ensure_submittable(canister_id)?;
 
-    let registry = StrikeRegistry {
-        canister_id: canister_id.clone(),
-        module_hash: None, // This can be set later if needed
-        name,
-        email,
-        telegram,
-        twitter,
-        project_name,
-        description,
-        website_url,
-        created_at: ic_cdk::api::time(),
-        added_by: caller,
-        status: StrikeStatus::Submitted,
-    };
+    // Counted before the call to the management canister, which is where a submission costs cycles
+    limits::consume_submission_quota(caller, ic_cdk::api::time())?;
 
-    // TODO: Validate canister ownership by caller
+    // The caller has to control the canister, or be approved by a principal that still does
+    management::ensure_controller(canister_id, controller).await?;
 
-    // Find exist one
-    if let Some(exist_registry) = REGISTRY.with(|s| s.borrow().get(&canister_id)) {
-        if exist_registry.status != StrikeStatus::Submitted {
-            return Err("Canister already trusted or blocked".to_string());
+    // Only submissions that passed the controller check count against the global cap, otherwise
+    // principals that control nothing could use it up
+    limits::consume_global_submission_quota(caller, ic_cdk::api::time())?;
+
+    let collected = deposits::collect(canister_id, caller).await?;
+
+    // The registry may have changed while we were waiting for the management canister and the ledger
+    let old_status = match ensure_submittable(canister_id) {
+        Ok(old_status) => old_status,
+        Err(err) => {
+            if collected {
+                deposits::spawn_refund(canister_id);
+            }
+            return Err(err);
         }
     };
 
-    REGISTRY.with(|s| s.borrow_mut().insert(canister_id.clone(), registry));
+    let now = ic_cdk::api::time();
+    let mut registry = StrikeRegistry {
+        canister_id,
+        module_hash: None, // Pinned once the canister gets trusted
+        name: String::new(),
+        email: String::new(),
+        telegram: None,
+        twitter: None,
+        project_name: String::new(),
+        description: String::new(),
+        website_url: None,
+        created_at: now,
+        added_by: caller,
+        status: StrikeStatus::Submitted,
+        status_reason: None,
+        report_count: Some(reports::report_count(canister_id)),
+    };
+    details.apply_to(&mut registry);
+
+    save_registry(registry);
+    // Votes were cast on the previous details
+    review::clear_review(canister_id);
+    if controller != caller {
+        approvals::consume(canister_id);
+    }
+    if old_status.is_none() {
+        stats::record_submission(now);
+    }
+
+    audit::record(
+        caller,
+        AuditRecord::registry(canister_id, AuditAction::AddRegistry).values(
+            old_status.map(AuditValue::Status),
+            Some(AuditValue::Status(StrikeStatus::Submitted)),
+        ),
+    );
 
     Ok(())
 }
 
-pub fn update_registry_status(canister_id: Principal, status: StrikeStatus) -> Result<(), String> {
-    let mut registry = REGISTRY
-        .with(|s| s.borrow_mut().get(&canister_id))
-        .ok_or("Canister not found")?;
+/// Only new canisters or ones still awaiting review can be (re)submitted, returns the current status if any
+fn ensure_submittable(canister_id: Principal) -> Result<Option<StrikeStatus>, StrikeError> {
+    match REGISTRY.with(|s| s.borrow().get(&canister_id)) {
+        Some(exist_registry) if exist_registry.status != StrikeStatus::Submitted => Err(StrikeError::AlreadyFinalized),
+        exist_registry => Ok(exist_registry.map(|registry| registry.status)),
+    }
+}
+
+/// Trust is granted to the code that was reviewed, so entries moving to `Trusted` pin the hash of
+/// their current module
+pub async fn module_hash_to_pin(canister_id: Principal, status: StrikeStatus) -> Result<Option<String>, StrikeError> {
+    match status {
+        StrikeStatus::Trusted => Ok(Some(fetch_module_hash(canister_id).await?)),
+        _ => Ok(None),
+    }
+}
 
+/// Moves an entry to `status` on behalf of the caller, pinning `module_hash` if given. It doesn't
+/// await, so callers can decide on the status and apply it without other calls interleaving.
+pub fn set_registry_status(
+    canister_id: Principal,
+    status: StrikeStatus,
+    reason: Option<String>,
+    module_hash: Option<String>,
+) -> Result<(), StrikeError> {
+    change_registry_status(caller(), canister_id, status, reason, module_hash)
+}
+
+/// Moves an entry to `status` on behalf of `actor`, e.g. the canister itself for changes it makes
+/// on its own. Every status change has to go through here, it drops what was pending on the old
+/// status and settles the deposit.
+pub fn change_registry_status(
+    actor: Principal,
+    canister_id: Principal,
+    status: StrikeStatus,
+    reason: Option<String>,
+    module_hash: Option<String>,
+) -> Result<(), StrikeError> {
+    let mut registry = REGISTRY.with(|s| s.borrow().get(&canister_id)).ok_or(StrikeError::NotFound)?;
+
+    let old_status = registry.status;
     registry.status = status;
+    registry.status_reason = reason.clone();
+    if module_hash.is_some() {
+        registry.module_hash = module_hash;
+    }
+
+    stats::record_status_change(old_status, status, registry.created_at, ic_cdk::api::time());
+    save_registry(registry);
+    // Votes were cast on the old status
+    review::clear_review(canister_id);
+    // Only trusted entries hold edits for review, the others are edited in place or not at all
+    if status != StrikeStatus::Trusted {
+        revisions::clear_revision(canister_id);
+    }
 
-    REGISTRY.with(|s| s.borrow_mut().insert(canister_id, registry));
+    // An appeal against a block that was lifted some other way has nothing left to decide
+    if old_status == StrikeStatus::Blocked && status != StrikeStatus::Blocked {
+        appeals::close_open_appeal(actor, canister_id);
+    }
+
+    match status {
+        StrikeStatus::Trusted if deposits::refunds_trusted() => deposits::spawn_refund(canister_id),
+        StrikeStatus::Blocked => {
+            deposits::forfeit(canister_id);
+        }
+        _ => {}
+    }
+
+    audit::record(
+        actor,
+        AuditRecord::registry(canister_id, AuditAction::UpdateRegistryStatus)
+            .values(Some(AuditValue::Status(old_status)), Some(AuditValue::Status(status)))
+            .reason(reason),
+    );
 
     Ok(())
 }
 
-pub fn get_registries(params: GetRegistriesParams) -> PaginatedResponse<StrikeRegistry> {
-    let mut result: Vec<StrikeRegistry> = Vec::new();
+/// Returns the hex encoded hash of the module currently installed on the canister
+pub async fn fetch_module_hash(canister_id: Principal) -> Result<String, StrikeError> {
+    management::get_canister_info(canister_id)
+        .await?
+        .module_hash
+        .map(hex::encode)
+        .ok_or_else(|| StrikeError::invalid_field("canister_id", "has no module installed"))
+}
 
-    REGISTRY.with(|s| {
-        let registry_ref = s.borrow();
-        for (id, _) in registry_ref.iter() {
-            // Get each registry with safe handling
-            if let Some(registry) = registry_ref.get(&id) {
-                if let Some(filter_status) = params.status {
-                    if registry.status == filter_status {
-                        result.push(registry);
-                    }
-                } else {
-                    result.push(registry);
-                }
-            }
+pub fn get_registries(params: GetRegistriesParams) -> Result<PaginatedResponse<StrikeRegistry>, StrikeError> {
+    let start_after = params.pagination.start_after.as_deref();
+    let limit = params.pagination.limit();
+    let order = params.order.unwrap_or(SortOrder::CreatedAtAsc);
+    let descending = order == SortOrder::CreatedAtDesc;
+
+    // Only the keys of the requested page are resolved to full entries
+    let page = match (params.status, order) {
+        (Some(status), SortOrder::ProjectNameAsc) => STATUS_NAME_INDEX.with(|s| {
+            index::scan(
+                &s.borrow(),
+                StatusNameIndexKey::first(status),
+                start_after,
+                limit,
+                false,
+                |key| key.status == status,
+            )
+        }),
+        (None, SortOrder::ProjectNameAsc) => {
+            NAME_INDEX.with(|s| index::scan(&s.borrow(), NameIndexKey::first(), start_after, limit, false, |_| true))
         }
+        (Some(status), _) => STATUS_INDEX.with(|s| {
+            let start = if descending { StatusIndexKey::last(status) } else { StatusIndexKey::first(status) };
+            index::scan(&s.borrow(), start, start_after, limit, descending, |key| key.status == status)
+        }),
+        (None, _) => CREATED_INDEX.with(|s| {
+            let start = if descending { CreatedIndexKey::last() } else { CreatedIndexKey::first() };
+            index::scan(&s.borrow(), start, start_after, limit, descending, |_| true)
+        }),
+    }?;
+
+    let total = match params.status {
+        Some(status) => index::status_count(status),
+        None => REGISTRY.with(|s| s.borrow().len()),
+    };
+
+    let items = REGISTRY.with(|s| {
+        let registry_ref = s.borrow();
+        page.canister_ids.iter().filter_map(|id| registry_ref.get(id)).collect()
     });
 
-    let total = result.len() as u32;
-    let items = result
-        .into_iter()
-        .paginate(params.pagination)
-        .map(|registry| registry.into())
+    Ok(PaginatedResponse {
+        total: total as u32,
+        items,
+        next_cursor: page.next_cursor,
+    })
+}
+
+/// The entries the caller submitted, newest first
+pub fn get_my_registrations(pagination: CursorPagination) -> Result<PaginatedResponse<MyRegistration>, StrikeError> {
+    let caller = caller();
+
+    let page = OWNER_INDEX.with(|s| {
+        index::scan(
+            &s.borrow(),
+            OwnerIndexKey::last(caller),
+            pagination.start_after.as_deref(),
+            pagination.limit(),
+            true,
+            |key| key.added_by == caller,
+        )
+    })?;
+
+    let items = page
+        .canister_ids
+        .iter()
+        .filter_map(|canister_id| get_strike_by_canister_id(*canister_id))
+        .map(|registry| {
+            let review_notes = review::get_review(registry.canister_id)
+                .map(|review| {
+                    review
+                        .votes
+                        .into_iter()
+                        .filter_map(|vote| {
+                            Some(ReviewNote {
+                                status: vote.status,
+                                reason: vote.reason?,
+                                timestamp: vote.timestamp,
+                            })
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            MyRegistration {
+                pending_revision: revisions::get_revision(registry.canister_id),
+                review_notes,
+                registry,
+            }
+        })
         .collect();
-    PaginatedResponse { total, items }
+
+    Ok(PaginatedResponse {
+        total: index::owner_count(caller) as u32,
+        items,
+        next_cursor: page.next_cursor,
+    })
+}
+
+/// Removes an entry on behalf of its submitter, e.g. when the project shut down. Blocked entries
+/// stay, otherwise withdrawing would lift the block.
+pub fn withdraw_registry(canister_id: Principal, reason: Option<String>) -> Result<(), StrikeError> {
+    let caller = caller();
+
+    limits::check_lengths(&[("reason", reason.as_deref(), MAX_REASON_LEN)])?;
+
+    let registry = get_strike_by_canister_id(canister_id).ok_or(StrikeError::NotFound)?;
+    if registry.added_by != caller {
+        return Err(StrikeError::Unauthorized);
+    }
+
+    if registry.status == StrikeStatus::Blocked {
+        return Err(StrikeError::InvalidStatus {
+            status: StrikeStatus::Blocked,
+        });
+    }
+
+    remove_registry(canister_id);
+
+    if deposits::refunds_withdrawals() {
+        deposits::spawn_refund(canister_id);
+    } else {
+        deposits::forfeit(canister_id);
+    }
+
+    // The tombstone keeps the history of the canister visible if it's ever submitted again
+    audit::record(
+        caller,
+        AuditRecord::registry(canister_id, AuditAction::WithdrawRegistry)
+            .values(Some(AuditValue::Tombstone((&registry).into())), None)
+            .reason(reason),
+    );
+
+    Ok(())
+}
+
+/// Removes an entry whatever its status, along with its appeals and the reports against it. A
+/// held deposit is forfeited, so that a later submission of the canister has to pay its own; an
+/// admin can still refund it with `refund_deposit`.
+pub fn purge_registry(canister_id: Principal, reason: String) -> Result<(), StrikeError> {
+    if reason.trim().is_empty() {
+        return Err(StrikeError::empty("reason"));
+    }
+
+    limits::check_lengths(&[("reason", Some(&reason), MAX_REASON_LEN)])?;
+
+    let registry = remove_registry(canister_id).ok_or(StrikeError::NotFound)?;
+    appeals::clear_appeals(canister_id);
+    reports::clear_reports(canister_id);
+
+    let caller = caller();
+    audit::record(
+        caller,
+        AuditRecord::registry(canister_id, AuditAction::PurgeRegistry)
+            .values(Some(AuditValue::Tombstone((&registry).into())), None)
+            .reason(Some(reason)),
+    );
+    if deposits::forfeit(canister_id) {
+        audit::record(caller, AuditRecord::registry(canister_id, AuditAction::ForfeitDeposit));
+    }
+
+    Ok(())
 }
@@ -1,15 +1,43 @@
-use candid::Principal;
-use pocket_ic::{query_candid, update_candid_as, PocketIc};
+use candid::{CandidType, Nat, Principal};
+use icrc_ledger_types::icrc::generic_metadata_value::MetadataValue;
+use icrc_ledger_types::icrc1::account::Account;
+use icrc_ledger_types::icrc2::approve::{ApproveArgs, ApproveError};
+use pocket_ic::{query_candid, query_candid_as, update_candid, update_candid_as, PocketIc};
 
 use cargo_metadata::MetadataCommand;
 use escargot::CargoBuild;
+use sha2::{Digest, Sha256};
 use std::path::PathBuf;
+use std::time::Duration;
 
-use crate::strike::{StrikeRegistry, StrikeStatus};
+use crate::appeals::{Appeal, AppealStatus};
+use crate::audit::{AuditAction, AuditEntry, AuditValue, GetAuditLogParams};
+use crate::certification::CertifiedStrike;
+use crate::deposits::{Deposit, DepositConfig, DepositStatus, RefundPolicy};
+use crate::error::StrikeError;
+use crate::features::FeatureToggles;
+use crate::http::{HttpRequest, HttpResponse};
+use crate::limits::{self, SubmissionLimits};
+use crate::reports::{Report, ReportCategory, ReportQueueEntry};
+use crate::review::Review;
+use crate::revisions::{RegistryDetails, UpdateRegistryDetailsParams};
+use crate::roles::{Role, RoleAssignment};
+use crate::search::SearchFilters;
+use crate::snapshot::{ImportProgress, SnapshotCursor, SnapshotExport};
+use crate::stats::{RegistryStats, StatusCount, STATS_WINDOW_DAYS};
+use crate::strike::{
+    AddRegistryParams, GetRegistriesParams, MyRegistration, StrikeRegistry, StrikeStatus, StrikeSummary,
+    UpdateRegistryStatusParams,
+};
+use crate::types::{CursorPagination, PaginatedResponse, SortOrder};
 
 /// Builds a canister with the specified name from the current
 /// package and returns the WebAssembly module.
 pub fn cargo_build_canister(bin_name: &str) -> Vec<u8> {
+    cargo_build_canister_with_features(bin_name, &[])
+}
+
+pub fn cargo_build_canister_with_features(bin_name: &str, features: &[&str]) -> Vec<u8> {
     let dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
 
     let cargo_toml_path = dir.join("Cargo.toml");
@@ -23,11 +51,16 @@ pub fn cargo_build_canister(bin_name: &str) -> Vec<u8> {
 
     // We use a different target path to stop the native cargo build
     // cache being invalidated every time we run this function
-    let wasm_target_dir = target_dir.join("canister-build");
+    // Each set of features gets its own as well
+    let wasm_target_dir = match features {
+        [] => target_dir.join("canister-build"),
+        features => target_dir.join(format!("canister-build-{}", features.join("-"))),
+    };
 
     let cargo_build = CargoBuild::new()
         .target("wasm32-unknown-unknown")
         .bin(bin_name)
+        .features(features.join(" "))
         // .args(["--profile", "canister-release"])
         .manifest_path(&cargo_toml_path)
         .target_dir(wasm_target_dir);
@@ -41,6 +74,11 @@ pub fn cargo_build_canister(bin_name: &str) -> Vec<u8> {
 // 2T cycles
 const INIT_CYCLES: u128 = 2_000_000_000_000;
 
+// The smallest valid wasm module, used as the code of the submitted project canisters
+const PROJECT_WASM: &[u8] = b"\0asm\x01\0\0\0";
+// The same module with an empty custom section, so that it has a different hash
+const CHANGED_PROJECT_WASM: &[u8] = b"\0asm\x01\0\0\0\x00\x02\x01x";
+
 struct Env {
     pic: PocketIc,
     deployer: Principal,
@@ -62,12 +100,69 @@ fn deploy() -> Env {
     }
 }
 
+/// Creates a canister controlled by `controller` to be submitted to the registry
+fn create_project_canister(env: &Env, controller: Principal) -> Principal {
+    let canister_id = env.pic.create_canister_with_settings(Some(controller), None);
+    env.pic.add_cycles(canister_id, INIT_CYCLES);
+    env.pic
+        .install_canister(canister_id, PROJECT_WASM.to_vec(), vec![], Some(controller));
+    canister_id
+}
+
+fn registry_params(canister_id: Principal, controller: Option<Principal>) -> AddRegistryParams {
+    AddRegistryParams {
+        canister_id,
+        name: "Ali".to_string(),
+        email: "ali@example.com".to_string(),
+        telegram: None,
+        twitter: None,
+        project_name: "Project".to_string(),
+        description: "A project".to_string(),
+        website_url: Some("https://example.com".to_string()),
+        controller,
+    }
+}
+
+fn add_registry(env: &Env, sender: Principal, params: AddRegistryParams) -> Result<(), StrikeError> {
+    let (result,) =
+        update_candid_as::<_, (Result<(), StrikeError>,)>(&env.pic, env.canister_id, sender, "add_registry", (params,))
+            .unwrap();
+    result
+}
+
+fn update_registry_status(env: &Env, canister_id: Principal, status: StrikeStatus) -> Result<(), StrikeError> {
+    let (result,) = update_candid_as::<_, (Result<(), StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        env.deployer,
+        "update_registry_status",
+        (UpdateRegistryStatusParams {
+            canister_id,
+            status,
+            reason: None,
+        },),
+    )
+    .unwrap();
+    result
+}
+
+fn get_strike(env: &Env, canister_id: Principal) -> Option<StrikeRegistry> {
+    let (registry,) = query_candid::<(Principal,), (Option<StrikeRegistry>,)>(
+        &env.pic,
+        env.canister_id,
+        "get_strike_by_canister_id",
+        (canister_id,),
+    )
+    .expect("");
+    registry
+}
+
 #[test]
 fn deployer_should_be_admin() {
     let env = deploy();
 
     let (deployer_is_admin,): (bool,) = query_candid(&env.pic, env.canister_id, "is_admin", (env.deployer,)).expect("");
-    assert_eq!(deployer_is_admin, true);
+    assert!(deployer_is_admin);
 }
 
 #[test]
@@ -79,7 +174,8 @@ fn should_add_admin() {
     update_candid_as::<_, (bool,)>(&env.pic, env.canister_id, ali, "add_admin", (ali,)).expect_err("Caller is not a admins");
 
     let (result,) =
-        update_candid_as::<_, (Result<(), String>,)>(&env.pic, env.canister_id, env.deployer, "add_admin", (ali,)).unwrap();
+        update_candid_as::<_, (Result<(), StrikeError>,)>(&env.pic, env.canister_id, env.deployer, "add_admin", (ali,))
+            .unwrap();
 
     assert!(result.is_ok());
 }
@@ -90,17 +186,35 @@ fn should_submit_strike() {
 
     let ali = Principal::from_slice(&[0x01]);
 
-    let canister_id = Principal::from_slice(&[0x02]);
+    let canister_id = create_project_canister(&env, ali);
+
+    let result = add_registry(&env, ali, registry_params(canister_id, None));
+    assert!(result.is_ok());
 
-    let (result,) = update_candid_as::<(Principal, Option<String>, Option<String>), (Result<(), String>,)>(
+    let (registry,) = query_candid::<(Principal,), (Option<StrikeRegistry>,)>(
         &env.pic,
         env.canister_id,
-        ali,
-        "add_registry",
-        (canister_id, None, None),
+        "get_strike_by_canister_id",
+        (canister_id,),
     )
-    .unwrap();
-    assert!(result.is_ok());
+    .expect("");
+    assert_eq!(registry.unwrap().status, StrikeStatus::Submitted);
+}
+
+#[test]
+fn should_reject_strike_from_non_controller() {
+    let env = deploy();
+
+    let ali = Principal::from_slice(&[0x01]);
+    let bob = Principal::from_slice(&[0x03]);
+
+    let canister_id = create_project_canister(&env, ali);
+
+    let result = add_registry(&env, bob, registry_params(canister_id, None));
+    assert_eq!(result, Err(StrikeError::Unauthorized));
+
+    let result = add_registry(&env, bob, registry_params(canister_id, Some(bob)));
+    assert_eq!(result, Err(StrikeError::Unauthorized));
 
     let (registry,) = query_candid::<(Principal,), (Option<StrikeRegistry>,)>(
         &env.pic,
@@ -109,25 +223,62 @@ fn should_submit_strike() {
         (canister_id,),
     )
     .expect("");
-    assert_eq!(registry.unwrap().status, StrikeStatus::Submitted);
+    assert!(registry.is_none());
 }
 
 #[test]
-fn should_approve_submitted_strike() {
+fn should_submit_strike_for_approving_controller() {
     let env = deploy();
 
     let ali = Principal::from_slice(&[0x01]);
+    let bob = Principal::from_slice(&[0x03]);
+    let carol = Principal::from_slice(&[0x04]);
+
+    let canister_id = create_project_canister(&env, ali);
 
-    let canister_id = Principal::from_slice(&[0x02]);
+    // Naming a controller isn't enough, the controller has to approve the submitter
+    let result = add_registry(&env, bob, registry_params(canister_id, Some(ali)));
+    assert_eq!(result, Err(StrikeError::Unauthorized));
+
+    let approve_submitter = |sender: Principal, submitter: Principal| {
+        let (result,) = update_candid_as::<_, (Result<(), StrikeError>,)>(
+            &env.pic,
+            env.canister_id,
+            sender,
+            "approve_submitter",
+            (canister_id, submitter),
+        )
+        .unwrap();
+        result
+    };
+    assert_eq!(approve_submitter(carol, carol), Err(StrikeError::Unauthorized));
+    approve_submitter(ali, bob).unwrap();
+
+    let result = add_registry(&env, carol, registry_params(canister_id, Some(ali)));
+    assert_eq!(result, Err(StrikeError::Unauthorized));
+
+    let result = add_registry(&env, bob, registry_params(canister_id, Some(ali)));
+    assert!(result.is_ok());
 
-    let (result,) = update_candid_as::<(Principal, Option<String>, Option<String>), (Result<(), String>,)>(
+    let (registry,) = query_candid::<(Principal,), (Option<StrikeRegistry>,)>(
         &env.pic,
         env.canister_id,
-        ali,
-        "add_registry",
-        (canister_id, None, None),
+        "get_strike_by_canister_id",
+        (canister_id,),
     )
-    .unwrap();
+    .expect("");
+    assert_eq!(registry.unwrap().added_by, bob);
+}
+
+#[test]
+fn should_approve_submitted_strike() {
+    let env = deploy();
+
+    let ali = Principal::from_slice(&[0x01]);
+
+    let canister_id = create_project_canister(&env, ali);
+
+    let result = add_registry(&env, ali, registry_params(canister_id, None));
     assert!(result.is_ok());
 
     let (registry,) = query_candid::<(Principal,), (Option<StrikeRegistry>,)>(
@@ -139,12 +290,16 @@ fn should_approve_submitted_strike() {
     .expect("");
     assert_eq!(registry.unwrap().status, StrikeStatus::Submitted);
 
-    let (result,) = update_candid_as::<(Principal, StrikeStatus), (Result<(), String>,)>(
+    let (result,) = update_candid_as::<(UpdateRegistryStatusParams,), (Result<(), StrikeError>,)>(
         &env.pic,
         env.canister_id,
         env.deployer,
         "update_registry_status",
-        (canister_id, StrikeStatus::Trusted),
+        (UpdateRegistryStatusParams {
+            canister_id,
+            status: StrikeStatus::Trusted,
+            reason: None,
+        },),
     )
     .unwrap();
     assert!(result.is_ok());
@@ -158,3 +313,1698 @@ fn should_approve_submitted_strike() {
     .expect("");
     assert_eq!(registry.unwrap().status, StrikeStatus::Trusted);
 }
+
+#[test]
+fn should_pin_module_hash_when_trusted() {
+    let env = deploy();
+
+    let ali = Principal::from_slice(&[0x01]);
+
+    let canister_id = create_project_canister(&env, ali);
+
+    add_registry(&env, ali, registry_params(canister_id, None)).unwrap();
+    assert_eq!(get_strike(&env, canister_id).unwrap().module_hash, None);
+
+    update_registry_status(&env, canister_id, StrikeStatus::Trusted).unwrap();
+
+    let registry = get_strike(&env, canister_id).unwrap();
+    assert_eq!(registry.status, StrikeStatus::Trusted);
+    assert_eq!(registry.module_hash, Some(hex::encode(Sha256::digest(PROJECT_WASM))));
+}
+
+#[test]
+fn should_move_trusted_strike_back_to_review_when_module_changes() {
+    let env = deploy();
+
+    let ali = Principal::from_slice(&[0x01]);
+
+    let canister_id = create_project_canister(&env, ali);
+
+    add_registry(&env, ali, registry_params(canister_id, None)).unwrap();
+    update_registry_status(&env, canister_id, StrikeStatus::Trusted).unwrap();
+
+    // Unchanged code keeps the entry trusted
+    env.pic.advance_time(Duration::from_secs(60 * 60));
+    for _ in 0..5 {
+        env.pic.tick();
+    }
+    assert_eq!(get_strike(&env, canister_id).unwrap().status, StrikeStatus::Trusted);
+
+    // A vote to move it back to review is pending when the code changes
+    let bob = Principal::from_slice(&[0x02]);
+    grant_role(&env, env.deployer, bob, Role::Reviewer).unwrap();
+    let (result,) = update_candid_as::<_, (Result<(), StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        env.deployer,
+        "set_review_quorum",
+        (2u32,),
+    )
+    .unwrap();
+    result.unwrap();
+    vote_as(&env, bob, canister_id, StrikeStatus::Submitted).unwrap();
+    assert_eq!(get_pending_reviews(&env).total, 1);
+
+    env.pic
+        .upgrade_canister(canister_id, CHANGED_PROJECT_WASM.to_vec(), vec![], Some(ali))
+        .unwrap();
+
+    env.pic.advance_time(Duration::from_secs(60 * 60));
+    for _ in 0..5 {
+        env.pic.tick();
+    }
+
+    let registry = get_strike(&env, canister_id).unwrap();
+    assert_eq!(registry.status, StrikeStatus::Submitted);
+    assert_eq!(registry.module_hash, Some(hex::encode(Sha256::digest(PROJECT_WASM))));
+    assert!(registry
+        .status_reason
+        .unwrap()
+        .contains(&hex::encode(Sha256::digest(CHANGED_PROJECT_WASM))));
+
+    // The demotion settles the review like any other status change
+    assert_eq!(get_pending_reviews(&env).total, 0);
+}
+
+fn get_audit_log(
+    env: &Env,
+    canister_id: Option<Principal>,
+    actor: Option<Principal>,
+    start_after: Option<String>,
+    limit: u32,
+) -> PaginatedResponse<AuditEntry> {
+    let (log,) = query_candid_as::<_, (Result<PaginatedResponse<AuditEntry>, StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        env.deployer,
+        "get_audit_log",
+        (GetAuditLogParams {
+            canister_id,
+            actor,
+            pagination: CursorPagination { start_after, limit },
+        },),
+    )
+    .unwrap();
+    log.unwrap()
+}
+
+#[test]
+fn should_record_registry_mutations_in_audit_log() {
+    let env = deploy();
+
+    let ali = Principal::from_slice(&[0x01]);
+
+    let canister_id = create_project_canister(&env, ali);
+
+    add_registry(&env, ali, registry_params(canister_id, None)).unwrap();
+
+    let (result,) = update_candid_as::<_, (Result<(), StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        env.deployer,
+        "update_registry_status",
+        (UpdateRegistryStatusParams {
+            canister_id,
+            status: StrikeStatus::Blocked,
+            reason: Some("Drains wallets".to_string()),
+        },),
+    )
+    .unwrap();
+    assert!(result.is_ok());
+
+    let log = get_audit_log(&env, Some(canister_id), None, None, 10);
+    assert_eq!(log.total, 3);
+    assert_eq!(log.items[0].action, AuditAction::UpdateRegistryStatus);
+    assert_eq!(log.items[0].caller, env.deployer);
+    assert_eq!(log.items[0].old_value, Some(AuditValue::Status(StrikeStatus::Submitted)));
+    assert_eq!(log.items[0].new_value, Some(AuditValue::Status(StrikeStatus::Blocked)));
+    assert_eq!(log.items[0].reason, Some("Drains wallets".to_string()));
+    assert_eq!(log.items[1].action, AuditAction::ReviewVote);
+    assert_eq!(log.items[1].caller, env.deployer);
+    assert_eq!(log.items[2].action, AuditAction::AddRegistry);
+    assert_eq!(log.items[2].caller, ali);
+
+    let log = get_audit_log(&env, None, Some(ali), None, 10);
+    assert_eq!(log.total, 1);
+    assert_eq!(log.items[0].action, AuditAction::AddRegistry);
+
+    let log = get_audit_log(&env, Some(canister_id), Some(env.deployer), None, 10);
+    assert_eq!(log.total, 2);
+
+    // Pages go from the newest entry to the oldest
+    let first = get_audit_log(&env, Some(canister_id), None, None, 2);
+    assert_eq!(first.items.len(), 2);
+    let second = get_audit_log(&env, Some(canister_id), None, first.next_cursor, 2);
+    assert_eq!(second.items.len(), 1);
+    assert_eq!(second.items[0].action, AuditAction::AddRegistry);
+    assert_eq!(second.next_cursor, None);
+}
+
+fn get_registries(env: &Env, params: GetRegistriesParams) -> PaginatedResponse<StrikeRegistry> {
+    let (response,) = query_candid_as::<_, (Result<PaginatedResponse<StrikeRegistry>, StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        env.deployer,
+        "get_registries",
+        (params,),
+    )
+    .unwrap();
+    response.unwrap()
+}
+
+#[test]
+fn should_list_registries_by_status() {
+    let env = deploy();
+
+    let ali = Principal::from_slice(&[0x01]);
+
+    let canister_ids: Vec<Principal> = (0..3).map(|_| create_project_canister(&env, ali)).collect();
+    for canister_id in &canister_ids {
+        add_registry(&env, ali, registry_params(*canister_id, None)).unwrap();
+    }
+    update_registry_status(&env, canister_ids[1], StrikeStatus::Trusted).unwrap();
+
+    let submitted = get_registries(
+        &env,
+        GetRegistriesParams {
+            status: Some(StrikeStatus::Submitted),
+            pagination: CursorPagination {
+                start_after: None,
+                limit: 1,
+            },
+            order: None,
+        },
+    );
+    assert_eq!(submitted.total, 2);
+    assert_eq!(submitted.items.len(), 1);
+    assert_eq!(submitted.items[0].canister_id, canister_ids[0]);
+
+    let submitted = get_registries(
+        &env,
+        GetRegistriesParams {
+            status: Some(StrikeStatus::Submitted),
+            pagination: CursorPagination {
+                start_after: submitted.next_cursor,
+                limit: 1,
+            },
+            order: None,
+        },
+    );
+    assert_eq!(submitted.items[0].canister_id, canister_ids[2]);
+    assert_eq!(submitted.next_cursor, None);
+
+    let trusted = get_registries(
+        &env,
+        GetRegistriesParams {
+            status: Some(StrikeStatus::Trusted),
+            pagination: CursorPagination {
+                start_after: None,
+                limit: 10,
+            },
+            order: None,
+        },
+    );
+    assert_eq!(trusted.total, 1);
+    assert_eq!(trusted.items[0].canister_id, canister_ids[1]);
+}
+
+#[test]
+fn should_page_registries_in_requested_order() {
+    let env = deploy();
+
+    let ali = Principal::from_slice(&[0x01]);
+
+    let mut canister_ids = vec![];
+    for project_name in ["Charlie", "alpha", "Bravo"] {
+        let canister_id = create_project_canister(&env, ali);
+        let params = AddRegistryParams {
+            project_name: project_name.to_string(),
+            ..registry_params(canister_id, None)
+        };
+        add_registry(&env, ali, params).unwrap();
+        canister_ids.push(canister_id);
+        env.pic.advance_time(Duration::from_secs(1));
+    }
+
+    let list_all = |order: SortOrder| {
+        let mut items = vec![];
+        let mut start_after = None;
+        loop {
+            let page = get_registries(
+                &env,
+                GetRegistriesParams {
+                    status: None,
+                    pagination: CursorPagination { start_after, limit: 2 },
+                    order: Some(order),
+                },
+            );
+            assert_eq!(page.total, 3);
+            items.extend(page.items.into_iter().map(|registry| registry.canister_id));
+            match page.next_cursor {
+                Some(cursor) => start_after = Some(cursor),
+                None => break,
+            }
+        }
+        items
+    };
+
+    assert_eq!(
+        list_all(SortOrder::CreatedAtAsc),
+        vec![canister_ids[0], canister_ids[1], canister_ids[2]]
+    );
+    assert_eq!(
+        list_all(SortOrder::CreatedAtDesc),
+        vec![canister_ids[2], canister_ids[1], canister_ids[0]]
+    );
+    assert_eq!(
+        list_all(SortOrder::ProjectNameAsc),
+        vec![canister_ids[1], canister_ids[2], canister_ids[0]]
+    );
+}
+
+#[test]
+fn should_search_registries_by_prefix() {
+    let env = deploy();
+
+    let ali = Principal::from_slice(&[0x01]);
+
+    let wallet = create_project_canister(&env, ali);
+    add_registry(
+        &env,
+        ali,
+        AddRegistryParams {
+            project_name: "Oranj Wallet".to_string(),
+            description: "Self custody for ICP".to_string(),
+            website_url: Some("https://wallet.oranj.io/app".to_string()),
+            ..registry_params(wallet, None)
+        },
+    )
+    .unwrap();
+
+    let swap = create_project_canister(&env, ali);
+    add_registry(
+        &env,
+        ali,
+        AddRegistryParams {
+            project_name: "Swap".to_string(),
+            description: "Token swaps".to_string(),
+            ..registry_params(swap, None)
+        },
+    )
+    .unwrap();
+    update_registry_status(&env, swap, StrikeStatus::Trusted).unwrap();
+
+    let search = |query: &str, status: Option<StrikeStatus>| {
+        let (response,) = query_candid::<_, (Result<PaginatedResponse<StrikeRegistry>, StrikeError>,)>(
+            &env.pic,
+            env.canister_id,
+            "search_registries",
+            (
+                query.to_string(),
+                SearchFilters { status },
+                CursorPagination {
+                    start_after: None,
+                    limit: 10,
+                },
+            ),
+        )
+        .unwrap();
+        response
+            .unwrap()
+            .items
+            .into_iter()
+            .map(|registry| registry.canister_id)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(search("WALL", None), vec![wallet]);
+    assert_eq!(search("oranj.io", None), vec![wallet]);
+    assert_eq!(search("oranj custody", None), vec![wallet]);
+    assert_eq!(search("oranj swap", None), vec![]);
+    assert_eq!(search("tok", Some(StrikeStatus::Trusted)), vec![swap]);
+    assert_eq!(search("tok", Some(StrikeStatus::Submitted)), vec![]);
+
+    // Names of submitters are redacted from anonymous callers, so they can't be searched either
+    assert_eq!(search("ali", None), vec![]);
+}
+
+fn http_get(env: &Env, url: &str) -> HttpResponse {
+    let request = HttpRequest {
+        method: "GET".to_string(),
+        url: url.to_string(),
+        headers: vec![],
+        body: vec![],
+        certificate_version: None,
+    };
+    let (response,) =
+        query_candid::<_, (HttpResponse,)>(&env.pic, env.canister_id, "http_request", (request.clone(),)).unwrap();
+    if response.upgrade != Some(true) {
+        return response;
+    }
+
+    // Like the HTTP gateway, retry the responses that can't be certified as an update call
+    let (response,) =
+        update_candid::<_, (HttpResponse,)>(&env.pic, env.canister_id, "http_request_update", (request,)).unwrap();
+    response
+}
+
+#[test]
+fn should_serve_registry_lookups_over_http() {
+    let env = deploy();
+
+    let ali = Principal::from_slice(&[0x01]);
+
+    let canister_id = create_project_canister(&env, ali);
+    add_registry(&env, ali, registry_params(canister_id, None)).unwrap();
+    update_registry_status(&env, canister_id, StrikeStatus::Trusted).unwrap();
+
+    let response = http_get(&env, &format!("/v1/canisters/{}", canister_id));
+    assert_eq!(response.status_code, 200);
+    assert!(response
+        .headers
+        .contains(&("Content-Type".to_string(), "application/json; charset=utf-8".to_string())));
+    let body: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+    assert_eq!(body["canister_id"], canister_id.to_text());
+    assert_eq!(body["status"], "Trusted");
+    assert!(body.get("email").is_none());
+
+    let response = http_get(&env, "/v1/trusted?limit=10");
+    assert_eq!(response.status_code, 200);
+    let body: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+    assert_eq!(body["items"][0]["canister_id"], canister_id.to_text());
+    assert_eq!(body["next_cursor"], serde_json::Value::Null);
+
+    let response = http_get(&env, "/v1/blocked");
+    let body: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+    assert_eq!(body["items"], serde_json::json!([]));
+
+    let unknown = Principal::from_slice(&[0x02]);
+    let response = http_get(&env, &format!("/v1/canisters/{}", unknown));
+    assert_eq!(response.status_code, 404);
+    assert!(response.headers.iter().any(|(name, _)| name == "IC-Certificate"));
+
+    assert_eq!(http_get(&env, "/v1/canisters/not-a-principal").status_code, 400);
+}
+
+#[test]
+fn should_certify_registry_lookups() {
+    let env = deploy();
+
+    let ali = Principal::from_slice(&[0x01]);
+
+    let canister_id = create_project_canister(&env, ali);
+    add_registry(&env, ali, registry_params(canister_id, None)).unwrap();
+    update_registry_status(&env, canister_id, StrikeStatus::Trusted).unwrap();
+
+    let (response,) = query_candid::<_, (Result<CertifiedStrike, StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        "get_certified_strike_by_canister_id",
+        (canister_id,),
+    )
+    .unwrap();
+    let certified = response.unwrap();
+    assert_eq!(certified.registry.unwrap().status, StrikeStatus::Trusted);
+    assert!(!certified.certificate.is_empty());
+    assert!(!certified.witness.is_empty());
+
+    let response = http_get(&env, &format!("/v1/canisters/{}", canister_id));
+    let header = response
+        .headers
+        .iter()
+        .find(|(name, _)| name == "IC-Certificate")
+        .map(|(_, value)| value.clone())
+        .unwrap();
+    assert!(header.starts_with("certificate=:"));
+    assert!(header.contains(", tree=:"));
+}
+
+fn grant_role(env: &Env, sender: Principal, principal: Principal, role: Role) -> Result<(), StrikeError> {
+    let (result,) =
+        update_candid_as::<_, (Result<(), StrikeError>,)>(&env.pic, env.canister_id, sender, "grant_role", (principal, role))
+            .unwrap();
+    result
+}
+
+#[test]
+fn should_enforce_roles() {
+    let env = deploy();
+
+    let ali = Principal::from_slice(&[0x01]);
+    let reviewer = Principal::from_slice(&[0x02]);
+    let support = Principal::from_slice(&[0x03]);
+
+    grant_role(&env, env.deployer, reviewer, Role::Reviewer).unwrap();
+    grant_role(&env, env.deployer, support, Role::Support).unwrap();
+
+    // Reviewers don't manage people
+    assert_eq!(grant_role(&env, reviewer, ali, Role::Support), Err(StrikeError::Unauthorized));
+
+    let canister_id = create_project_canister(&env, ali);
+    add_registry(&env, ali, registry_params(canister_id, None)).unwrap();
+
+    let (result,) = update_candid_as::<_, (Result<(), StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        reviewer,
+        "update_registry_status",
+        (UpdateRegistryStatusParams {
+            canister_id,
+            status: StrikeStatus::Blocked,
+            reason: None,
+        },),
+    )
+    .unwrap();
+    assert!(result.is_ok());
+
+    // Support can't change statuses
+    update_candid_as::<_, (Result<(), StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        support,
+        "update_registry_status",
+        (UpdateRegistryStatusParams {
+            canister_id,
+            status: StrikeStatus::Trusted,
+            reason: None,
+        },),
+    )
+    .expect_err("Caller is not a reviewer");
+
+    let get_strike_as = |sender: Principal| {
+        let (registry,) = query_candid_as::<_, (Option<StrikeRegistry>,)>(
+            &env.pic,
+            env.canister_id,
+            sender,
+            "get_strike_by_canister_id",
+            (canister_id,),
+        )
+        .unwrap();
+        registry.unwrap()
+    };
+
+    assert_eq!(get_strike_as(support).email, "ali@example.com");
+    assert_eq!(get_strike_as(ali).email, "ali@example.com");
+    assert_eq!(get_strike_as(reviewer).email, "");
+    assert_eq!(get_strike_as(Principal::anonymous()).email, "");
+
+    let (roles,) = query_candid_as::<_, (Vec<RoleAssignment>,)>(&env.pic, env.canister_id, support, "list_roles", ()).unwrap();
+    assert_eq!(roles.len(), 3);
+}
+
+fn vote_as(env: &Env, sender: Principal, canister_id: Principal, status: StrikeStatus) -> Result<(), StrikeError> {
+    let (result,) = update_candid_as::<_, (Result<(), StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        sender,
+        "update_registry_status",
+        (UpdateRegistryStatusParams {
+            canister_id,
+            status,
+            reason: None,
+        },),
+    )
+    .unwrap();
+    result
+}
+
+fn get_pending_reviews(env: &Env) -> PaginatedResponse<Review> {
+    let (result,) = query_candid_as::<_, (Result<PaginatedResponse<Review>, StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        env.deployer,
+        "get_pending_reviews",
+        (CursorPagination {
+            start_after: None,
+            limit: 10,
+        },),
+    )
+    .unwrap();
+    result.unwrap()
+}
+
+#[test]
+fn should_require_review_quorum() {
+    let env = deploy();
+
+    let ali = Principal::from_slice(&[0x01]);
+    let bob = Principal::from_slice(&[0x02]);
+    let carol = Principal::from_slice(&[0x03]);
+
+    for reviewer in [ali, bob, carol] {
+        grant_role(&env, env.deployer, reviewer, Role::Reviewer).unwrap();
+    }
+    let (result,) = update_candid_as::<_, (Result<(), StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        env.deployer,
+        "set_review_quorum",
+        (2u32,),
+    )
+    .unwrap();
+    result.unwrap();
+
+    let trusted = create_project_canister(&env, ali);
+    add_registry(&env, ali, registry_params(trusted, None)).unwrap();
+
+    vote_as(&env, ali, trusted, StrikeStatus::Trusted).unwrap();
+    assert_eq!(get_strike(&env, trusted).unwrap().status, StrikeStatus::Submitted);
+    assert_eq!(get_pending_reviews(&env).items[0].votes.len(), 1);
+
+    vote_as(&env, bob, trusted, StrikeStatus::Trusted).unwrap();
+    assert_eq!(get_strike(&env, trusted).unwrap().status, StrikeStatus::Trusted);
+    assert_eq!(get_pending_reviews(&env).total, 0);
+
+    // Moving an entry back to review takes the same quorum
+    vote_as(&env, ali, trusted, StrikeStatus::Submitted).unwrap();
+    assert_eq!(get_strike(&env, trusted).unwrap().status, StrikeStatus::Trusted);
+    vote_as(&env, bob, trusted, StrikeStatus::Submitted).unwrap();
+    assert_eq!(get_strike(&env, trusted).unwrap().status, StrikeStatus::Submitted);
+    assert_eq!(get_pending_reviews(&env).total, 0);
+
+    // A block vote against an approval escalates to the admins
+    let disputed = create_project_canister(&env, ali);
+    add_registry(&env, ali, registry_params(disputed, None)).unwrap();
+
+    vote_as(&env, ali, disputed, StrikeStatus::Trusted).unwrap();
+    vote_as(&env, carol, disputed, StrikeStatus::Blocked).unwrap();
+    vote_as(&env, bob, disputed, StrikeStatus::Trusted).unwrap();
+    assert_eq!(get_strike(&env, disputed).unwrap().status, StrikeStatus::Submitted);
+    assert!(get_pending_reviews(&env).items[0].escalated);
+
+    vote_as(&env, env.deployer, disputed, StrikeStatus::Blocked).unwrap();
+    assert_eq!(get_strike(&env, disputed).unwrap().status, StrikeStatus::Blocked);
+
+    // Blocking takes the quorum as well
+    let blocked = create_project_canister(&env, ali);
+    add_registry(&env, ali, registry_params(blocked, None)).unwrap();
+
+    vote_as(&env, carol, blocked, StrikeStatus::Blocked).unwrap();
+    assert_eq!(get_strike(&env, blocked).unwrap().status, StrikeStatus::Submitted);
+    vote_as(&env, bob, blocked, StrikeStatus::Blocked).unwrap();
+    assert_eq!(get_strike(&env, blocked).unwrap().status, StrikeStatus::Blocked);
+}
+
+fn report_canister(env: &Env, sender: Principal, canister_id: Principal) -> Result<(), StrikeError> {
+    let (result,) = update_candid_as::<_, (Result<(), StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        sender,
+        "report_canister",
+        (
+            canister_id,
+            ReportCategory::Phishing,
+            Some("https://example.com/evidence".to_string()),
+            None::<String>,
+        ),
+    )
+    .unwrap();
+    result
+}
+
+#[test]
+fn should_queue_reported_canisters_by_report_count() {
+    let env = deploy();
+
+    let ali = Principal::from_slice(&[0x01]);
+    let bob = Principal::from_slice(&[0x02]);
+
+    let registered = create_project_canister(&env, ali);
+    add_registry(&env, ali, registry_params(registered, None)).unwrap();
+    let other = create_project_canister(&env, bob);
+    add_registry(&env, bob, registry_params(other, None)).unwrap();
+    let unregistered = Principal::from_slice(&[0x03]);
+
+    report_canister(&env, ali, registered).unwrap();
+    report_canister(&env, bob, registered).unwrap();
+    report_canister(&env, ali, other).unwrap();
+    // Canisters are reported whether they're registered or not
+    report_canister(&env, bob, unregistered).unwrap();
+
+    // A reporter can't report the same canister twice
+    assert_eq!(report_canister(&env, bob, registered), Err(StrikeError::AlreadyExists));
+
+    assert_eq!(get_strike(&env, registered).unwrap().report_count, Some(2));
+
+    let (queue,) = query_candid_as::<_, (Result<PaginatedResponse<ReportQueueEntry>, StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        env.deployer,
+        "get_report_queue",
+        (CursorPagination {
+            start_after: None,
+            limit: 10,
+        },),
+    )
+    .unwrap();
+    let queue = queue.unwrap();
+    assert_eq!(queue.total, 3);
+    assert_eq!(queue.items[0].canister_id, registered);
+    assert_eq!(queue.items[0].report_count, 2);
+    assert_eq!(queue.items[0].status, Some(StrikeStatus::Submitted));
+
+    let entry = |canister_id: Principal| queue.items.iter().find(|entry| entry.canister_id == canister_id).unwrap();
+    assert_eq!(entry(other).report_count, 1);
+    assert_eq!(entry(other).status, Some(StrikeStatus::Submitted));
+    assert_eq!(entry(unregistered).report_count, 1);
+    assert_eq!(entry(unregistered).status, None);
+}
+
+#[test]
+fn should_appeal_blocked_strike() {
+    let env = deploy();
+
+    let ali = Principal::from_slice(&[0x01]);
+    let bob = Principal::from_slice(&[0x02]);
+
+    let canister_id = create_project_canister(&env, ali);
+    add_registry(&env, ali, registry_params(canister_id, None)).unwrap();
+
+    let appeal = |sender: Principal| {
+        let (result,) = update_candid_as::<_, (Result<(), StrikeError>,)>(
+            &env.pic,
+            env.canister_id,
+            sender,
+            "appeal_block",
+            (canister_id, "We fixed the drainer".to_string()),
+        )
+        .unwrap();
+        result
+    };
+
+    // Only blocked canisters can be appealed, and only by their submitter or controllers
+    assert_eq!(
+        appeal(ali),
+        Err(StrikeError::InvalidStatus {
+            status: StrikeStatus::Submitted
+        })
+    );
+    update_registry_status(&env, canister_id, StrikeStatus::Blocked).unwrap();
+    assert_eq!(appeal(bob), Err(StrikeError::Unauthorized));
+
+    appeal(ali).unwrap();
+    assert_eq!(appeal(ali), Err(StrikeError::AlreadyExists));
+
+    let (result,) = update_candid_as::<_, (Result<(), StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        env.deployer,
+        "reject_appeal",
+        (canister_id, "Still drains wallets".to_string()),
+    )
+    .unwrap();
+    result.unwrap();
+
+    appeal(ali).unwrap();
+    let open_appeals = || {
+        let (result,) = query_candid_as::<_, (Result<PaginatedResponse<Appeal>, StrikeError>,)>(
+            &env.pic,
+            env.canister_id,
+            env.deployer,
+            "get_open_appeals",
+            (CursorPagination {
+                start_after: None,
+                limit: 10,
+            },),
+        )
+        .unwrap();
+        result.unwrap()
+    };
+    let open = open_appeals();
+    assert_eq!(open.total, 1);
+    assert_eq!(open.items[0].canister_id, canister_id);
+
+    let (result,) = update_candid_as::<_, (Result<(), StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        env.deployer,
+        "accept_appeal",
+        (canister_id, StrikeStatus::Submitted, None::<String>),
+    )
+    .unwrap();
+    result.unwrap();
+    assert_eq!(get_strike(&env, canister_id).unwrap().status, StrikeStatus::Submitted);
+    assert_eq!(open_appeals().total, 0);
+
+    let (thread,) = query_candid_as::<_, (Result<Vec<Appeal>, StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        ali,
+        "get_appeals",
+        (canister_id,),
+    )
+    .unwrap();
+    let thread = thread.unwrap();
+    assert_eq!(thread.len(), 2);
+    assert_eq!(thread[0].status, AppealStatus::Rejected);
+    assert_eq!(thread[1].status, AppealStatus::Accepted);
+
+    // Lifting the block by review closes the open appeal, which can't demote the entry afterwards
+    update_registry_status(&env, canister_id, StrikeStatus::Blocked).unwrap();
+    appeal(ali).unwrap();
+    update_registry_status(&env, canister_id, StrikeStatus::Trusted).unwrap();
+    assert_eq!(open_appeals().total, 0);
+    let (result,) = update_candid_as::<_, (Result<(), StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        env.deployer,
+        "accept_appeal",
+        (canister_id, StrikeStatus::Submitted, None::<String>),
+    )
+    .unwrap();
+    assert_eq!(result, Err(StrikeError::NotFound));
+    assert_eq!(get_strike(&env, canister_id).unwrap().status, StrikeStatus::Trusted);
+    let (thread,) = query_candid_as::<_, (Result<Vec<Appeal>, StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        ali,
+        "get_appeals",
+        (canister_id,),
+    )
+    .unwrap();
+    assert_eq!(thread.unwrap()[2].status, AppealStatus::Closed);
+
+    let (result,) = query_candid_as::<_, (Result<Vec<Appeal>, StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        bob,
+        "get_appeals",
+        (canister_id,),
+    )
+    .unwrap();
+    assert_eq!(result.unwrap_err(), StrikeError::Unauthorized);
+}
+
+#[test]
+fn should_hold_edits_of_trusted_strike_for_review() {
+    let env = deploy();
+
+    let ali = Principal::from_slice(&[0x01]);
+
+    let canister_id = create_project_canister(&env, ali);
+    add_registry(&env, ali, registry_params(canister_id, None)).unwrap();
+
+    let update_details = |details: RegistryDetails| {
+        let (result,) = update_candid_as::<_, (Result<(), StrikeError>,)>(
+            &env.pic,
+            env.canister_id,
+            ali,
+            "update_registry_details",
+            (UpdateRegistryDetailsParams { canister_id, details },),
+        )
+        .unwrap();
+        result
+    };
+
+    // Entries under review are edited in place
+    update_details(RegistryDetails {
+        description: Some("A better project".to_string()),
+        ..Default::default()
+    })
+    .unwrap();
+    assert_eq!(get_strike(&env, canister_id).unwrap().description, "A better project");
+
+    update_registry_status(&env, canister_id, StrikeStatus::Trusted).unwrap();
+
+    update_details(RegistryDetails {
+        email: Some("contact@example.com".to_string()),
+        twitter: Some("@project".to_string()),
+        website_url: Some("https://phishing.example.com".to_string()),
+        ..Default::default()
+    })
+    .unwrap();
+
+    // Only the contact details change right away
+    let registry = get_strike(&env, canister_id).unwrap();
+    assert_eq!(registry.email, "contact@example.com");
+    assert_eq!(registry.twitter, None);
+    assert_eq!(registry.website_url, Some("https://example.com".to_string()));
+
+    let (result,) = update_candid_as::<_, (Result<(), StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        env.deployer,
+        "approve_revision",
+        (canister_id,),
+    )
+    .unwrap();
+    result.unwrap();
+
+    let registry = get_strike(&env, canister_id).unwrap();
+    assert_eq!(registry.status, StrikeStatus::Trusted);
+    assert_eq!(registry.twitter, Some("@project".to_string()));
+    assert_eq!(registry.website_url, Some("https://phishing.example.com".to_string()));
+
+    // Edits pending when the entry stops being trusted are dropped rather than approved later
+    update_details(RegistryDetails {
+        website_url: Some("https://drainer.example.com".to_string()),
+        ..Default::default()
+    })
+    .unwrap();
+    update_registry_status(&env, canister_id, StrikeStatus::Blocked).unwrap();
+
+    let (result,) = update_candid_as::<_, (Result<(), StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        env.deployer,
+        "approve_revision",
+        (canister_id,),
+    )
+    .unwrap();
+    assert_eq!(result, Err(StrikeError::NotFound));
+    assert_eq!(
+        get_strike(&env, canister_id).unwrap().website_url,
+        Some("https://phishing.example.com".to_string())
+    );
+}
+
+#[test]
+fn should_withdraw_and_purge_strikes() {
+    let env = deploy();
+
+    let ali = Principal::from_slice(&[0x01]);
+
+    let withdrawn = create_project_canister(&env, ali);
+    add_registry(&env, ali, registry_params(withdrawn, None)).unwrap();
+
+    let (result,) = update_candid_as::<_, (Result<(), StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        ali,
+        "withdraw_registry",
+        (withdrawn, Some("Project shut down".to_string())),
+    )
+    .unwrap();
+    result.unwrap();
+    assert!(get_strike(&env, withdrawn).is_none());
+
+    let log = get_audit_log(&env, Some(withdrawn), None, None, 10);
+    assert_eq!(log.items[0].action, AuditAction::WithdrawRegistry);
+    assert!(matches!(
+        &log.items[0].old_value,
+        Some(AuditValue::Tombstone(tombstone)) if tombstone.added_by == ali
+    ));
+
+    // Blocked entries can't be withdrawn, only purged
+    let blocked = create_project_canister(&env, ali);
+    add_registry(&env, ali, registry_params(blocked, None)).unwrap();
+    update_registry_status(&env, blocked, StrikeStatus::Blocked).unwrap();
+
+    let (result,) = update_candid_as::<_, (Result<(), StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        ali,
+        "withdraw_registry",
+        (blocked, None::<String>),
+    )
+    .unwrap();
+    assert_eq!(
+        result,
+        Err(StrikeError::InvalidStatus {
+            status: StrikeStatus::Blocked
+        })
+    );
+
+    report_canister(&env, ali, blocked).unwrap();
+
+    let (result,) = update_candid_as::<_, (Result<(), StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        env.deployer,
+        "purge_registry",
+        (blocked, "Data removal request".to_string()),
+    )
+    .unwrap();
+    result.unwrap();
+    assert!(get_strike(&env, blocked).is_none());
+
+    // Its reports go with it
+    let (reports,) =
+        query_candid_as::<_, (Vec<Report>,)>(&env.pic, env.canister_id, env.deployer, "get_reports", (blocked,)).unwrap();
+    assert!(reports.is_empty());
+
+    let page = get_registries(
+        &env,
+        GetRegistriesParams {
+            status: None,
+            pagination: CursorPagination {
+                start_after: None,
+                limit: 10,
+            },
+            order: None,
+        },
+    );
+    assert_eq!(page.total, 0);
+}
+
+#[test]
+fn should_limit_submissions() {
+    let env = deploy();
+
+    let ali = Principal::from_slice(&[0x01]);
+
+    let (result,) = update_candid_as::<_, (Result<(), StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        env.deployer,
+        "set_submission_limits",
+        (SubmissionLimits {
+            per_principal: 1,
+            ..SubmissionLimits::default()
+        },),
+    )
+    .unwrap();
+    result.unwrap();
+
+    let first = create_project_canister(&env, ali);
+    add_registry(&env, ali, registry_params(first, None)).unwrap();
+
+    // The quota is checked before the message is executed
+    let second = create_project_canister(&env, ali);
+    let result = update_candid_as::<_, (Result<(), StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        ali,
+        "add_registry",
+        (registry_params(second, None),),
+    );
+    assert!(result.is_err());
+
+    // Oversized fields are rejected, as are oversized messages
+    let mut params = registry_params(first, None);
+    params.description = "a".repeat(limits::MAX_DESCRIPTION_LEN + 1);
+    let result = add_registry(&env, env.deployer, params);
+    assert_eq!(
+        result,
+        Err(StrikeError::invalid_field(
+            "description",
+            format!("cannot exceed {} bytes", limits::MAX_DESCRIPTION_LEN)
+        ))
+    );
+
+    let mut params = registry_params(first, None);
+    params.description = "a".repeat(limits::MAX_INGRESS_ARG_SIZE);
+    let result =
+        update_candid_as::<_, (Result<(), StrikeError>,)>(&env.pic, env.canister_id, env.deployer, "add_registry", (params,));
+    assert!(result.is_err());
+}
+
+#[test]
+fn should_not_count_failed_controller_checks_against_global_quota() {
+    let env = deploy();
+
+    let ali = Principal::from_slice(&[0x01]);
+    let bob = Principal::from_slice(&[0x02]);
+
+    let (result,) = update_candid_as::<_, (Result<(), StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        env.deployer,
+        "set_submission_limits",
+        (SubmissionLimits {
+            global: 1,
+            ..SubmissionLimits::default()
+        },),
+    )
+    .unwrap();
+    result.unwrap();
+
+    // Principals that don't control the canister are turned away without using up the global cap
+    let canister_id = create_project_canister(&env, ali);
+    for sender in 0x10..0x14 {
+        let result = add_registry(&env, Principal::from_slice(&[sender]), registry_params(canister_id, None));
+        assert_eq!(result, Err(StrikeError::Unauthorized));
+    }
+    add_registry(&env, ali, registry_params(canister_id, None)).unwrap();
+
+    // Which the controller's submission did
+    let other = create_project_canister(&env, bob);
+    let result = update_candid_as::<_, (Result<(), StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        bob,
+        "add_registry",
+        (registry_params(other, None),),
+    );
+    assert!(result.is_err());
+}
+
+#[derive(CandidType)]
+struct LedgerArchiveOptions {
+    num_blocks_to_archive: u64,
+    trigger_threshold: u64,
+    controller_id: Principal,
+}
+
+#[derive(CandidType)]
+struct LedgerFeatureFlags {
+    icrc2: bool,
+}
+
+#[derive(CandidType)]
+struct LedgerInitArgs {
+    minting_account: Account,
+    transfer_fee: Nat,
+    token_symbol: String,
+    token_name: String,
+    metadata: Vec<(String, MetadataValue)>,
+    initial_balances: Vec<(Account, Nat)>,
+    feature_flags: Option<LedgerFeatureFlags>,
+    archive_options: LedgerArchiveOptions,
+}
+
+#[derive(CandidType)]
+enum LedgerArg {
+    Init(LedgerInitArgs),
+}
+
+const LEDGER_FEE: u64 = 10_000;
+
+/// Deploys an ICRC-1 ledger, see `scripts/download-icrc1-ledger.sh`
+fn deploy_ledger(env: &Env, initial_balances: Vec<(Principal, u64)>) -> Principal {
+    let dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+    let wasm = std::fs::read(dir.join("ic-icrc1-ledger.wasm.gz")).expect("run scripts/download-icrc1-ledger.sh first");
+
+    let args = LedgerArg::Init(LedgerInitArgs {
+        minting_account: Account::from(env.deployer),
+        transfer_fee: Nat::from(LEDGER_FEE),
+        token_symbol: "TST".to_string(),
+        token_name: "Test".to_string(),
+        metadata: vec![],
+        initial_balances: initial_balances
+            .into_iter()
+            .map(|(owner, amount)| (Account::from(owner), Nat::from(amount)))
+            .collect(),
+        feature_flags: Some(LedgerFeatureFlags { icrc2: true }),
+        archive_options: LedgerArchiveOptions {
+            num_blocks_to_archive: 1000,
+            trigger_threshold: 2000,
+            controller_id: env.deployer,
+        },
+    });
+
+    let ledger = env.pic.create_canister_with_settings(Some(env.deployer), None);
+    env.pic.add_cycles(ledger, INIT_CYCLES);
+    env.pic
+        .install_canister(ledger, wasm, candid::encode_one(args).unwrap(), Some(env.deployer));
+    ledger
+}
+
+fn balance_of(env: &Env, ledger: Principal, owner: Principal) -> Nat {
+    let (balance,) = query_candid::<_, (Nat,)>(&env.pic, ledger, "icrc1_balance_of", (Account::from(owner),)).unwrap();
+    balance
+}
+
+fn get_deposit(env: &Env, sender: Principal, canister_id: Principal) -> Option<Deposit> {
+    let (deposit,) =
+        query_candid_as::<_, (Option<Deposit>,)>(&env.pic, env.canister_id, sender, "get_deposit", (canister_id,)).unwrap();
+    deposit
+}
+
+#[test]
+fn should_hold_and_refund_deposits() {
+    let env = deploy();
+
+    let ali = Principal::from_slice(&[0x01]);
+    let initial_balance = 1_000_000_000u64;
+    let amount = 100_000_000u64;
+
+    let ledger = deploy_ledger(&env, vec![(ali, initial_balance)]);
+    let (result,) = update_candid_as::<_, (Result<(), StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        env.deployer,
+        "set_deposit_config",
+        (Some(DepositConfig {
+            ledger,
+            amount: Nat::from(amount),
+            refund_policy: RefundPolicy::OnTrusted,
+        }),),
+    )
+    .unwrap();
+    result.unwrap();
+
+    // Nothing can be collected without an approval
+    let trusted = create_project_canister(&env, ali);
+    let result = add_registry(&env, ali, registry_params(trusted, None));
+    assert!(matches!(
+        result,
+        Err(StrikeError::CallFailed { canister_id, reason }) if canister_id == ledger && reason.contains("InsufficientAllowance")
+    ));
+
+    let (result,) = update_candid_as::<_, (Result<Nat, ApproveError>,)>(
+        &env.pic,
+        ledger,
+        ali,
+        "icrc2_approve",
+        (ApproveArgs {
+            from_subaccount: None,
+            spender: Account::from(env.canister_id),
+            amount: Nat::from(3 * (amount + LEDGER_FEE)),
+            expected_allowance: None,
+            expires_at: None,
+            fee: None,
+            memo: None,
+            created_at_time: None,
+        },),
+    )
+    .unwrap();
+    result.unwrap();
+
+    add_registry(&env, ali, registry_params(trusted, None)).unwrap();
+    assert_eq!(get_deposit(&env, ali, trusted).unwrap().status, DepositStatus::Held);
+    assert_eq!(
+        balance_of(&env, ledger, ali),
+        Nat::from(initial_balance - amount - 2 * LEDGER_FEE)
+    );
+
+    // Deposits are private to the depositor and the staff
+    assert!(get_deposit(&env, Principal::anonymous(), trusted).is_none());
+
+    // Trusted entries get their deposit back, less the fee of the refund
+    update_registry_status(&env, trusted, StrikeStatus::Trusted).unwrap();
+    for _ in 0..5 {
+        env.pic.tick();
+    }
+    assert!(matches!(
+        get_deposit(&env, ali, trusted).unwrap().status,
+        DepositStatus::Refunded { .. }
+    ));
+    assert_eq!(balance_of(&env, ledger, ali), Nat::from(initial_balance - 3 * LEDGER_FEE));
+
+    // Blocked entries don't
+    let blocked = create_project_canister(&env, ali);
+    add_registry(&env, ali, registry_params(blocked, None)).unwrap();
+    update_registry_status(&env, blocked, StrikeStatus::Blocked).unwrap();
+    assert_eq!(get_deposit(&env, ali, blocked).unwrap().status, DepositStatus::Forfeited);
+    assert_eq!(balance_of(&env, ledger, env.canister_id), Nat::from(amount));
+
+    // Neither do purged ones, whose deposit isn't left for a later submission to reuse
+    let purged = create_project_canister(&env, ali);
+    add_registry(&env, ali, registry_params(purged, None)).unwrap();
+    let (result,) = update_candid_as::<_, (Result<(), StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        env.deployer,
+        "purge_registry",
+        (purged, "Spam".to_string()),
+    )
+    .unwrap();
+    result.unwrap();
+    assert_eq!(get_deposit(&env, ali, purged).unwrap().status, DepositStatus::Forfeited);
+    assert_eq!(balance_of(&env, ledger, env.canister_id), Nat::from(2 * amount));
+
+    let log = get_audit_log(&env, Some(purged), None, None, 10);
+    assert_eq!(log.items[0].action, AuditAction::ForfeitDeposit);
+    assert_eq!(log.items[1].action, AuditAction::PurgeRegistry);
+}
+
+#[test]
+fn should_migrate_registry_from_first_release() {
+    let dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+    let legacy_wasm = std::fs::read(dir.join("strike_backend-legacy.wasm")).expect("run scripts/build-legacy-wasm.sh first");
+
+    let deployer = Principal::from_text("rrkah-fqaaa-aaaaa-aaaaq-cai").unwrap();
+    let pic = PocketIc::new();
+    let canister_id = pic.create_canister_with_settings(Some(deployer), None);
+    pic.add_cycles(canister_id, INIT_CYCLES);
+    pic.install_canister(canister_id, legacy_wasm, vec![], Some(deployer));
+    let env = Env {
+        pic,
+        deployer,
+        canister_id,
+    };
+
+    // The first release neither checked controllers nor knew the fields added since, which it ignores
+    let ali = Principal::from_slice(&[0x01]);
+    let trusted = create_project_canister(&env, ali);
+    let submitted = create_project_canister(&env, ali);
+    for project in [trusted, submitted] {
+        let (result,) = update_candid_as::<_, (Result<(), String>,)>(
+            &env.pic,
+            env.canister_id,
+            ali,
+            "add_registry",
+            (registry_params(project, None),),
+        )
+        .unwrap();
+        result.unwrap();
+    }
+    let (result,) = update_candid_as::<_, (Result<(), String>,)>(
+        &env.pic,
+        env.canister_id,
+        env.deployer,
+        "update_registry_status",
+        (UpdateRegistryStatusParams {
+            canister_id: trusted,
+            status: StrikeStatus::Trusted,
+            reason: None,
+        },),
+    )
+    .unwrap();
+    result.unwrap();
+
+    let wasm = cargo_build_canister("strike_backend");
+    env.pic
+        .upgrade_canister(env.canister_id, wasm.clone(), vec![], Some(env.deployer))
+        .unwrap();
+
+    let registry = get_strike(&env, trusted).unwrap();
+    assert_eq!(registry.status, StrikeStatus::Trusted);
+    assert_eq!(registry.project_name, "Project");
+    assert_eq!(registry.added_by, ali);
+    assert_eq!(registry.status_reason, None);
+
+    // Entries trusted before module hashes were pinned get the current one instead of being demoted
+    env.pic.advance_time(Duration::from_secs(60 * 60));
+    for _ in 0..5 {
+        env.pic.tick();
+    }
+    let registry = get_strike(&env, trusted).unwrap();
+    assert_eq!(registry.status, StrikeStatus::Trusted);
+    assert_eq!(registry.module_hash, Some(hex::encode(Sha256::digest(PROJECT_WASM))));
+
+    // The indexes are rebuilt from the migrated entries
+    let listed = get_registries(
+        &env,
+        GetRegistriesParams {
+            status: Some(StrikeStatus::Submitted),
+            pagination: CursorPagination {
+                start_after: None,
+                limit: 10,
+            },
+            order: None,
+        },
+    );
+    assert_eq!(listed.total, 1);
+    assert_eq!(listed.items[0].canister_id, submitted);
+
+    // Migrated entries can be updated and survive later upgrades
+    update_registry_status(&env, submitted, StrikeStatus::Blocked).unwrap();
+    env.pic
+        .upgrade_canister(env.canister_id, wasm, vec![], Some(env.deployer))
+        .unwrap();
+    assert_eq!(get_strike(&env, submitted).unwrap().status, StrikeStatus::Blocked);
+    assert_eq!(get_strike(&env, trusted).unwrap().status, StrikeStatus::Trusted);
+}
+
+/// Run with `make bench`
+#[cfg(feature = "bench")]
+#[test]
+fn should_decode_registry_entries_with_fewer_instructions() {
+    use crate::encoding::bench::EncodingBenchmark;
+
+    let pic = PocketIc::new();
+    let wasm = cargo_build_canister_with_features("strike_backend", &["bench"]);
+    let canister_id = pic.create_canister();
+    pic.add_cycles(canister_id, INIT_CYCLES);
+    pic.install_canister(canister_id, wasm, vec![], None);
+
+    let (benchmark,) = query_candid::<(), (EncodingBenchmark,)>(&pic, canister_id, "bench_registry_encoding", ()).unwrap();
+    println!("{:#?}", benchmark);
+
+    assert!(benchmark.cbor.entry_bytes < benchmark.candid.entry_bytes);
+    assert!(benchmark.cbor.lookup_instructions < benchmark.candid.lookup_instructions);
+    assert!(benchmark.cbor.listing_instructions < benchmark.candid.listing_instructions);
+}
+
+#[test]
+fn should_pause_features_and_keep_config_across_upgrades() {
+    let env = deploy();
+
+    let ali = Principal::from_slice(&[0x01]);
+
+    let toggles = FeatureToggles {
+        submissions: false,
+        reports: false,
+        ..FeatureToggles::default()
+    };
+    let (result,) = update_candid_as::<_, (Result<(), StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        env.deployer,
+        "set_feature_toggles",
+        (toggles,),
+    )
+    .unwrap();
+    result.unwrap();
+    let (result,) = update_candid_as::<_, (Result<(), StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        env.deployer,
+        "set_review_quorum",
+        (2u32,),
+    )
+    .unwrap();
+    result.unwrap();
+
+    // Paused submissions are rejected before the message is executed
+    let canister_id = create_project_canister(&env, ali);
+    let result = update_candid_as::<_, (Result<(), StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        ali,
+        "add_registry",
+        (registry_params(canister_id, None),),
+    );
+    assert!(result.is_err());
+    assert!(matches!(
+        report_canister(&env, ali, canister_id),
+        Err(StrikeError::Unavailable { .. })
+    ));
+
+    env.pic
+        .upgrade_canister(
+            env.canister_id,
+            cargo_build_canister("strike_backend"),
+            vec![],
+            Some(env.deployer),
+        )
+        .unwrap();
+
+    let (restored,) = query_candid::<(), (FeatureToggles,)>(&env.pic, env.canister_id, "get_feature_toggles", ()).unwrap();
+    assert_eq!(restored, toggles);
+    let (quorum,) = query_candid::<(), (u32,)>(&env.pic, env.canister_id, "get_review_quorum", ()).unwrap();
+    assert_eq!(quorum, 2);
+}
+
+#[test]
+fn should_export_and_import_snapshot() {
+    let source = deploy();
+
+    let ali = Principal::from_slice(&[0x01]);
+    let bob = Principal::from_slice(&[0x02]);
+
+    let canister_ids: Vec<Principal> = (0..3).map(|_| create_project_canister(&source, ali)).collect();
+    for canister_id in &canister_ids {
+        add_registry(&source, ali, registry_params(*canister_id, None)).unwrap();
+    }
+    update_registry_status(&source, canister_ids[1], StrikeStatus::Trusted).unwrap();
+    grant_role(&source, source.deployer, bob, Role::Reviewer).unwrap();
+
+    let mut manifest = None;
+    let mut chunks = vec![];
+    let mut cursor: Option<SnapshotCursor> = None;
+    loop {
+        let (export,) = query_candid_as::<_, (SnapshotExport,)>(
+            &source.pic,
+            source.canister_id,
+            source.deployer,
+            "export_snapshot",
+            (cursor,),
+        )
+        .unwrap();
+        if let Some(exported) = export.manifest {
+            manifest = Some((exported, export.manifest_hash.unwrap()));
+        }
+        chunks.push(export.chunk);
+        cursor = export.next;
+        if cursor.is_none() {
+            break;
+        }
+    }
+    let (manifest, manifest_hash) = manifest.unwrap();
+    assert_eq!(manifest.registry_count, 3);
+    assert_eq!(manifest.chunk_checksums.len(), chunks.len());
+
+    let target = deploy();
+    for chunk in chunks {
+        let (result,) = update_candid_as::<_, (Result<(), StrikeError>,)>(
+            &target.pic,
+            target.canister_id,
+            target.deployer,
+            "import_snapshot_chunk",
+            (chunk,),
+        )
+        .unwrap();
+        result.unwrap();
+    }
+
+    // A manifest that doesn't hash to the expected value is rejected before anything is written
+    let mut tampered = manifest.clone();
+    tampered.registry_count += 1;
+    let (result,) = update_candid_as::<_, (Result<ImportProgress, StrikeError>,)>(
+        &target.pic,
+        target.canister_id,
+        target.deployer,
+        "finalize_import",
+        (tampered.clone(), manifest_hash.clone()),
+    )
+    .unwrap();
+    assert!(matches!(result, Err(StrikeError::InvalidField { field, .. }) if field == "manifest_hash"));
+    assert!(get_strike(&target, canister_ids[0]).is_none());
+
+    // So is one whose counts differ from the staged chunks, even with a matching hash
+    let tampered_hash = tampered.hash();
+    let (result,) = update_candid_as::<_, (Result<ImportProgress, StrikeError>,)>(
+        &target.pic,
+        target.canister_id,
+        target.deployer,
+        "finalize_import",
+        (tampered, tampered_hash),
+    )
+    .unwrap();
+    assert!(matches!(result, Err(StrikeError::InvalidField { field, .. }) if field == "chunks"));
+    assert!(get_strike(&target, canister_ids[0]).is_none());
+
+    loop {
+        let (result,) = update_candid_as::<_, (Result<ImportProgress, StrikeError>,)>(
+            &target.pic,
+            target.canister_id,
+            target.deployer,
+            "finalize_import",
+            (manifest.clone(), manifest_hash.clone()),
+        )
+        .unwrap();
+        let progress = result.unwrap();
+        if progress.committed_chunks == progress.total_chunks {
+            break;
+        }
+    }
+
+    let trusted = get_registries(
+        &target,
+        GetRegistriesParams {
+            status: Some(StrikeStatus::Trusted),
+            pagination: CursorPagination {
+                start_after: None,
+                limit: 10,
+            },
+            order: None,
+        },
+    );
+    assert_eq!(trusted.total, 1);
+    assert_eq!(trusted.items[0].canister_id, canister_ids[1]);
+    assert_eq!(get_strike(&target, canister_ids[2]).unwrap().status, StrikeStatus::Submitted);
+
+    let (roles,) =
+        query_candid_as::<_, (Vec<RoleAssignment>,)>(&target.pic, target.canister_id, target.deployer, "list_roles", ())
+            .unwrap();
+    assert!(roles.contains(&RoleAssignment {
+        principal: bob,
+        role: Role::Reviewer
+    }));
+}
+
+#[test]
+fn should_look_up_strikes_in_batches() {
+    let env = deploy();
+
+    let ali = Principal::from_slice(&[0x01]);
+
+    let trusted = create_project_canister(&env, ali);
+    let submitted = create_project_canister(&env, ali);
+    for canister_id in [trusted, submitted] {
+        add_registry(&env, ali, registry_params(canister_id, None)).unwrap();
+    }
+    update_registry_status(&env, trusted, StrikeStatus::Trusted).unwrap();
+    let unknown = Principal::from_slice(&[0xff; 10]);
+
+    let canister_ids = vec![unknown, trusted, submitted];
+    for method in ["get_strikes_by_canister_ids", "get_strikes_by_canister_ids_composite"] {
+        let (result,) = query_candid::<_, (Result<Vec<StrikeSummary>, StrikeError>,)>(
+            &env.pic,
+            env.canister_id,
+            method,
+            (canister_ids.clone(),),
+        )
+        .unwrap();
+        let summaries = result.unwrap();
+
+        let statuses: Vec<Option<StrikeStatus>> = summaries.iter().map(|summary| summary.status).collect();
+        assert_eq!(
+            statuses,
+            vec![None, Some(StrikeStatus::Trusted), Some(StrikeStatus::Submitted)]
+        );
+        assert_eq!(summaries[1].canister_id, trusted);
+        assert_eq!(summaries[1].module_hash, Some(hex::encode(Sha256::digest(PROJECT_WASM))));
+    }
+
+    let (result,) = update_candid_as::<_, (Result<(), StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        env.deployer,
+        "set_max_batch_lookup",
+        (2u32,),
+    )
+    .unwrap();
+    result.unwrap();
+
+    let (result,) = query_candid::<_, (Result<Vec<StrikeSummary>, StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        "get_strikes_by_canister_ids",
+        (canister_ids,),
+    )
+    .unwrap();
+    assert!(matches!(result, Err(StrikeError::InvalidField { field, .. }) if field == "canister_ids"));
+}
+
+#[test]
+fn should_list_own_registrations_with_review_notes() {
+    let env = deploy();
+
+    let ali = Principal::from_slice(&[0x01]);
+    let bob = Principal::from_slice(&[0x02]);
+    let carol = Principal::from_slice(&[0x03]);
+
+    grant_role(&env, env.deployer, bob, Role::Reviewer).unwrap();
+    let (result,) = update_candid_as::<_, (Result<(), StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        env.deployer,
+        "set_review_quorum",
+        (2u32,),
+    )
+    .unwrap();
+    result.unwrap();
+
+    let first = create_project_canister(&env, ali);
+    let second = create_project_canister(&env, ali);
+    let third = create_project_canister(&env, ali);
+    for canister_id in [first, second, third] {
+        add_registry(&env, ali, registry_params(canister_id, None)).unwrap();
+        env.pic.advance_time(Duration::from_secs(1));
+    }
+    let other = create_project_canister(&env, carol);
+    add_registry(&env, carol, registry_params(other, None)).unwrap();
+
+    let (result,) = update_candid_as::<_, (Result<(), StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        bob,
+        "update_registry_status",
+        (UpdateRegistryStatusParams {
+            canister_id: second,
+            status: StrikeStatus::Blocked,
+            reason: Some("The website doesn't match the canister".to_string()),
+        },),
+    )
+    .unwrap();
+    result.unwrap();
+
+    let get_my_registrations = |start_after: Option<String>| {
+        let (result,) = query_candid_as::<_, (Result<PaginatedResponse<MyRegistration>, StrikeError>,)>(
+            &env.pic,
+            env.canister_id,
+            ali,
+            "get_my_registrations",
+            (CursorPagination { start_after, limit: 2 },),
+        )
+        .unwrap();
+        result.unwrap()
+    };
+
+    // Newest first, without the entries of other submitters
+    let page = get_my_registrations(None);
+    assert_eq!(page.total, 3);
+    let canister_ids: Vec<Principal> = page.items.iter().map(|item| item.registry.canister_id).collect();
+    assert_eq!(canister_ids, vec![third, second]);
+
+    // Submitters see their contact details and the notes of the reviewers
+    assert_eq!(page.items[1].registry.email, "ali@example.com");
+    let notes = &page.items[1].review_notes;
+    assert_eq!(notes.len(), 1);
+    assert_eq!(notes[0].status, StrikeStatus::Blocked);
+    assert_eq!(notes[0].reason, "The website doesn't match the canister");
+
+    let page = get_my_registrations(page.next_cursor);
+    let canister_ids: Vec<Principal> = page.items.iter().map(|item| item.registry.canister_id).collect();
+    assert_eq!(canister_ids, vec![first]);
+    assert_eq!(page.next_cursor, None);
+
+    let result = query_candid_as::<_, (Result<PaginatedResponse<MyRegistration>, StrikeError>,)>(
+        &env.pic,
+        env.canister_id,
+        Principal::anonymous(),
+        "get_my_registrations",
+        (CursorPagination {
+            start_after: None,
+            limit: 2,
+        },),
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn should_publish_moderation_stats() {
+    let env = deploy();
+
+    let ali = Principal::from_slice(&[0x01]);
+
+    let trusted = create_project_canister(&env, ali);
+    let blocked = create_project_canister(&env, ali);
+    let submitted = create_project_canister(&env, ali);
+    for canister_id in [trusted, blocked, submitted] {
+        add_registry(&env, ali, registry_params(canister_id, None)).unwrap();
+    }
+
+    env.pic.advance_time(Duration::from_secs(90 * 60));
+    update_registry_status(&env, trusted, StrikeStatus::Trusted).unwrap();
+    env.pic.advance_time(Duration::from_secs(2 * 60 * 60));
+    update_registry_status(&env, blocked, StrikeStatus::Blocked).unwrap();
+
+    let get_stats = || {
+        let (stats,) =
+            query_candid_as::<_, (RegistryStats,)>(&env.pic, env.canister_id, Principal::anonymous(), "get_stats", ()).unwrap();
+        stats
+    };
+
+    let stats = get_stats();
+    assert_eq!(
+        stats.status_counts,
+        vec![
+            StatusCount {
+                status: StrikeStatus::Submitted,
+                count: 1
+            },
+            StatusCount {
+                status: StrikeStatus::Trusted,
+                count: 1
+            },
+            StatusCount {
+                status: StrikeStatus::Blocked,
+                count: 1
+            },
+        ]
+    );
+    assert_eq!(stats.daily.len() as u64, STATS_WINDOW_DAYS);
+    let totals = stats.daily.iter().fold((0, 0, 0), |(submissions, approvals, blocks), day| {
+        (submissions + day.submissions, approvals + day.approvals, blocks + day.blocks)
+    });
+    assert_eq!(totals, (3, 1, 1));
+    assert_eq!(stats.reviewed, 2);
+    // An hour and a half and three and a half hours, rounded down to the hour
+    assert_eq!(stats.median_review_time_secs, Some(60 * 60));
+
+    // The counters are kept in stable memory
+    env.pic
+        .upgrade_canister(
+            env.canister_id,
+            cargo_build_canister("strike_backend"),
+            vec![],
+            Some(env.deployer),
+        )
+        .unwrap();
+    assert_eq!(get_stats(), stats);
+}
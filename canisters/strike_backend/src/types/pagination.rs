@@ -1,31 +1,43 @@
-extern crate alloc;
-
-use std::iter::{Skip, Take};
-
 use candid::CandidType;
 use serde::{Deserialize, Serialize};
 
+/// The largest page returned by cursor based listings
+pub const MAX_PAGE_LIMIT: u32 = 100;
+
 #[derive(Clone, Debug, Deserialize, CandidType)]
-pub struct Pagination {
-    pub page: u32,
-    #[serde(rename = "pageSize")]
-    pub page_size: u32,
+pub struct CursorPagination {
+    /// The `next_cursor` of the previous page, or none for the first page
+    pub start_after: Option<String>,
+    pub limit: u32,
 }
 
-#[derive(Clone, Debug, Serialize, CandidType)]
+impl CursorPagination {
+    pub fn limit(&self) -> usize {
+        self.limit.min(MAX_PAGE_LIMIT) as usize
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, CandidType, PartialEq)]
+pub enum SortOrder {
+    CreatedAtAsc,
+    CreatedAtDesc,
+    ProjectNameAsc,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, CandidType)]
 pub struct PaginatedResponse<T> {
     pub items: Vec<T>,
     pub total: u32,
+    /// Cursor of the following page for cursor based listings, none on the last page
+    pub next_cursor: Option<String>,
 }
 
-pub trait Paginate: Iterator {
-    fn paginate(self, params: Pagination) -> Take<Skip<Self>>
-    where
-        Self: Sized,
-    {
-        self.skip((params.page.saturating_sub(1) * params.page_size) as usize)
-            .take(params.page_size as usize)
+impl<T> PaginatedResponse<T> {
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> PaginatedResponse<U> {
+        PaginatedResponse {
+            items: self.items.into_iter().map(f).collect(),
+            total: self.total,
+            next_cursor: self.next_cursor,
+        }
     }
 }
-
-impl<T> Paginate for T where T: Iterator + ?Sized {}
@@ -0,0 +1,250 @@
+use crate::error::StrikeError;
+
+// The format characters of Unicode 15.1, general category Cf. They're invisible and are used to make
+// a name render as something else, e.g. bidirectional overrides, or to make lookalikes of a name
+// compare unequal, e.g. zero width spaces.
+const FORMAT_CHARACTERS: [(char, char); 21] = [
+    ('\u{00AD}', '\u{00AD}'),
+    ('\u{0600}', '\u{0605}'),
+    ('\u{061C}', '\u{061C}'),
+    ('\u{06DD}', '\u{06DD}'),
+    ('\u{070F}', '\u{070F}'),
+    ('\u{0890}', '\u{0891}'),
+    ('\u{08E2}', '\u{08E2}'),
+    ('\u{180E}', '\u{180E}'),
+    ('\u{200B}', '\u{200F}'),
+    ('\u{202A}', '\u{202E}'),
+    ('\u{2060}', '\u{2064}'),
+    ('\u{2066}', '\u{206F}'),
+    ('\u{FEFF}', '\u{FEFF}'),
+    ('\u{FFF9}', '\u{FFFB}'),
+    ('\u{110BD}', '\u{110BD}'),
+    ('\u{110CD}', '\u{110CD}'),
+    ('\u{13430}', '\u{1343F}'),
+    ('\u{1BCA0}', '\u{1BCA3}'),
+    ('\u{1D173}', '\u{1D17A}'),
+    ('\u{E0001}', '\u{E0001}'),
+    ('\u{E0020}', '\u{E007F}'),
+];
+
+const MAX_EMAIL_LOCAL_LEN: usize = 64;
+const MAX_LABEL_LEN: usize = 63;
+
+fn is_spoofing(c: char) -> bool {
+    c.is_control() || FORMAT_CHARACTERS.iter().any(|(first, last)| (*first..=*last).contains(&c))
+}
+
+fn check_characters(field: &str, value: &str, allow_line_breaks: bool) -> Result<(), StrikeError> {
+    let rejected = value
+        .chars()
+        .any(|c| is_spoofing(c) && !(allow_line_breaks && (c == '\n' || c == '\t')));
+    if rejected {
+        return Err(StrikeError::invalid_field(
+            field,
+            "cannot contain control or invisible formatting characters",
+        ));
+    }
+    Ok(())
+}
+
+/// A single line of text such as a name, trimmed
+pub fn text(field: &str, value: &str) -> Result<String, StrikeError> {
+    check_characters(field, value, false)?;
+
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(StrikeError::empty(field));
+    }
+    Ok(value.to_string())
+}
+
+/// Text that may span several lines, trimmed
+pub fn multiline_text(field: &str, value: &str) -> Result<String, StrikeError> {
+    check_characters(field, value, true)?;
+
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(StrikeError::empty(field));
+    }
+    Ok(value.to_string())
+}
+
+fn is_hostname(host: &str) -> bool {
+    let labels: Vec<&str> = host.split('.').collect();
+    let valid_labels = labels.iter().all(|label| {
+        !label.is_empty()
+            && label.len() <= MAX_LABEL_LEN
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    });
+
+    // At least a name and a top level domain, which is never numeric
+    let tld = labels.last().copied().unwrap_or_default();
+    valid_labels && labels.len() >= 2 && tld.chars().any(|c| c.is_ascii_alphabetic())
+}
+
+pub fn email(field: &str, value: &str) -> Result<String, StrikeError> {
+    let value = text(field, value)?;
+    let invalid = || StrikeError::invalid_field(field, "is not a valid email address");
+
+    let (local, domain) = value.rsplit_once('@').ok_or_else(invalid)?;
+    let valid_local = !local.is_empty()
+        && local.len() <= MAX_EMAIL_LOCAL_LEN
+        && !local.starts_with('.')
+        && !local.ends_with('.')
+        && !local.contains("..")
+        && local
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-/=?^_`{|}~.".contains(c));
+    if !valid_local || !is_hostname(domain) {
+        return Err(invalid());
+    }
+
+    Ok(format!("{}@{}", local, domain.to_ascii_lowercase()))
+}
+
+/// An http(s) URL with a host. Credentials are rejected, `https://trusted.com@evil.com` goes to evil.com.
+pub fn website_url(field: &str, value: &str) -> Result<String, StrikeError> {
+    let value = text(field, value)?;
+    let invalid = |reason: &str| StrikeError::invalid_field(field, reason);
+
+    if value.chars().any(char::is_whitespace) {
+        return Err(invalid("cannot contain whitespace"));
+    }
+
+    let lower = value.to_ascii_lowercase();
+    let rest = ["https://", "http://"]
+        .iter()
+        .find_map(|scheme| lower.starts_with(scheme).then(|| &value[scheme.len()..]))
+        .ok_or_else(|| invalid("must be an http or https URL"))?;
+
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    if authority.contains('@') {
+        return Err(invalid("cannot contain credentials"));
+    }
+
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, Some(port)),
+        None => (authority, None),
+    };
+    if port.map_or(false, |port| port.parse::<u16>().is_err()) {
+        return Err(invalid("has an invalid port"));
+    }
+    if !is_hostname(host) {
+        return Err(invalid("must have a valid host"));
+    }
+
+    Ok(value)
+}
+
+/// Reduces a handle given as `@name`, `name` or a profile link on one of `domains` to `@name`, in
+/// lowercase since the handles of both Telegram and Twitter are case insensitive
+fn handle(field: &str, value: &str, domains: &[&str], min_len: usize, max_len: usize) -> Result<String, StrikeError> {
+    let value = text(field, value)?;
+    let lower = value.to_ascii_lowercase();
+
+    let without_scheme = lower
+        .strip_prefix("https://")
+        .or_else(|| lower.strip_prefix("http://"))
+        .unwrap_or(&lower);
+    let without_www = without_scheme.strip_prefix("www.").unwrap_or(without_scheme);
+    let name = domains
+        .iter()
+        .find_map(|domain| without_www.strip_prefix(domain).and_then(|path| path.strip_prefix('/')))
+        .map(|path| path.split(['/', '?', '#']).next().unwrap_or_default())
+        .unwrap_or(without_www);
+    let name = name.strip_prefix('@').unwrap_or(name);
+
+    let valid = (min_len..=max_len).contains(&name.len()) && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(StrikeError::invalid_field(
+            field,
+            format!(
+                "must be a handle of {} to {} letters, digits or underscores",
+                min_len, max_len
+            ),
+        ));
+    }
+
+    Ok(format!("@{}", name))
+}
+
+pub fn telegram(field: &str, value: &str) -> Result<String, StrikeError> {
+    handle(field, value, &["t.me", "telegram.me"], 5, 32)
+}
+
+pub fn twitter(field: &str, value: &str) -> Result<String, StrikeError> {
+    handle(field, value, &["twitter.com", "x.com"], 1, 15)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_rejects_spoofing() {
+        assert_eq!(text("name", "  Strike "), Ok("Strike".to_string()));
+        assert!(text("name", "Strike\u{202E}kirtS").is_err());
+        for invisible in ['\u{00AD}', '\u{200B}', '\u{200D}', '\u{2060}', '\u{FEFF}', '\u{E0041}'] {
+            assert!(text("name", &format!("Str{}ike", invisible)).is_err());
+        }
+        // Visible characters next to the ranges are fine
+        assert!(text("name", "Strike\u{2010}\u{2070}").is_ok());
+        assert!(text("name", "Strike\0").is_err());
+        assert!(text("name", "Strike\nWallet").is_err());
+        assert_eq!(multiline_text("description", "A\nwallet"), Ok("A\nwallet".to_string()));
+        assert_eq!(text("name", " "), Err(StrikeError::empty("name")));
+    }
+
+    #[test]
+    fn test_email() {
+        assert_eq!(email("email", "ali@Example.COM"), Ok("ali@example.com".to_string()));
+        assert!(email("email", "ali.b+strike@mail.example.io").is_ok());
+        for invalid in [
+            "ali",
+            "ali@",
+            "@example.com",
+            "ali@example",
+            "ali..b@example.com",
+            "ali b@example.com",
+        ] {
+            assert!(email("email", invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_website_url() {
+        assert!(website_url("website_url", "https://example.com").is_ok());
+        assert!(website_url("website_url", "http://app.example.com:8080/path?q=1").is_ok());
+        for invalid in [
+            "example.com",
+            "ftp://example.com",
+            "https://",
+            "https://localhost",
+            "https://trusted.com@evil.com",
+            "https://example.com:99999",
+            "https://exa mple.com",
+        ] {
+            assert!(website_url("website_url", invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_handles_are_normalized() {
+        for value in ["@Strike_Bot", "strike_bot", "https://t.me/strike_bot", "t.me/Strike_Bot"] {
+            assert_eq!(telegram("telegram", value), Ok("@strike_bot".to_string()));
+        }
+        assert!(telegram("telegram", "@bot").is_err());
+
+        for value in [
+            "@Strike",
+            "https://twitter.com/strike",
+            "https://x.com/Strike/status/1",
+            "www.x.com/strike",
+        ] {
+            assert_eq!(twitter("twitter", value), Ok("@strike".to_string()));
+        }
+        assert!(twitter("twitter", "@strike-wallet").is_err());
+    }
+}
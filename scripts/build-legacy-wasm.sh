@@ -0,0 +1,25 @@
+#!/bin/bash
+
+# Builds the backend of the first release, whose registry entries predate versioning. The
+# PocketIC tests upgrade it to the current code to check the migration.
+cd canisters/strike_backend
+
+if [ -f "strike_backend-legacy.wasm" ]; then
+    cd ../..
+    exit 0
+fi
+
+# The full SHA, a short one can become ambiguous as the history grows
+LEGACY_COMMIT="f114ee7ee510fc04bdcf1ad04bfdb39b0555d461"
+WORKTREE=$(mktemp -d)
+
+echo "Legacy backend build starting"
+git worktree add --detach "$WORKTREE" "$LEGACY_COMMIT" || exit 1
+# Resolves the dependencies the releases share to the same versions as the current build
+cp ../../Cargo.lock "$WORKTREE/" 2>/dev/null
+cargo build --manifest-path "$WORKTREE/canisters/strike_backend/Cargo.toml" --target wasm32-unknown-unknown \
+    --release --target-dir "$WORKTREE/target" || { git worktree remove --force "$WORKTREE"; exit 1; }
+cp "$WORKTREE/target/wasm32-unknown-unknown/release/strike_backend.wasm" strike_backend-legacy.wasm
+git worktree remove --force "$WORKTREE"
+echo "Legacy backend build completed"
+cd ../..
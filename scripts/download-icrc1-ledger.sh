@@ -0,0 +1,32 @@
+#!/bin/bash
+
+# The ICRC-1 ledger the PocketIC tests deploy as the ledger of the submission deposits
+cd canisters/strike_backend
+
+LEDGER_SUITE_RELEASE="ledger-suite-icrc-2024-09-13"
+# The sha256 of ic-icrc1-ledger.wasm.gz in that release, update both together
+LEDGER_WASM_SHA256=""
+
+if [ -z "$LEDGER_WASM_SHA256" ]; then
+    echo "LEDGER_WASM_SHA256 isn't set for ${LEDGER_SUITE_RELEASE}"
+    exit 1
+fi
+
+# A cached download is checked as well, it may predate a version bump
+if [ -f "ic-icrc1-ledger.wasm.gz" ]; then
+    echo "${LEDGER_WASM_SHA256}  ic-icrc1-ledger.wasm.gz" | sha256sum -c --quiet - || exit 1
+    cd ../..
+    exit 0
+fi
+
+echo "ICRC-1 ledger download starting"
+curl -fLs https://github.com/dfinity/ic/releases/download/${LEDGER_SUITE_RELEASE}/ic-icrc1-ledger.wasm.gz \
+    -o ic-icrc1-ledger.wasm.gz.part || { rm -f ic-icrc1-ledger.wasm.gz.part; exit 1; }
+if ! echo "${LEDGER_WASM_SHA256}  ic-icrc1-ledger.wasm.gz.part" | sha256sum -c --quiet -; then
+    echo "ic-icrc1-ledger.wasm.gz doesn't match the pinned sha256"
+    rm -f ic-icrc1-ledger.wasm.gz.part
+    exit 1
+fi
+mv ic-icrc1-ledger.wasm.gz.part ic-icrc1-ledger.wasm.gz
+echo "ICRC-1 ledger download completed"
+cd ../..